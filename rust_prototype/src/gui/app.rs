@@ -2,18 +2,36 @@ use eframe::egui;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::thread;
 use crate::legion_core;
+use crate::legion_core::device::database::KeyboardBacklight;
+use crate::legion_core::capability::{Capability, CapabilityReport, CapabilityStatus};
+use crate::legion_core::device::models::LaptopModel;
+use crate::legion_core::hw::battery::ChargeMode;
+use crate::legion_core::hw::lighting::{Brightness, KeyboardLighting, LightingEffect, Rgb, WaveDirection};
+use crate::legion_core::result::{HardwareReadResult, WriteError, WriteOutcome};
+use crate::legion_core::safety::guards::WriteToken;
+use crate::legion_core::safety::journal::JournalEntry;
+use crate::legion_core::safety::undo::UndoEntry;
+use crate::legion_core::snapshot::HardwareStateSnapshot;
 
 pub struct LegionControlApp {
-    device_name: String,
-    bios_version: String,
-    supported: bool,
-    battery_percent: Option<u16>,
-    battery_charging: Option<bool>,
-    conservation_mode: Option<bool>,
-    rapid_charge: Option<bool>,
-    power_profile: Option<legion_core::hw::power::PowerProfile>,
+    /// Last hardware read; None until the first refresh completes.
+    snapshot: Option<HardwareStateSnapshot>,
+    available_profiles: Vec<legion_core::hw::power::PowerProfile>,
+    capability_report: CapabilityReport,
+    /// Write journal as of the last refresh, oldest first.
+    journal: Vec<JournalEntry>,
+    /// Change the Undo button would revert.
+    last_change: Option<UndoEntry>,
+    /// Keyboard lighting being edited, and the state last sent or read back; a change is
+    /// sent as a whole once the mouse is released (not on every frame of a color drag).
+    lighting: KeyboardLighting,
+    lighting_sent: KeyboardLighting,
+    /// `lighting_sent` from before the write in progress, restored if that write fails.
+    lighting_before_write: Option<KeyboardLighting>,
     status_message: String,
     last_error: Option<String>,
+    /// Last write was accepted but not confirmed by readback.
+    last_unverified: Option<String>,
     show_sensitive: bool,  // Privacy: hide device ID/BIOS by default
     
     // Threading
@@ -27,29 +45,29 @@ enum GuiUpdate {
     StateRefreshed(Box<DeviceState>),
     Error(String),
     ActionComplete(String),
+    /// The write went through but could not be read back.
+    ActionUnverified(String),
 }
 
 #[derive(Debug, Clone)]
 struct DeviceState {
-    device_name: String,
-    bios_version: String,
-    supported: bool,
-    battery_percent: Option<u16>,
-    battery_charging: Option<bool>,
-    conservation_mode: Option<bool>,
-    rapid_charge: Option<bool>,
-    power_profile: Option<legion_core::hw::power::PowerProfile>,
+    snapshot: HardwareStateSnapshot,
+    available_profiles: Vec<legion_core::hw::power::PowerProfile>,
+    capability_report: CapabilityReport,
+    journal: Vec<JournalEntry>,
+    last_change: Option<UndoEntry>,
 }
 
 #[derive(Debug)]
 enum GuiAction {
     Refresh,
-    SetConservation(bool),
-    SetRapidCharge(bool),
-    SetProfile(legion_core::hw::power::PowerProfile),
+    // Writes carry the value the UI showed, so a stale view is refused as a Conflict.
+    SetConservation(bool, Option<ChargeMode>),
+    SetRapidCharge(bool, Option<ChargeMode>),
+    SetProfile(legion_core::hw::power::PowerProfile, Option<legion_core::hw::power::PowerProfile>),
     SetLightingOwner(bool),
-    SetBrightness(u8),
-    SetStaticColor(u8, u8, u8),
+    SetLighting(KeyboardLighting, Option<KeyboardLighting>),
+    Undo,
 }
 
 impl Default for LegionControlApp {
@@ -61,90 +79,79 @@ impl Default for LegionControlApp {
         
         // Spawn Background Worker Thread (MTA)
         thread::spawn(move || {
-            loop {
-                match rx_action.recv() {
-                    Ok(action) => {
-                        match action {
-                            GuiAction::Refresh => {
-                                let state = perform_refresh();
-                                let _ = tx_scan.send(GuiUpdate::StateRefreshed(Box::new(state)));
-                            },
-                            GuiAction::SetConservation(enable) => {
-                                // Write with global lock
-                                legion_core::safety::guards::GlobalWriteLock::request_write_access();
-                                match legion_core::hw::battery::set_conservation_mode(enable) {
-                                    Ok(_) => { let _ = tx_scan.send(GuiUpdate::ActionComplete(format!("Conservation Mode: {}", if enable { "ON" } else { "OFF" }))); },
-                                    Err(e) => { let _ = tx_scan.send(GuiUpdate::Error(format!("Failed to set Conservation Mode: {}", e))); }
-                                }
-                                // Auto-refresh after write
-                                let state = perform_refresh();
-                                let _ = tx_scan.send(GuiUpdate::StateRefreshed(Box::new(state)));
-                            },
-                            GuiAction::SetRapidCharge(enable) => {
-                                legion_core::safety::guards::GlobalWriteLock::request_write_access();
-                                match legion_core::hw::battery::set_rapid_charge(enable) {
-                                    Ok(_) => { let _ = tx_scan.send(GuiUpdate::ActionComplete(format!("Rapid Charge: {}", if enable { "ON" } else { "OFF" }))); },
-                                    Err(e) => { let _ = tx_scan.send(GuiUpdate::Error(format!("Failed to set Rapid Charge: {}", e))); }
-                                }
-                                let state = perform_refresh();
-                                let _ = tx_scan.send(GuiUpdate::StateRefreshed(Box::new(state)));
-                            },
-                            GuiAction::SetProfile(p) => {
-                                legion_core::safety::guards::GlobalWriteLock::request_write_access();
-                                match legion_core::hw::power::set_power_profile(p) {
-                                    Ok(_) => { let _ = tx_scan.send(GuiUpdate::ActionComplete(format!("Profile set to {:?}", p))); },
-                                    Err(e) => { let _ = tx_scan.send(GuiUpdate::Error(format!("Failed to set Profile: {}", e))); }
-                                }
-                                let state = perform_refresh();
-                                let _ = tx_scan.send(GuiUpdate::StateRefreshed(Box::new(state)));
-                            },
-                             GuiAction::SetLightingOwner(enable) => {
-                                // WMI Call
-                                let wmi = match crate::platform::windows::WmiQueryHandler::new() {
-                                    Ok(w) => w,
-                                    Err(e) => { let _ = tx_scan.send(GuiUpdate::Error(format!("Failed to init WMI: {}", e))); return; }
-                                };
-                                match wmi.set_light_control_owner(enable) {
-                                    Ok(_) => { let _ = tx_scan.send(GuiUpdate::ActionComplete(format!("Lighting Control: {}", if enable { "APP" } else { "FIRMWARE" }))); },
-                                    Err(e) => { let _ = tx_scan.send(GuiUpdate::Error(format!("Failed to set ownership: {}", e))); }
-                                }
-                            },
-                            GuiAction::SetBrightness(level) => {
-                                // HID Call
-                                legion_core::safety::guards::GlobalWriteLock::request_write_access();
-                                let lc = legion_core::hw::lighting::LightingController::new();
-                                match lc.set_brightness(level) {
-                                    Ok(_) => { let _ = tx_scan.send(GuiUpdate::ActionComplete(format!("Brightness set to {}", level))); },
-                                    Err(e) => { let _ = tx_scan.send(GuiUpdate::Error(format!("Lighting Error: {}", e))); }
-                                }
-                            },
-                            GuiAction::SetStaticColor(r, g, b) => {
-                                // HID Call
-                                legion_core::safety::guards::GlobalWriteLock::request_write_access();
-                                let lc = legion_core::hw::lighting::LightingController::new();
-                                match lc.set_static_color(r, g, b) {
-                                    Ok(_) => { let _ = tx_scan.send(GuiUpdate::ActionComplete("Static Color Applied".to_string())); },
-                                    Err(e) => { let _ = tx_scan.send(GuiUpdate::Error(format!("Lighting Error: {}", e))); }
-                                }
-                            }
-                        }
+            // Runs until the GUI drops its sender (channel closed)
+            while let Ok(action) = rx_action.recv() {
+                match action {
+                    GuiAction::Refresh => {
+                        let state = perform_refresh();
+                        let _ = tx_scan.send(GuiUpdate::StateRefreshed(Box::new(state)));
                     },
-                    Err(_) => break, // Channel closed
+                    GuiAction::SetConservation(enable, expected) => {
+                        // One write token per action, revoked when it goes out of scope
+                        let value = if enable { "ON" } else { "OFF" };
+                        let token = WriteToken::grant(legion_core::hw::battery::CHARGE_MODE_OPERATION, format!("Conservation {}", value), "GUI Conservation Mode checkbox");
+                        send_outcome(&tx_scan, "Conservation Mode", value,
+                            legion_core::hw::battery::set_conservation_mode(enable, expected, &token));
+                        drop(token);
+                        // Auto-refresh after write
+                        let state = perform_refresh();
+                        let _ = tx_scan.send(GuiUpdate::StateRefreshed(Box::new(state)));
+                    },
+                    GuiAction::SetRapidCharge(enable, expected) => {
+                        let value = if enable { "ON" } else { "OFF" };
+                        let token = WriteToken::grant(legion_core::hw::battery::CHARGE_MODE_OPERATION, format!("Rapid Charge {}", value), "GUI Rapid Charge checkbox");
+                        send_outcome(&tx_scan, "Rapid Charge", value,
+                            legion_core::hw::battery::set_rapid_charge(enable, expected, &token));
+                        drop(token);
+                        let state = perform_refresh();
+                        let _ = tx_scan.send(GuiUpdate::StateRefreshed(Box::new(state)));
+                    },
+                    GuiAction::SetProfile(p, expected) => {
+                        let token = WriteToken::grant(legion_core::hw::power::POWER_PROFILE_OPERATION, format!("{:?}", p), "GUI profile button");
+                        send_outcome(&tx_scan, "Profile", &format!("{:?}", p),
+                            legion_core::hw::power::set_power_profile(p, expected, &token));
+                        drop(token);
+                        let state = perform_refresh();
+                        let _ = tx_scan.send(GuiUpdate::StateRefreshed(Box::new(state)));
+                    },
+                     GuiAction::SetLightingOwner(enable) => {
+                        let token = WriteToken::grant(legion_core::hw::lighting::LIGHTING_OWNER_OPERATION, if enable { "APP" } else { "FIRMWARE" }, "GUI lighting control");
+                        send_outcome(&tx_scan, "Lighting Control", if enable { "APP" } else { "FIRMWARE" },
+                            legion_core::hw::lighting::set_light_control_owner(enable, &token));
+                    },
+                    GuiAction::SetLighting(lighting, expected) => {
+                        // HID Call
+                        let token = WriteToken::grant(legion_core::hw::lighting::LIGHTING_OPERATION, lighting, "GUI keyboard lighting");
+                        let lc = legion_core::hw::lighting::LightingController::new();
+                        send_outcome(&tx_scan, "Keyboard Lighting", &lighting.to_string(), lc.apply(&lighting, expected.as_ref(), &token));
+                        drop(token);
+                        let state = perform_refresh();
+                        let _ = tx_scan.send(GuiUpdate::StateRefreshed(Box::new(state)));
+                    },
+                    GuiAction::Undo => {
+                        match legion_core::safety::undo::active().and_then(|h| h.undo_last(legion_core::backend::active(), "GUI Undo button")) {
+                            Some((entry, result)) => send_outcome(&tx_scan, &format!("Undo {}", entry.change.operation()), &entry.change.restored_value(), result),
+                            None => { let _ = tx_scan.send(GuiUpdate::ActionComplete("Nothing to undo".to_string())); }
+                        }
+                        let state = perform_refresh();
+                        let _ = tx_scan.send(GuiUpdate::StateRefreshed(Box::new(state)));
+                    }
                 }
             }
         });
         
         Self {
-            device_name: "Detecting...".to_string(),
-            bios_version: "".to_string(),
-            supported: false,
-            battery_percent: None,
-            battery_charging: None,
-            conservation_mode: None,
-            rapid_charge: None,
-            power_profile: None,
+            snapshot: None,
+            available_profiles: Vec::new(),
+            capability_report: CapabilityReport::default(),
+            journal: Vec::new(),
+            last_change: None,
+            lighting: KeyboardLighting::default(),
+            lighting_sent: KeyboardLighting::default(),
+            lighting_before_write: None,
             status_message: "Initializing...".to_string(),
             last_error: None,
+            last_unverified: None,
             rx,
             tx_action,
             is_busy: false,
@@ -153,45 +160,71 @@ impl Default for LegionControlApp {
     }
 }
 
+/// Reports a write: Verified and Unverified are shown differently, failures as errors.
+fn send_outcome(tx: &Sender<GuiUpdate>, what: &str, value: &str, result: Result<WriteOutcome, WriteError>) {
+    let update = match result {
+        Ok(WriteOutcome::Verified) => GuiUpdate::ActionComplete(format!("{}: {} (verified)", what, value)),
+        Ok(WriteOutcome::Unverified(e)) => GuiUpdate::ActionUnverified(
+            format!("{}: {} was written but could not be read back [{}]", what, value, e.code())),
+        Err(e) => GuiUpdate::Error(format!("Failed to set {} [{}]: {}", what, e.code(), e)),
+    };
+    let _ = tx.send(update);
+}
+
 fn perform_refresh() -> DeviceState {
-    let mut state = DeviceState {
-        device_name: "Unknown".to_string(),
-        bios_version: "".to_string(),
-        supported: false,
-        battery_percent: None,
-        battery_charging: None,
-        conservation_mode: None,
-        rapid_charge: None,
-        power_profile: None,
+    let snapshot = HardwareStateSnapshot::capture();
+    let capability_report = match &snapshot.device {
+        HardwareReadResult::Success(device) => legion_core::capability::probe::probe(device),
+        HardwareReadResult::Failure(_) => CapabilityReport::default(),
     };
-    
-    // Device Detection (WMI)
-    match legion_core::device::detect::detect_device() {
-        Ok(device) => {
-            state.device_name = device.model_name;
-            state.bios_version = device.bios_version;
-            state.supported = device.supported;
-        },
-        Err(e) => {
-            // SHOW THE ERROR in the UI
-            state.device_name = format!("Error: {}", e); 
-            // Also log to console if possible, though user can't see it easily
+
+    // Only offer modes both the firmware and the model database list
+    let available_profiles = match (&snapshot.device, &snapshot.thermal_mode_choices) {
+        (HardwareReadResult::Success(LaptopModel { capabilities: Some(caps), .. }), HardwareReadResult::Success(choices))
+            if capability_report.is_usable(Capability::ThermalMode) => caps.filter_thermal_modes(choices),
+        _ => Vec::new(),
+    };
+
+    let journal = legion_core::safety::journal::active()
+        .and_then(|j| j.entries().ok())
+        .unwrap_or_default();
+
+    let last_change = legion_core::safety::undo::active().and_then(|h| h.last());
+
+    DeviceState { snapshot, available_profiles, capability_report, journal, last_change }
+}
+
+impl LegionControlApp {
+    fn device(&self) -> Option<&LaptopModel> {
+        self.snapshot.as_ref().and_then(|s| s.device.value())
+    }
+
+    fn supported(&self) -> bool {
+        self.device().is_some_and(|d| d.supported)
+    }
+
+    /// A value from the last snapshot, if that read succeeded.
+    fn read<T>(&self, field: impl FnOnce(&HardwareStateSnapshot) -> &HardwareReadResult<T>) -> Option<&T> {
+        self.snapshot.as_ref().and_then(|s| field(s).value())
+    }
+
+    /// A read as display text; failures show their error code rather than a default.
+    fn read_label<T>(&self, field: impl FnOnce(&HardwareStateSnapshot) -> &HardwareReadResult<T>, show: impl FnOnce(&T) -> String) -> String {
+        match self.snapshot.as_ref().map(field) {
+            Some(HardwareReadResult::Success(value)) => show(value),
+            Some(HardwareReadResult::Failure(e)) => format!("Unknown [{}]", e.code()),
+            None => "N/A".to_string(),
         }
     }
-    
-    if let Some(bat) = legion_core::hw::battery::get_battery_status() {
-        state.battery_percent = Some(bat.charge_percent);
-        state.battery_charging = Some(bat.is_charging);
+
+    /// Orange hint under a control whose capability probe came back Degraded.
+    fn degraded_note(&self, ui: &mut egui::Ui, capability: Capability) {
+        if let Some(e) = self.capability_report.evidence(capability).filter(|e| e.status == CapabilityStatus::Degraded) {
+            ui.colored_label(egui::Color32::from_rgb(255, 140, 0),
+                format!("⚠ Degraded: {}", e.detail.as_deref().unwrap_or(&e.evidence_code)));
+        }
     }
-    
-    state.conservation_mode = legion_core::hw::battery::get_conservation_mode();
-    state.rapid_charge = legion_core::hw::battery::get_rapid_charge();
-    state.power_profile = legion_core::hw::power::get_power_profile();
-    
-    state
-}
 
-impl LegionControlApp {
     fn request_refresh(&mut self) {
         if !self.is_busy {
             self.status_message = "Refreshing...".to_string();
@@ -208,36 +241,51 @@ impl eframe::App for LegionControlApp {
             self.is_busy = false;
             match update {
                 GuiUpdate::StateRefreshed(state) => {
-                    self.device_name = state.device_name;
-                    self.bios_version = state.bios_version;
-                    self.supported = state.supported;
-                    self.battery_percent = state.battery_percent;
-                    self.battery_charging = state.battery_charging;
-                    self.conservation_mode = state.conservation_mode;
-                    self.rapid_charge = state.rapid_charge;
-                    self.power_profile = state.power_profile;
-                    
-                    if self.supported {
-                        self.status_message = "Ready".to_string();
-                    } else if self.device_name == "Detection Failed" {
-                        self.status_message = "Error: Device Detection Failed".to_string();
-                        // Keep last error specific if we had one
-                    } else {
-                        self.status_message = "Unsupported Device (Read-Only)".to_string();
+                    let DeviceState { snapshot, available_profiles, capability_report, journal, last_change } = *state;
+                    self.status_message = match &snapshot.device {
+                        HardwareReadResult::Success(device) if device.supported => "Ready".to_string(),
+                        HardwareReadResult::Success(_) => "Unsupported Device (Read-Only)".to_string(),
+                        HardwareReadResult::Failure(e) => format!("Error: Device Detection Failed [{}]", e.code()),
+                    };
+                    // Follow the keyboard unless an edit is still waiting to be sent
+                    if let HardwareReadResult::Success(live) = snapshot.lighting {
+                        if self.lighting == self.lighting_sent {
+                            self.lighting = live;
+                        }
+                        self.lighting_sent = live;
                     }
+                    self.snapshot = Some(snapshot);
+                    self.available_profiles = available_profiles;
+                    self.capability_report = capability_report;
+                    self.journal = journal;
+                    self.last_change = last_change;
                 },
                 GuiUpdate::Error(e) => {
+                    // The lighting write failed: show the state the keyboard still has
+                    if let Some(previous) = self.lighting_before_write.take() {
+                        self.lighting = previous;
+                        self.lighting_sent = previous;
+                    }
                     self.last_error = Some(e);
+                    self.last_unverified = None;
                     self.status_message = "Error".to_string();
                 },
                 GuiUpdate::ActionComplete(msg) => {
+                    self.lighting_before_write = None;
+                    self.last_error = None;
+                    self.last_unverified = None;
                     self.status_message = msg;
+                },
+                GuiUpdate::ActionUnverified(msg) => {
+                    self.lighting_before_write = None;
+                    self.last_error = None;
+                    self.last_unverified = Some(msg);
                 }
             }
         }
     
         // Auto-refresh on start
-        if self.device_name == "Detecting..." && !self.is_busy {
+        if self.snapshot.is_none() && !self.is_busy {
             self.request_refresh();
         }
 
@@ -275,10 +323,15 @@ impl eframe::App for LegionControlApp {
                 });
                 ui.add_space(5.0);
                 
+                let (device_name, bios_version) = match self.snapshot.as_ref().map(|s| &s.device) {
+                    Some(HardwareReadResult::Success(device)) => (device.marketing_model.clone(), device.bios_version.clone()),
+                    Some(HardwareReadResult::Failure(e)) => (format!("Error: {}", e), String::new()),
+                    None => ("Detecting...".to_string(), String::new()),
+                };
                 egui::Grid::new("device_info_grid").striped(true).show(ui, |ui| {
                     ui.label("Model:");
                     if self.show_sensitive {
-                        ui.strong(&self.device_name);
+                        ui.strong(&device_name);
                     } else {
                         // Discord-style gray spoiler
                         let (rect, _) = ui.allocate_exact_size(egui::vec2(80.0, 18.0), egui::Sense::hover());
@@ -288,7 +341,7 @@ impl eframe::App for LegionControlApp {
                     
                     ui.label("BIOS:");
                     if self.show_sensitive {
-                        ui.label(&self.bios_version);
+                        ui.label(&bios_version);
                     } else {
                         // Discord-style gray spoiler
                         let (rect, _) = ui.allocate_exact_size(egui::vec2(80.0, 18.0), egui::Sense::hover());
@@ -297,7 +350,7 @@ impl eframe::App for LegionControlApp {
                     ui.end_row();
                     
                     ui.label("Status:");
-                    if self.supported {
+                    if self.supported() {
                         ui.colored_label(egui::Color32::GREEN, "Supported");
                     } else {
                         ui.colored_label(egui::Color32::from_rgb(255, 140, 0), "Unsupported");
@@ -305,12 +358,33 @@ impl eframe::App for LegionControlApp {
                     ui.end_row();
                     
                     ui.label("Battery:");
-                    if let Some(pct) = self.battery_percent {
-                         let charging_text = if self.battery_charging == Some(true) { "⚡ " } else { "" };
-                         ui.label(format!("{}{}%", charging_text, pct));
-                    } else {
-                        ui.label("N/A");
-                    }
+                    // Show the charging bolt whenever mains power is connected.
+                    ui.label(self.read_label(|s| &s.battery, |bat| {
+                        let charging_text = if bat.is_charging() || bat.ac_online == Some(true) { "⚡ " } else { "" };
+                        format!("{}{}%", charging_text, bat.charge_percent)
+                    }));
+                    ui.end_row();
+
+                    ui.label("CPU / GPU:");
+                    let fmt_temp = |t: Option<f32>| t.map(|c| format!("{:.0}°C", c)).unwrap_or_else(|| "N/A".to_string());
+                    ui.label(self.read_label(|s| &s.temperatures, |temps| format!("{} / {}",
+                        fmt_temp(legion_core::hw::thermal::cpu_temp(temps)), fmt_temp(legion_core::hw::thermal::gpu_temp(temps)))));
+                    ui.end_row();
+
+                    ui.label("Charge Mode:");
+                    ui.label(self.read_label(|s| &s.charge_mode, |mode| mode.to_string()));
+                    ui.end_row();
+
+                    ui.label("Overdrive:");
+                    ui.label(self.read_label(|s| &s.overdrive, |on| if *on { "ON" } else { "OFF" }.to_string()));
+                    ui.end_row();
+
+                    ui.label("GPU Mode:");
+                    ui.label(self.read_label(|s| &s.igpu_mode, |mode| mode.to_string()));
+                    ui.end_row();
+
+                    ui.label("Read at:");
+                    ui.label(self.snapshot.as_ref().map(|s| legion_core::time::format_utc(s.captured_at)).unwrap_or_else(|| "N/A".to_string()));
                     ui.end_row();
                 });
             });
@@ -324,37 +398,55 @@ impl eframe::App for LegionControlApp {
                 ui.add_space(5.0);
 
                 // Interactions disabled if busy
-                ui.set_enabled(!self.is_busy && self.supported);
+                ui.set_enabled(!self.is_busy && self.supported());
 
-                // Conservation Mode
-                ui.horizontal(|ui| {
-                    ui.label("Conservation Mode:");
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                         let current = self.conservation_mode.unwrap_or(false);
-                         let mut val = current;
-                         if ui.checkbox(&mut val, if current { "ON" } else { "OFF" }).clicked() {
-                             let _ = self.tx_action.send(GuiAction::SetConservation(!current));
-                             self.is_busy = true;
-                         }
+                let offers_conservation = self.capability_report.is_usable(Capability::BatteryConservationMode);
+                let offers_rapid = self.capability_report.is_usable(Capability::BatteryRapidCharge);
+
+                if offers_conservation {
+                    // Conservation Mode
+                    ui.horizontal(|ui| {
+                        ui.label("Conservation Mode:");
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                             // An unknown state is shown as such, never as OFF.
+                             match self.read(|s| &s.conservation_mode).copied() {
+                                 Some(current) => {
+                                     let mut val = current;
+                                     if ui.checkbox(&mut val, if current { "ON" } else { "OFF" }).clicked() {
+                                         let _ = self.tx_action.send(GuiAction::SetConservation(!current, self.read(|s| &s.charge_mode).copied()));
+                                         self.is_busy = true;
+                                     }
+                                 }
+                                 None => { ui.label(self.read_label(|s| &s.conservation_mode, |_| String::new())); }
+                             }
+                        });
                     });
-                });
-                ui.small("Limits battery charge to ~60% to extend lifespan.");
+                    ui.small("Limits battery charge to ~60% to extend lifespan.");
+                    self.degraded_note(ui, Capability::BatteryConservationMode);
                 
-                ui.add_space(5.0);
+                    ui.add_space(5.0);
+                }
 
-                // Rapid Charge
-                ui.horizontal(|ui| {
-                    ui.label("Rapid Charge:");
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        let current = self.rapid_charge.unwrap_or(false);
-                        let mut val = current;
-                         if ui.checkbox(&mut val, if current { "ON" } else { "OFF" }).clicked() {
-                             let _ = self.tx_action.send(GuiAction::SetRapidCharge(!current));
-                             self.is_busy = true;
-                         }
+                if offers_rapid {
+                    // Rapid Charge
+                    ui.horizontal(|ui| {
+                        ui.label("Rapid Charge:");
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            match self.read(|s| &s.rapid_charge).copied() {
+                                Some(current) => {
+                                    let mut val = current;
+                                    if ui.checkbox(&mut val, if current { "ON" } else { "OFF" }).clicked() {
+                                        let _ = self.tx_action.send(GuiAction::SetRapidCharge(!current, self.read(|s| &s.charge_mode).copied()));
+                                        self.is_busy = true;
+                                    }
+                                }
+                                None => { ui.label(self.read_label(|s| &s.rapid_charge, |_| String::new())); }
+                            }
+                        });
                     });
-                });
-                ui.small("Charges significantly faster. May generate heat.");
+                    ui.small("Charges significantly faster. May generate heat.");
+                    self.degraded_note(ui, Capability::BatteryRapidCharge);
+                }
 
                 ui.add_space(10.0);
                 ui.separator();
@@ -366,18 +458,22 @@ impl eframe::App for LegionControlApp {
                     let profiles = [
                         ("Quiet", legion_core::hw::power::PowerProfile::Quiet, egui::Color32::from_rgb(100, 149, 237)), // Cornflower Blue
                         ("Balanced", legion_core::hw::power::PowerProfile::Balanced, egui::Color32::WHITE),
+                        ("Bal-Perf", legion_core::hw::power::PowerProfile::BalancedPerformance, egui::Color32::from_rgb(255, 165, 0)), // Orange
                         ("Perf", legion_core::hw::power::PowerProfile::Performance, egui::Color32::from_rgb(220, 20, 60)), // Crimson
+                        ("Custom", legion_core::hw::power::PowerProfile::Custom, egui::Color32::from_rgb(147, 112, 219)), // Medium Purple
                     ];
                     
-                    for (name, profile, color) in profiles {
-                        let is_current = self.power_profile == Some(profile);
+                    // Only offer what the firmware lists
+                    for (name, profile, color) in profiles.into_iter().filter(|(_, p, _)| self.available_profiles.contains(p)) {
+                        let is_current = self.read(|s| &s.thermal_mode) == Some(&profile);
                         // Custom button with color indicator
                         if ui.add(egui::Button::new(egui::RichText::new(name).color(if is_current { color } else { ui.visuals().text_color() })).selected(is_current)).clicked() && !is_current {
-                             let _ = self.tx_action.send(GuiAction::SetProfile(profile));
+                             let _ = self.tx_action.send(GuiAction::SetProfile(profile, self.read(|s| &s.thermal_mode).copied()));
                              self.is_busy = true;
                         }
                     }
                 });
+                self.degraded_note(ui, Capability::ThermalMode);
             });
             
             ui.add_space(10.0);
@@ -388,7 +484,21 @@ impl eframe::App for LegionControlApp {
                 ui.heading("Keyboard Backlight");
                 ui.add_space(5.0);
                 
-                ui.set_enabled(!self.is_busy && self.supported);
+                let backlight = self.device().and_then(|d| d.capabilities.as_ref()).map(|c| c.keyboard_backlight).unwrap_or_default();
+                // Only the ITE 4-zone controller is driven from here
+                let unsupported_note = match backlight {
+                    _ if self.capability_report.is_usable(Capability::FourZoneRgbKeyboard) => None,
+                    KeyboardBacklight::FourZoneRgb => Some("4-zone keyboard not confirmed on this machine."),
+                    KeyboardBacklight::White => Some("White backlight: use Fn + Space."),
+                    KeyboardBacklight::Spectrum => Some("Spectrum per-key RGB is not supported yet."),
+                    KeyboardBacklight::None => Some("No controllable backlight on this model."),
+                };
+                if let Some(note) = unsupported_note {
+                    ui.label(note);
+                    return;
+                }
+
+                ui.set_enabled(!self.is_busy && self.supported());
 
                 // Ownership Toggle
                 if ui.button("Take Control (Enable App Lighting)").clicked() {
@@ -396,51 +506,143 @@ impl eframe::App for LegionControlApp {
                      self.is_busy = true;
                 }
                 ui.small("Must click this once to enable custom effects.");
+                ui.label(format!("Current: {}", self.read_label(|s| &s.lighting, |l| l.to_string())));
                 
                 ui.add_space(5.0);
                 ui.separator();
                 
+                let lighting = &mut self.lighting;
+
                 ui.label("Brightness:");
                 ui.horizontal(|ui| {
-                    if ui.button("OFF").clicked() {
-                        let _ = self.tx_action.send(GuiAction::SetBrightness(0));
-                    }
-                    if ui.button("LOW").clicked() {
-                         let _ = self.tx_action.send(GuiAction::SetBrightness(1));
-                    }
-                    if ui.button("HIGH").clicked() {
-                         let _ = self.tx_action.send(GuiAction::SetBrightness(2));
+                    for (name, brightness) in [("OFF", Brightness::Off), ("LOW", Brightness::Low), ("HIGH", Brightness::High)] {
+                        if ui.selectable_label(lighting.brightness == brightness, name).clicked() {
+                            lighting.brightness = brightness;
+                        }
                     }
                 });
-                
+
                 ui.add_space(5.0);
-                ui.label("Static Color Presets:");
                 ui.horizontal(|ui| {
-                    if ui.button(egui::RichText::new("BLUE").color(egui::Color32::BLUE)).clicked() {
-                         let _ = self.tx_action.send(GuiAction::SetStaticColor(0, 0, 255));
-                    }
-                    if ui.button(egui::RichText::new("WHITE").color(egui::Color32::WHITE)).clicked() {
-                         let _ = self.tx_action.send(GuiAction::SetStaticColor(255, 255, 255));
-                    }
-                    if ui.button(egui::RichText::new("RED").color(egui::Color32::RED)).clicked() {
-                         let _ = self.tx_action.send(GuiAction::SetStaticColor(255, 0, 0));
+                    ui.label("Effect:");
+                    egui::ComboBox::from_id_source("lighting_effect")
+                        .selected_text(lighting.effect.to_string())
+                        .show_ui(ui, |ui| {
+                            for effect in LightingEffect::ALL {
+                                ui.selectable_value(&mut lighting.effect, effect, effect.to_string());
+                            }
+                        });
+                    if lighting.effect != LightingEffect::Static {
+                        ui.label("Speed:");
+                        ui.add(egui::Slider::new(&mut lighting.speed, KeyboardLighting::SPEEDS));
                     }
                 });
+                if lighting.effect == LightingEffect::Wave {
+                    ui.horizontal(|ui| {
+                        ui.label("Direction:");
+                        ui.radio_value(&mut lighting.direction, WaveDirection::LeftToRight, "Left → Right");
+                        ui.radio_value(&mut lighting.direction, WaveDirection::RightToLeft, "Right → Left");
+                    });
+                }
+
+                if lighting.effect != LightingEffect::Smooth {
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        ui.label("Zones:");
+                        for zone in lighting.zones.iter_mut() {
+                            let mut rgb = [zone.r, zone.g, zone.b];
+                            if ui.color_edit_button_srgb(&mut rgb).changed() {
+                                *zone = Rgb::new(rgb[0], rgb[1], rgb[2]);
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("All zones:");
+                        for (name, color, preset) in [("BLUE", egui::Color32::BLUE, Rgb::BLUE), ("WHITE", egui::Color32::WHITE, Rgb::new(255, 255, 255)), ("RED", egui::Color32::RED, Rgb::new(255, 0, 0))] {
+                            if ui.button(egui::RichText::new(name).color(color)).clicked() {
+                                lighting.zones = [preset; 4];
+                            }
+                        }
+                    });
+                }
+
+                if self.lighting != self.lighting_sent && !self.is_busy && !ui.input(|i| i.pointer.any_down()) {
+                    // The keyboard should still show what was last sent, if it can be read back at all
+                    let expected = self.read(|s| &s.lighting).map(|_| self.lighting_sent);
+                    self.lighting_before_write = Some(self.lighting_sent);
+                    self.lighting_sent = self.lighting;
+                    let _ = self.tx_action.send(GuiAction::SetLighting(self.lighting, expected));
+                    self.is_busy = true;
+                }
             });
             
             ui.add_space(10.0);
 
+            // Section: Write-token audit log (this session only)
+            egui::CollapsingHeader::new("Audit Log").show(ui, |ui| {
+                let entries = legion_core::safety::guards::audit_log();
+                if entries.is_empty() {
+                    ui.label("No writes this session.");
+                }
+                for e in entries.iter().rev() {
+                    ui.horizontal(|ui| {
+                        ui.monospace(legion_core::time::format_utc(e.granted_at));
+                        ui.label(format!("{} → {}", e.operation, e.target));
+                        ui.small(format!("{}, {}{}", e.reason, if e.used { "used" } else { "unused" },
+                            if e.revoked_at.is_some() { ", revoked" } else { "" }));
+                    });
+                }
+            });
+
+            // Section: Write journal (persists across runs); interrupted writes are only reported
+            let interrupted = self.journal.iter().filter(|e| e.is_incomplete()).count();
+            let title = if interrupted > 0 { format!("Write Journal ({} interrupted)", interrupted) } else { "Write Journal".to_string() };
+            egui::CollapsingHeader::new(title).show(ui, |ui| {
+                if self.journal.is_empty() {
+                    ui.label("No journaled writes.");
+                }
+                for e in self.journal.iter().rev().take(20) {
+                    ui.horizontal(|ui| {
+                        ui.monospace(&e.intent.at_utc);
+                        ui.label(format!("{} → {}", e.intent.operation, e.intent.target));
+                        match (&e.result, &e.reconciled) {
+                            (Some(result), _) => { ui.small(&result.status); }
+                            (None, reconciled) => {
+                                ui.colored_label(egui::Color32::from_rgb(255, 140, 0), format!("Interrupted, not repeated (value then: {})",
+                                    reconciled.as_ref().map_or("unknown", |r| r.current.as_str())));
+                            }
+                        }
+                    });
+                }
+                ui.small("Clear with: legion-loq-control journal clear");
+            });
+
+            ui.add_space(10.0);
+
             // Footer / Status
             ui.vertical_centered(|ui| {
                 if let Some(ref err) = self.last_error {
                     ui.colored_label(egui::Color32::RED, format!("⚠ {}", err));
+                } else if let Some(ref msg) = self.last_unverified {
+                    ui.colored_label(egui::Color32::from_rgb(255, 140, 0), format!("? Unverified: {}", msg));
                 } else {
                     ui.label(egui::RichText::new(&self.status_message).italics());
                 }
                 
-                if ui.button("⟳ Refresh State").clicked() {
-                    self.request_refresh();
-                }
+                ui.horizontal(|ui| {
+                    if ui.button("⟳ Refresh State").clicked() {
+                        self.request_refresh();
+                    }
+                    let undo = ui.add_enabled(!self.is_busy && self.last_change.is_some(), egui::Button::new("↶ Undo"));
+                    let undo = match &self.last_change {
+                        Some(entry) => undo.on_hover_text(format!("Revert {} ({})", entry.change, entry.at_utc)),
+                        None => undo.on_hover_text("Nothing to undo"),
+                    };
+                    if undo.clicked() {
+                        let _ = self.tx_action.send(GuiAction::Undo);
+                        self.is_busy = true;
+                    }
+                });
             });
             });
         });
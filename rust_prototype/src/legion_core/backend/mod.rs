@@ -0,0 +1,181 @@
+use crate::legion_core::device::models::DeviceIdentity;
+use crate::legion_core::hw::battery::{BatteryStatus, ChargeMode};
+use crate::legion_core::hw::gpu::IgpuMode;
+use crate::legion_core::hw::ite_device::HidCollection;
+use crate::legion_core::hw::power::PowerProfile;
+use crate::legion_core::hw::thermal::TemperatureReading;
+use crate::legion_core::hw::toggles::Toggle;
+use crate::legion_core::result::{HardwareError, HardwareResult};
+use log::info;
+use std::error::Error;
+use std::sync::OnceLock;
+
+pub mod simulated;
+
+/// Overrides backend selection: `simulated` or `native` (platform default).
+pub const BACKEND_ENV: &str = "LEGION_BACKEND";
+
+/// Everything `legion_core` needs from the machine it runs on.
+///
+/// The core modules (`hw::*`, `device::detect`) are written against this trait only.
+/// Platform code in `crate::platform` provides the real implementations; the active
+/// backend is chosen once per process by `active()`.
+pub trait HardwareBackend: Send + Sync {
+    /// Short identifier used in logs and diagnostics (e.g. "windows").
+    fn name(&self) -> &'static str;
+
+    // --- Identity ---
+    /// Raw firmware identity strings. Implementations must never read serial numbers.
+    fn identity(&self) -> HardwareResult<DeviceIdentity>;
+
+    // --- Battery ---
+    fn battery_status(&self) -> HardwareResult<BatteryStatus>;
+
+    // --- Charge mode ---
+    fn conservation_mode(&self) -> HardwareResult<bool>;
+    fn set_conservation_mode(&self, enable: bool) -> HardwareResult<()>;
+    fn rapid_charge(&self) -> HardwareResult<bool>;
+    fn set_rapid_charge(&self, enable: bool) -> HardwareResult<()>;
+    /// Unified charge mode. The default combines the two flags, reading an unsupported
+    /// rapid charge as off; backends with a single firmware read (EnergyDrv) override it.
+    fn charge_mode(&self) -> HardwareResult<ChargeMode> {
+        // Conservation and rapid are exclusive in firmware, so conservation decides on its own.
+        if self.conservation_mode()? {
+            return Ok(ChargeMode::Conservation);
+        }
+        match self.rapid_charge() {
+            Ok(true) => Ok(ChargeMode::Rapid),
+            Ok(false) | Err(HardwareError::Unsupported(_)) => Ok(ChargeMode::Normal),
+            Err(e) => Err(e),
+        }
+    }
+
+    // --- Simple on/off firmware toggles (optional; default: unsupported) ---
+    fn toggle(&self, toggle: Toggle) -> HardwareResult<bool> {
+        unsupported(self.name(), toggle.label())
+    }
+    fn set_toggle(&self, toggle: Toggle, _enable: bool) -> HardwareResult<()> {
+        unsupported(self.name(), toggle.label())
+    }
+
+    // --- Thermal mode / power profile ---
+    fn power_profile(&self) -> HardwareResult<PowerProfile>;
+    fn set_power_profile(&self, profile: PowerProfile) -> HardwareResult<()>;
+    /// Profiles the firmware accepts, in display order.
+    fn power_profile_choices(&self) -> HardwareResult<Vec<PowerProfile>>;
+
+    // --- Display / GPU (optional; default: unsupported) ---
+    /// Display panel overdrive on/off.
+    fn overdrive(&self) -> HardwareResult<bool> {
+        unsupported(self.name(), "Display overdrive")
+    }
+    fn igpu_mode(&self) -> HardwareResult<IgpuMode> {
+        unsupported(self.name(), "iGPU mode")
+    }
+
+    // --- Sensors (optional; default: unsupported) ---
+    fn temperatures(&self) -> HardwareResult<Vec<TemperatureReading>> {
+        unsupported(self.name(), "Temperature sensors")
+    }
+
+    // --- Lighting transport ---
+    /// Hand keyboard lighting to the app (true) or back to the firmware (false).
+    fn set_light_control_owner(&self, app_control: bool) -> HardwareResult<()>;
+    /// Deliver one already-encoded keyboard lighting feature report.
+    fn send_lighting_report(&self, report: &[u8]) -> HardwareResult<()>;
+    /// Read back the keyboard lighting feature report currently applied (optional; default: unsupported).
+    fn lighting_report(&self) -> HardwareResult<Vec<u8>> {
+        unsupported(self.name(), "Keyboard lighting read-back")
+    }
+    /// Every ITE (`048D`) HID collection, for `ite_device::select` (optional; default: unsupported).
+    fn lighting_collections(&self) -> HardwareResult<Vec<HidCollection>> {
+        unsupported(self.name(), "HID enumeration")
+    }
+}
+
+/// Standard error for a feature a backend does not implement.
+pub fn unsupported<T>(backend: &str, feature: &str) -> HardwareResult<T> {
+    Err(HardwareError::Unsupported(format!("{} is not supported by the {} backend", feature, backend)))
+}
+
+/// Fallback for platforms without a hardware implementation.
+/// Every call fails, so the core reports "unknown" instead of guessing.
+#[cfg(not(any(windows, target_os = "linux")))]
+pub struct UnsupportedBackend;
+
+#[cfg(not(any(windows, target_os = "linux")))]
+impl UnsupportedBackend {
+    fn unsupported<T>(&self) -> HardwareResult<T> {
+        Err(HardwareError::Unsupported(format!("No hardware backend available for this platform ({})", std::env::consts::OS)))
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+impl HardwareBackend for UnsupportedBackend {
+    fn name(&self) -> &'static str { "unsupported" }
+
+    fn identity(&self) -> HardwareResult<DeviceIdentity> { self.unsupported() }
+
+    fn battery_status(&self) -> HardwareResult<BatteryStatus> { self.unsupported() }
+
+    fn conservation_mode(&self) -> HardwareResult<bool> { self.unsupported() }
+    fn set_conservation_mode(&self, _enable: bool) -> HardwareResult<()> { self.unsupported() }
+    fn rapid_charge(&self) -> HardwareResult<bool> { self.unsupported() }
+    fn set_rapid_charge(&self, _enable: bool) -> HardwareResult<()> { self.unsupported() }
+
+    fn power_profile(&self) -> HardwareResult<PowerProfile> { self.unsupported() }
+    fn set_power_profile(&self, _profile: PowerProfile) -> HardwareResult<()> { self.unsupported() }
+    fn power_profile_choices(&self) -> HardwareResult<Vec<PowerProfile>> { self.unsupported() }
+
+    fn set_light_control_owner(&self, _app_control: bool) -> HardwareResult<()> { self.unsupported() }
+    fn send_lighting_report(&self, _report: &[u8]) -> HardwareResult<()> { self.unsupported() }
+}
+
+static ACTIVE: OnceLock<Box<dyn HardwareBackend>> = OnceLock::new();
+
+/// Selects and installs the process backend. Call once at startup, before any hardware access.
+///
+/// `simulate` (the `--simulate` flag) wins; otherwise `LEGION_BACKEND` decides,
+/// falling back to the platform default.
+pub fn init(simulate: bool) -> Result<(), Box<dyn Error>> {
+    let requested = if simulate {
+        "simulated".to_string()
+    } else {
+        std::env::var(BACKEND_ENV).unwrap_or_default().to_lowercase()
+    };
+
+    let backend: Box<dyn HardwareBackend> = match requested.as_str() {
+        "" | "native" => default_backend(),
+        "simulated" | "sim" => Box::new(simulated::SimulatedBackend::from_env()?),
+        other => return Err(format!("Unknown {} '{}'. Use 'native' or 'simulated'.", BACKEND_ENV, other).into()),
+    };
+
+    let name = backend.name();
+    ACTIVE.set(backend).map_err(|_| "Hardware backend already initialized")?;
+    info!("Hardware backend: {}", name);
+    Ok(())
+}
+
+/// Returns the backend for this process, selecting the platform default if `init` was never called.
+pub fn active() -> &'static dyn HardwareBackend {
+    ACTIVE.get_or_init(|| {
+        let backend = default_backend();
+        info!("Hardware backend: {}", backend.name());
+        backend
+    }).as_ref()
+}
+
+fn default_backend() -> Box<dyn HardwareBackend> {
+    #[cfg(windows)]
+    {
+        Box::new(crate::platform::windows::WindowsBackend)
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Box::new(crate::platform::linux::LinuxBackend::from_env())
+    }
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        Box::new(UnsupportedBackend)
+    }
+}
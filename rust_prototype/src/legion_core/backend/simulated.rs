@@ -0,0 +1,571 @@
+use super::HardwareBackend;
+use crate::legion_core::device::models::DeviceIdentity;
+use crate::legion_core::hw::battery::{self, BatteryStatus, ChargeMode, ChargingState};
+use crate::legion_core::hw::gpu::IgpuMode;
+use crate::legion_core::hw::ite_device::{self, HidCollection};
+use crate::legion_core::hw::power::PowerProfile;
+use crate::legion_core::hw::thermal::{SensorKind, TemperatureReading};
+use crate::legion_core::hw::toggles::Toggle;
+use crate::legion_core::result::{HardwareError, HardwareResult};
+use log::info;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Initial state overrides, e.g. `battery=45,ac=false,charge_mode=rapid,thermal_mode=1`.
+pub const STATE_ENV: &str = "LEGION_SIM_STATE";
+/// Injected failures, e.g. `set_conservation_mode=access_denied,power_profile=timeout`.
+pub const FAULTS_ENV: &str = "LEGION_SIM_FAULTS";
+
+/// Every operation that can have a fault injected; names match `HardwareBackend` methods.
+pub const OPERATIONS: &[&str] = &[
+    "identity",
+    "battery_status",
+    "conservation_mode",
+    "set_conservation_mode",
+    "rapid_charge",
+    "set_rapid_charge",
+    "charge_mode",
+    "toggle",
+    "set_toggle",
+    "power_profile",
+    "set_power_profile",
+    "power_profile_choices",
+    "overdrive",
+    "igpu_mode",
+    "temperatures",
+    "set_light_control_owner",
+    "send_lighting_report",
+    "lighting_report",
+    "lighting_collections",
+];
+
+/// What the 83DV's `C993` answers GetFeature with: its identity (`CC 05` + VID/PID),
+/// not the lighting state.
+const IDENTITY_LIGHTING_REPORT: [u8; 33] = [
+    0xCC, 0x05, 0xFF, 0x0A, 0x00, 0x8D, 0x04, 0x93, 0xC9, 0x00, 0x03,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// How long a simulated timeout blocks before failing, so the GUI spinner is visible.
+const TIMEOUT_DELAY: Duration = Duration::from_millis(1500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    AccessDenied,
+    Timeout,
+    InvalidData,
+    Unavailable,
+    Unsupported,
+    Failed,
+}
+
+impl Fault {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "access_denied" | "denied" => Some(Fault::AccessDenied),
+            "timeout" | "timed_out" => Some(Fault::Timeout),
+            "invalid_data" | "invalid" => Some(Fault::InvalidData),
+            "unavailable" => Some(Fault::Unavailable),
+            "unsupported" => Some(Fault::Unsupported),
+            "failed" => Some(Fault::Failed),
+            _ => None,
+        }
+    }
+
+    /// The error `operation` fails with while this fault is injected.
+    fn error(self, operation: &str) -> HardwareError {
+        match self {
+            Fault::AccessDenied => HardwareError::AccessDenied(format!("{}: Access is denied (simulated)", operation)),
+            Fault::Timeout => HardwareError::TimedOut(format!("{}: The operation timed out (simulated)", operation)),
+            Fault::InvalidData => HardwareError::InvalidData(format!("{}: Invalid data returned (simulated)", operation)),
+            Fault::Unavailable => HardwareError::Unavailable(format!("{}: Device not available (simulated)", operation)),
+            Fault::Unsupported => HardwareError::Unsupported(format!("{}: Not supported (simulated)", operation)),
+            Fault::Failed => HardwareError::Failed(format!("{}: The call failed (simulated)", operation)),
+        }
+    }
+}
+
+/// Mutable machine state of the virtual Legion.
+#[derive(Debug, Clone)]
+pub struct SimulatedState {
+    pub manufacturer: String,
+    /// DMI product_name / Win32 Name: the machine type.
+    pub machine_type: String,
+    /// DMI product_version / Win32 Version: the marketing name.
+    pub model: String,
+    pub bios_version: String,
+    pub battery_percent: u16,
+    pub ac_online: bool,
+    pub conservation: bool,
+    pub rapid_charge: bool,
+    pub fn_lock: bool,
+    pub usb_charging: bool,
+    pub camera_power: bool,
+    /// Raw SmartFanMode value, as the WMI interface would report it.
+    pub thermal_mode: u32,
+    pub overdrive: bool,
+    /// Raw GetIGPUModeStatus value.
+    pub igpu_mode: u32,
+    pub cpu_temp: f32,
+    pub gpu_temp: f32,
+    pub light_control_owner_app: bool,
+    /// Feature report the keyboard controller answers GetFeature with: the identity
+    /// reply by default, or a `CC 16` lighting state for a controller with read-back.
+    pub lighting_report: Vec<u8>,
+    /// Last lighting report sent to the controller.
+    pub sent_lighting_report: Option<Vec<u8>>,
+    /// ITE HID collections, as `HardwareBackend::lighting_collections` would list them.
+    pub hid_collections: Vec<HidCollection>,
+}
+
+impl Default for SimulatedState {
+    /// A LOQ 15IRX9 (83DV) on NECN50WW, matching `hardware-evidence/83DV`.
+    fn default() -> Self {
+        Self {
+            manufacturer: "LENOVO".to_string(),
+            machine_type: "83DV".to_string(),
+            model: "LOQ 15IRX9".to_string(),
+            bios_version: "NECN50WW".to_string(),
+            battery_percent: 80,
+            ac_online: true,
+            conservation: false,
+            rapid_charge: false,
+            fn_lock: false,
+            usb_charging: false,
+            camera_power: true,
+            thermal_mode: 2, // Balanced
+            overdrive: false,
+            igpu_mode: 1, // IntegratedOnly
+            cpu_temp: 52.0,
+            gpu_temp: 45.0,
+            light_control_owner_app: false,
+            lighting_report: IDENTITY_LIGHTING_REPORT.to_vec(),
+            sent_lighting_report: None,
+            hid_collections: loq_hid_collections(),
+        }
+    }
+}
+
+/// The 83DV inventory: `C993` has 17, 33, 65 and 192-byte feature collections, `C996`
+/// none of 33 bytes. The usages are placeholders; selection does not look at them.
+fn loq_hid_collections() -> Vec<HidCollection> {
+    let collection = |product_id, usage, len| HidCollection {
+        path: format!("sim:{:04x}:{:04x}", product_id, usage),
+        vendor_id: ite_device::VENDOR_ID,
+        product_id,
+        usage_page: 0xFF89,
+        usage,
+        feature_report_len: Ok(len),
+    };
+    vec![
+        collection(0xC993, 0x0001, 17),
+        collection(0xC993, 0x00CC, 33),
+        collection(0xC993, 0x0002, 65),
+        collection(0xC993, 0x0003, 192),
+        collection(0xC996, 0x0001, 17),
+    ]
+}
+
+/// Fully in-memory backend that behaves like a LOQ 83DV.
+///
+/// Selected with `--simulate` or `LEGION_BACKEND=simulated`. State and faults can be
+/// scripted through `LEGION_SIM_STATE` / `LEGION_SIM_FAULTS` or the builder methods.
+pub struct SimulatedBackend {
+    state: Mutex<SimulatedState>,
+    faults: Mutex<HashMap<&'static str, Fault>>,
+}
+
+impl SimulatedBackend {
+    pub fn new() -> Self {
+        Self::with_state(SimulatedState::default())
+    }
+
+    pub fn with_state(state: SimulatedState) -> Self {
+        Self {
+            state: Mutex::new(state),
+            faults: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Builds a backend from `LEGION_SIM_STATE` and `LEGION_SIM_FAULTS`.
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let backend = Self::new();
+        if let Ok(spec) = std::env::var(STATE_ENV) {
+            backend.apply_state_spec(&spec)?;
+        }
+        if let Ok(spec) = std::env::var(FAULTS_ENV) {
+            backend.apply_fault_spec(&spec)?;
+        }
+        Ok(backend)
+    }
+
+    /// Makes every call to `operation` fail with `fault` until cleared.
+    pub fn inject(&self, operation: &str, fault: Fault) -> Result<(), Box<dyn Error>> {
+        let key = OPERATIONS.iter().find(|op| **op == operation)
+            .ok_or_else(|| format!("Unknown simulated operation '{}'", operation))?;
+        self.faults.lock().unwrap().insert(key, fault);
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub fn clear_faults(&self) {
+        self.faults.lock().unwrap().clear();
+    }
+
+    /// Copy of the current simulated state.
+    #[cfg(test)]
+    pub fn snapshot(&self) -> SimulatedState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Applies a comma-separated `key=value` list to the simulated state.
+    pub fn apply_state_spec(&self, spec: &str) -> Result<(), Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        for (key, value) in parse_pairs(spec)? {
+            match key {
+                "manufacturer" => state.manufacturer = value.to_string(),
+                "machine_type" => state.machine_type = value.to_string(),
+                "model" => state.model = value.to_string(),
+                "bios" | "bios_version" => state.bios_version = value.to_string(),
+                "battery" => state.battery_percent = value.parse()
+                    .map_err(|_| format!("Invalid battery percent '{}'", value))?,
+                "ac" | "charging" => state.ac_online = parse_bool(value)?,
+                "charge_mode" => match value {
+                    "normal" => { state.conservation = false; state.rapid_charge = false; },
+                    "conservation" => { state.conservation = true; state.rapid_charge = false; },
+                    "rapid" => { state.conservation = false; state.rapid_charge = true; },
+                    _ => return Err(format!("Invalid charge_mode '{}'. Use normal, conservation or rapid.", value).into()),
+                },
+                // Raw EnergyDrv bits, e.g. 0x24 for the conflicting state real firmware never reports.
+                "charge_mode_bits" => {
+                    let bits = u32::from_str_radix(value.trim_start_matches("0x"), 16)
+                        .map_err(|_| format!("Invalid charge_mode_bits '{}'. Use hex, e.g. 0x20.", value))?;
+                    state.conservation = bits & battery::ENERGY_CONSERVATION_BIT != 0;
+                    state.rapid_charge = bits & battery::ENERGY_RAPID_CHARGE_BIT != 0;
+                },
+                "fn_lock" => state.fn_lock = parse_bool(value)?,
+                "usb_charging" => state.usb_charging = parse_bool(value)?,
+                "camera_power" => state.camera_power = parse_bool(value)?,
+                "thermal_mode" => state.thermal_mode = value.parse()
+                    .map_err(|_| format!("Invalid thermal_mode '{}'", value))?,
+                "cpu_temp" => state.cpu_temp = value.parse()
+                    .map_err(|_| format!("Invalid cpu_temp '{}'", value))?,
+                "overdrive" => state.overdrive = parse_bool(value)?,
+                "igpu_mode" => state.igpu_mode = value.parse()
+                    .map_err(|_| format!("Invalid igpu_mode '{}'", value))?,
+                "gpu_temp" => state.gpu_temp = value.parse()
+                    .map_err(|_| format!("Invalid gpu_temp '{}'", value))?,
+                _ => return Err(format!("Unknown simulated state key '{}'", key).into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies a comma-separated `operation=fault` list.
+    pub fn apply_fault_spec(&self, spec: &str) -> Result<(), Box<dyn Error>> {
+        for (operation, value) in parse_pairs(spec)? {
+            let fault = Fault::parse(value).ok_or_else(|| {
+                format!("Invalid fault '{}'. Use access_denied, timeout, invalid_data, unavailable, unsupported or failed.", value)
+            })?;
+            self.inject(operation, fault)?;
+        }
+        Ok(())
+    }
+
+    fn check(&self, operation: &'static str) -> HardwareResult<()> {
+        let fault = self.faults.lock().unwrap().get(operation).copied();
+        match fault {
+            None => Ok(()),
+            Some(fault) => {
+                if fault == Fault::Timeout {
+                    thread::sleep(TIMEOUT_DELAY);
+                }
+                Err(fault.error(operation))
+            }
+        }
+    }
+
+    fn read<T>(&self, operation: &'static str, f: impl FnOnce(&SimulatedState) -> T) -> HardwareResult<T> {
+        self.check(operation)?;
+        Ok(f(&self.state.lock().unwrap()))
+    }
+
+    fn write(&self, operation: &'static str, f: impl FnOnce(&mut SimulatedState)) -> HardwareResult<()> {
+        self.check(operation)?;
+        f(&mut self.state.lock().unwrap());
+        info!("[sim] {} applied", operation);
+        Ok(())
+    }
+}
+
+impl Default for SimulatedBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HardwareBackend for SimulatedBackend {
+    fn name(&self) -> &'static str {
+        "simulated"
+    }
+
+    fn identity(&self) -> HardwareResult<DeviceIdentity> {
+        self.read("identity", |s| DeviceIdentity {
+            manufacturer: s.manufacturer.clone(),
+            product_name: s.machine_type.clone(),
+            product_version: s.model.clone(),
+            product_family: Some(s.model.clone()),
+            bios_version: s.bios_version.clone(),
+        })
+    }
+
+    fn battery_status(&self) -> HardwareResult<BatteryStatus> {
+        self.read("battery_status", |s| {
+            let state = match (s.ac_online, s.battery_percent) {
+                (false, _) => ChargingState::Discharging,
+                (true, p) if p >= 100 => ChargingState::Full,
+                // Conservation holds the battery at ~60%.
+                (true, p) if s.conservation && p >= 60 => ChargingState::NotCharging,
+                (true, _) => ChargingState::Charging,
+            };
+            let power_w = match state {
+                ChargingState::Charging if s.rapid_charge => Some(65.0),
+                ChargingState::Charging => Some(35.0),
+                ChargingState::Discharging => Some(18.5),
+                _ => Some(0.0),
+            };
+            // 60 Wh design pack, slightly worn.
+            let energy_full_wh = 57.0;
+            BatteryStatus {
+                charge_percent: s.battery_percent,
+                state,
+                ac_online: Some(s.ac_online),
+                energy_now_wh: Some(energy_full_wh * s.battery_percent as f32 / 100.0),
+                energy_full_wh: Some(energy_full_wh),
+                energy_design_wh: Some(60.0),
+                voltage_v: Some(15.9),
+                power_w,
+                cycle_count: Some(42),
+            }
+        })
+    }
+
+    fn conservation_mode(&self) -> HardwareResult<bool> {
+        self.read("conservation_mode", |s| s.conservation)
+    }
+
+    fn set_conservation_mode(&self, enable: bool) -> HardwareResult<()> {
+        // Firmware treats conservation and rapid charge as mutually exclusive.
+        self.write("set_conservation_mode", |s| {
+            s.conservation = enable;
+            if enable {
+                s.rapid_charge = false;
+            }
+        })
+    }
+
+    fn rapid_charge(&self) -> HardwareResult<bool> {
+        self.read("rapid_charge", |s| s.rapid_charge)
+    }
+
+    fn set_rapid_charge(&self, enable: bool) -> HardwareResult<()> {
+        self.write("set_rapid_charge", |s| {
+            s.rapid_charge = enable;
+            if enable {
+                s.conservation = false;
+            }
+        })
+    }
+
+    fn toggle(&self, toggle: Toggle) -> HardwareResult<bool> {
+        self.read("toggle", |s| *toggle_field(s, toggle))
+    }
+
+    fn set_toggle(&self, toggle: Toggle, enable: bool) -> HardwareResult<()> {
+        self.write("set_toggle", |s| *toggle_field_mut(s, toggle) = enable)
+    }
+
+    fn charge_mode(&self) -> HardwareResult<ChargeMode> {
+        // Answer like the EnergyDrv read so the decoder runs under simulation too.
+        let bits = self.read("charge_mode", |s| {
+            (if s.conservation { battery::ENERGY_CONSERVATION_BIT } else { 0 })
+                | (if s.rapid_charge { battery::ENERGY_RAPID_CHARGE_BIT } else { 0 })
+        })?;
+        ChargeMode::from_energy_bits(bits)
+    }
+
+    fn power_profile(&self) -> HardwareResult<PowerProfile> {
+        self.read("power_profile", |s| PowerProfile::from_smart_fan_mode(s.thermal_mode))
+    }
+
+    fn set_power_profile(&self, profile: PowerProfile) -> HardwareResult<()> {
+        let mode = profile.to_smart_fan_mode()
+            .ok_or_else(|| HardwareError::Unsupported(format!("{} has no SmartFanMode value", profile)))?;
+        self.write("set_power_profile", |s| s.thermal_mode = mode)
+    }
+
+    fn power_profile_choices(&self) -> HardwareResult<Vec<PowerProfile>> {
+        // The LOQ 15IRX9 has no Custom (God Mode) profile.
+        self.read("power_profile_choices", |_| vec![PowerProfile::Quiet, PowerProfile::Balanced, PowerProfile::Performance])
+    }
+
+    fn overdrive(&self) -> HardwareResult<bool> {
+        self.read("overdrive", |s| s.overdrive)
+    }
+
+    fn igpu_mode(&self) -> HardwareResult<IgpuMode> {
+        let raw = self.read("igpu_mode", |s| s.igpu_mode)?;
+        IgpuMode::from_raw(raw).ok_or_else(|| HardwareError::InvalidData(format!("Unknown GetIGPUModeStatus value {}", raw)))
+    }
+
+    fn temperatures(&self) -> HardwareResult<Vec<TemperatureReading>> {
+        self.read("temperatures", |s| vec![
+            TemperatureReading {
+                kind: SensorKind::CpuPackage,
+                source: "coretemp".to_string(),
+                label: "Package id 0".to_string(),
+                celsius: s.cpu_temp,
+            },
+            TemperatureReading {
+                kind: SensorKind::Gpu,
+                source: "nouveau".to_string(),
+                label: "temp1".to_string(),
+                celsius: s.gpu_temp,
+            },
+        ])
+    }
+
+    fn set_light_control_owner(&self, app_control: bool) -> HardwareResult<()> {
+        self.write("set_light_control_owner", |s| s.light_control_owner_app = app_control)
+    }
+
+    fn send_lighting_report(&self, report: &[u8]) -> HardwareResult<()> {
+        self.write("send_lighting_report", |s| {
+            s.sent_lighting_report = Some(report.to_vec());
+            // A controller that answers with its identity keeps doing so after a write
+            if ite_device::check_not_identity(&s.lighting_report).is_ok() {
+                s.lighting_report = report.to_vec();
+            }
+        })
+    }
+
+    fn lighting_report(&self) -> HardwareResult<Vec<u8>> {
+        let report = self.read("lighting_report", |s| s.lighting_report.clone())?;
+        ite_device::check_not_identity(&report)?;
+        Ok(report)
+    }
+
+    fn lighting_collections(&self) -> HardwareResult<Vec<HidCollection>> {
+        self.read("lighting_collections", |s| s.hid_collections.clone())
+    }
+}
+
+fn toggle_field(state: &SimulatedState, toggle: Toggle) -> &bool {
+    match toggle {
+        Toggle::FnLock => &state.fn_lock,
+        Toggle::UsbCharging => &state.usb_charging,
+        Toggle::CameraPower => &state.camera_power,
+    }
+}
+
+fn toggle_field_mut(state: &mut SimulatedState, toggle: Toggle) -> &mut bool {
+    match toggle {
+        Toggle::FnLock => &mut state.fn_lock,
+        Toggle::UsbCharging => &mut state.usb_charging,
+        Toggle::CameraPower => &mut state.camera_power,
+    }
+}
+
+fn parse_pairs(spec: &str) -> Result<Vec<(&str, &str)>, Box<dyn Error>> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| format!("Expected key=value, got '{}'", item).into())
+        })
+        .collect()
+}
+
+fn parse_bool(value: &str) -> Result<bool, Box<dyn Error>> {
+    match value {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" => Ok(false),
+        _ => Err(format!("Invalid boolean '{}'", value).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_to_loq_83dv() {
+        let sim = SimulatedBackend::new();
+        let identity = sim.identity().unwrap();
+        assert_eq!(identity.manufacturer, "LENOVO");
+        assert_eq!(identity.product_name, "83DV");
+        assert_eq!(identity.product_version, "LOQ 15IRX9");
+        assert_eq!(identity.bios_version, "NECN50WW");
+        assert_eq!(sim.power_profile().unwrap(), PowerProfile::Balanced);
+    }
+
+    #[test]
+    fn charge_modes_are_mutually_exclusive() {
+        let sim = SimulatedBackend::new();
+        sim.set_rapid_charge(true).unwrap();
+        sim.set_conservation_mode(true).unwrap();
+        assert!(sim.conservation_mode().unwrap());
+        assert!(!sim.rapid_charge().unwrap());
+    }
+
+    #[test]
+    fn conservation_holds_charge_on_ac() {
+        let sim = SimulatedBackend::new();
+        sim.apply_state_spec("battery=60,charge_mode=conservation").unwrap();
+        assert_eq!(sim.battery_status().unwrap().state, ChargingState::NotCharging);
+        sim.apply_state_spec("charge_mode=normal").unwrap();
+        assert_eq!(sim.battery_status().unwrap().state, ChargingState::Charging);
+    }
+
+    #[test]
+    fn state_spec_overrides_defaults() {
+        let sim = SimulatedBackend::new();
+        sim.apply_state_spec("battery=45, ac=off, charge_mode=rapid, thermal_mode=1").unwrap();
+        let state = sim.snapshot();
+        assert_eq!(state.battery_percent, 45);
+        assert!(!state.ac_online);
+        assert!(state.rapid_charge);
+        assert_eq!(state.thermal_mode, 1);
+        assert!(sim.apply_state_spec("charge_mode=turbo").is_err());
+        assert!(sim.apply_state_spec("fan=max").is_err());
+    }
+
+    #[test]
+    fn injected_fault_fails_only_that_operation() {
+        let sim = SimulatedBackend::new();
+        sim.apply_fault_spec("set_power_profile=access_denied").unwrap();
+
+        let err = sim.set_power_profile(PowerProfile::Performance).unwrap_err();
+        assert!(matches!(err, HardwareError::AccessDenied(_)));
+        assert_eq!(err.code(), "access_denied");
+        assert_eq!(sim.power_profile().unwrap(), PowerProfile::Balanced, "failed write must not change state");
+
+        sim.clear_faults();
+        sim.set_power_profile(PowerProfile::Performance).unwrap();
+        assert_eq!(sim.snapshot().thermal_mode, 3);
+    }
+
+    #[test]
+    fn rejects_unknown_operations_and_faults() {
+        let sim = SimulatedBackend::new();
+        assert!(sim.inject("set_fan_speed", Fault::Timeout).is_err());
+        assert!(sim.apply_fault_spec("power_profile=explode").is_err());
+        assert!(sim.apply_fault_spec("power_profile").is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use serde::{Serialize, Deserialize};
+use std::fmt;
+
+pub mod probe;
+
+/// A user-facing control that is gated on its own evidence.
+///
+/// Names match the `capability` values in `hardware-evidence/*/*.json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Capability {
+    BatteryConservationMode,
+    BatteryRapidCharge,
+    ThermalMode,
+    /// Custom fan curve (`Fan_Get_Table` / `Fan_Set_Table`).
+    FanControl,
+    WhiteKeyboardBacklight,
+    FourZoneRgbKeyboard,
+    SpectrumKeyboard,
+    DisplayOverdrive,
+    HybridGraphicsMode,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 9] = [
+        Capability::BatteryConservationMode,
+        Capability::BatteryRapidCharge,
+        Capability::ThermalMode,
+        Capability::FanControl,
+        Capability::WhiteKeyboardBacklight,
+        Capability::FourZoneRgbKeyboard,
+        Capability::SpectrumKeyboard,
+        Capability::DisplayOverdrive,
+        Capability::HybridGraphicsMode,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Capability::BatteryConservationMode => "Conservation Mode",
+            Capability::BatteryRapidCharge => "Rapid Charge",
+            Capability::ThermalMode => "Thermal Mode",
+            Capability::FanControl => "Fan Table",
+            Capability::WhiteKeyboardBacklight => "White Backlight",
+            Capability::FourZoneRgbKeyboard => "4-Zone RGB Keyboard",
+            Capability::SpectrumKeyboard => "Spectrum Keyboard",
+            Capability::DisplayOverdrive => "Display Overdrive",
+            Capability::HybridGraphicsMode => "Hybrid Graphics",
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CapabilityStatus {
+    /// Not enough information to decide; the control is hidden.
+    #[default]
+    Unknown,
+    Unsupported,
+    Supported,
+    /// Expected on this model but the live probe failed; offered with a warning.
+    Degraded,
+}
+
+impl fmt::Display for CapabilityStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CapabilityStatus::Unknown => write!(f, "Unknown"),
+            CapabilityStatus::Unsupported => write!(f, "Unsupported"),
+            CapabilityStatus::Supported => write!(f, "Supported"),
+            CapabilityStatus::Degraded => write!(f, "Degraded"),
+        }
+    }
+}
+
+/// Why a capability has the status it has. Serialises like the `observations` entries
+/// in `hardware-evidence/*/*.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilityEvidence {
+    pub capability: Capability,
+    pub status: CapabilityStatus,
+    /// Stable, machine-readable reason, e.g. `read_ok` or `model_database_absent`.
+    pub evidence_code: String,
+    /// Where the evidence came from: the model database revision or the backend name.
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// One `CapabilityEvidence` per `Capability`, produced by `probe::probe_capabilities`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CapabilityReport {
+    pub observations: Vec<CapabilityEvidence>,
+}
+
+impl CapabilityReport {
+    pub fn evidence(&self, capability: Capability) -> Option<&CapabilityEvidence> {
+        self.observations.iter().find(|e| e.capability == capability)
+    }
+
+    pub fn status(&self, capability: Capability) -> CapabilityStatus {
+        self.evidence(capability).map(|e| e.status).unwrap_or_default()
+    }
+
+    /// Whether the control should be offered: Supported, or Degraded (offered with a warning).
+    pub fn is_usable(&self, capability: Capability) -> bool {
+        matches!(self.status(capability), CapabilityStatus::Supported | CapabilityStatus::Degraded)
+    }
+}
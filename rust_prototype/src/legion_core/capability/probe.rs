@@ -0,0 +1,232 @@
+use super::{Capability, CapabilityEvidence, CapabilityReport, CapabilityStatus};
+use crate::legion_core::backend::{self, HardwareBackend};
+use crate::legion_core::device::database::{self, KeyboardBacklight, ModelCapabilities};
+use crate::legion_core::device::models::LaptopModel;
+use crate::legion_core::hw::battery::ChargeMode;
+use crate::legion_core::hw::ite_device;
+use crate::legion_core::result::{HardwareError, HardwareResult};
+
+/// Probes every `Capability` for the detected machine against the active backend.
+pub fn probe(model: &LaptopModel) -> CapabilityReport {
+    probe_capabilities(model, backend::active(), &database::active().revision)
+}
+
+/// Combines what the model database says with what the backend can actually read.
+///
+/// * not listed for this machine → `Unsupported`
+/// * listed and the live read works → `Supported`
+/// * listed but the backend has no way to reach it → `Unsupported` (`backend_unsupported`)
+/// * listed but the live read fails → `Degraded` (`read_<error code>`, offered with the error as detail)
+/// * 4-zone keyboard: listed and an ITE collection passes `ite_device::select` → `Supported`,
+///   none does → `Unsupported` (`hid_interface_not_found`, each rejection as detail)
+/// * listed but this app has no way to touch it yet → `Unknown`
+/// * machine not in the database → `Unknown` for everything
+pub fn probe_capabilities(model: &LaptopModel, backend: &dyn HardwareBackend, db_revision: &str) -> CapabilityReport {
+    let db_source = format!("model database {}", db_revision);
+
+    let Some(caps) = &model.capabilities else {
+        return CapabilityReport {
+            observations: Capability::ALL.iter().map(|&capability| CapabilityEvidence {
+                capability,
+                status: CapabilityStatus::Unknown,
+                evidence_code: "model_not_in_database".to_string(),
+                source: db_source.clone(),
+                detail: Some(format!("{} / {}", model.machine_type, model.bios_version)),
+            }).collect(),
+        };
+    };
+
+    let observations = Capability::ALL.iter().map(|&capability| {
+        let evidence = |status, code: &str, source: &str, detail: Option<String>| CapabilityEvidence {
+            capability,
+            status,
+            evidence_code: code.to_string(),
+            source: source.to_string(),
+            detail,
+        };
+
+        if !listed(caps, capability) {
+            return evidence(CapabilityStatus::Unsupported, "model_database_absent", &db_source, None);
+        }
+
+        let live = match capability {
+            Capability::BatteryConservationMode => backend.conservation_mode().map(|_| None),
+            Capability::BatteryRapidCharge => backend.rapid_charge().map(|_| None),
+            Capability::ThermalMode => thermal_mode_detail(caps, backend),
+            Capability::DisplayOverdrive => backend.overdrive().map(|_| None),
+            Capability::HybridGraphicsMode => backend.igpu_mode().map(|_| None),
+            // The controller must pass the allowlist, usage and report-length checks.
+            Capability::FourZoneRgbKeyboard => match backend.lighting_collections().and_then(|c| ite_device::select(&c).map(|s| s.to_string())) {
+                Ok(selected) => return evidence(CapabilityStatus::Supported, "hid_collection_verified", backend.name(), Some(format!("ITE {}", selected))),
+                Err(e @ HardwareError::Unavailable(_)) => {
+                    return evidence(CapabilityStatus::Unsupported, "hid_interface_not_found", backend.name(), Some(e.to_string()));
+                }
+                Err(e) => Err(e),
+            },
+            Capability::FanControl
+            | Capability::WhiteKeyboardBacklight
+            | Capability::SpectrumKeyboard => {
+                return evidence(CapabilityStatus::Unknown, "no_backend_support", backend.name(), None);
+            }
+        };
+
+        match live {
+            Ok(None) => evidence(CapabilityStatus::Supported, "read_ok", backend.name(), None),
+            Ok(Some(detail)) => evidence(CapabilityStatus::Degraded, "fewer_modes_than_listed", backend.name(), Some(detail)),
+            Err(e @ HardwareError::Unsupported(_)) => {
+                evidence(CapabilityStatus::Unsupported, "backend_unsupported", backend.name(), Some(e.to_string()))
+            }
+            Err(e) => evidence(CapabilityStatus::Degraded, &format!("read_{}", e.code()), backend.name(), Some(e.to_string())),
+        }
+    }).collect();
+
+    CapabilityReport { observations }
+}
+
+fn listed(caps: &ModelCapabilities, capability: Capability) -> bool {
+    match capability {
+        Capability::BatteryConservationMode => caps.offers_charge_mode(ChargeMode::Conservation),
+        Capability::BatteryRapidCharge => caps.offers_charge_mode(ChargeMode::Rapid),
+        Capability::ThermalMode => !caps.thermal_modes.is_empty(),
+        Capability::FanControl => caps.fan_table,
+        Capability::WhiteKeyboardBacklight => caps.keyboard_backlight == KeyboardBacklight::White,
+        Capability::FourZoneRgbKeyboard => caps.keyboard_backlight == KeyboardBacklight::FourZoneRgb,
+        Capability::SpectrumKeyboard => caps.keyboard_backlight == KeyboardBacklight::Spectrum,
+        Capability::DisplayOverdrive => caps.overdrive,
+        Capability::HybridGraphicsMode => caps.hybrid_graphics,
+    }
+}
+
+/// Reads the current mode and the firmware's choices; `Some(detail)` if the firmware
+/// offers fewer of the listed modes than the database expects.
+fn thermal_mode_detail(caps: &ModelCapabilities, backend: &dyn HardwareBackend) -> HardwareResult<Option<String>> {
+    backend.power_profile()?;
+    let choices = backend.power_profile_choices()?;
+    let missing: Vec<String> = caps.thermal_modes.iter()
+        .filter(|mode| !choices.contains(mode))
+        .map(|mode| format!("{:?}", mode))
+        .collect();
+    Ok(if missing.is_empty() { None } else { Some(format!("firmware does not offer {}", missing.join(", "))) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legion_core::backend::simulated::{Fault, SimulatedBackend, SimulatedState};
+    use crate::legion_core::device::database::ModelDatabase;
+    use crate::legion_core::device::detect::model_from_identity;
+    use crate::legion_core::hw::power::PowerProfile;
+
+    fn loq() -> LaptopModel {
+        let backend = SimulatedBackend::new();
+        model_from_identity(backend.identity().unwrap(), &ModelDatabase::embedded())
+    }
+
+    #[test]
+    fn simulated_loq_gates_each_capability() {
+        let report = probe_capabilities(&loq(), &SimulatedBackend::new(), "test");
+        assert_eq!(report.status(Capability::BatteryConservationMode), CapabilityStatus::Supported);
+        assert_eq!(report.status(Capability::BatteryRapidCharge), CapabilityStatus::Supported);
+        assert_eq!(report.status(Capability::ThermalMode), CapabilityStatus::Supported);
+        assert_eq!(report.status(Capability::FourZoneRgbKeyboard), CapabilityStatus::Supported);
+        assert_eq!(report.status(Capability::SpectrumKeyboard), CapabilityStatus::Unsupported);
+        assert_eq!(report.status(Capability::WhiteKeyboardBacklight), CapabilityStatus::Unsupported);
+        assert_eq!(report.status(Capability::FanControl), CapabilityStatus::Unknown);
+        assert_eq!(report.status(Capability::DisplayOverdrive), CapabilityStatus::Supported);
+        assert_eq!(report.status(Capability::HybridGraphicsMode), CapabilityStatus::Supported);
+        assert_eq!(report.observations.len(), Capability::ALL.len());
+    }
+
+    #[test]
+    fn failing_read_degrades_only_that_capability() {
+        let backend = SimulatedBackend::new();
+        backend.inject("rapid_charge", Fault::AccessDenied).unwrap();
+        let report = probe_capabilities(&loq(), &backend, "test");
+
+        let rapid = report.evidence(Capability::BatteryRapidCharge).unwrap();
+        assert_eq!(rapid.status, CapabilityStatus::Degraded);
+        assert_eq!(rapid.evidence_code, "read_access_denied");
+        assert!(report.is_usable(Capability::BatteryRapidCharge));
+        assert_eq!(report.status(Capability::BatteryConservationMode), CapabilityStatus::Supported);
+    }
+
+    #[test]
+    fn backend_without_the_feature_is_unsupported() {
+        let backend = SimulatedBackend::new();
+        backend.inject("rapid_charge", Fault::Unsupported).unwrap();
+        let report = probe_capabilities(&loq(), &backend, "test");
+
+        let rapid = report.evidence(Capability::BatteryRapidCharge).unwrap();
+        assert_eq!(rapid.status, CapabilityStatus::Unsupported);
+        assert_eq!(rapid.evidence_code, "backend_unsupported");
+        assert!(!report.is_usable(Capability::BatteryRapidCharge));
+    }
+
+    #[test]
+    fn overdrive_and_hybrid_graphics_follow_the_live_read() {
+        let backend = SimulatedBackend::new();
+        backend.inject("overdrive", Fault::Unsupported).unwrap();
+        backend.inject("igpu_mode", Fault::Unavailable).unwrap();
+        let report = probe_capabilities(&loq(), &backend, "test");
+        let overdrive = report.evidence(Capability::DisplayOverdrive).unwrap();
+        assert_eq!((overdrive.status, overdrive.evidence_code.as_str()), (CapabilityStatus::Unsupported, "backend_unsupported"));
+        let igpu = report.evidence(Capability::HybridGraphicsMode).unwrap();
+        assert_eq!((igpu.status, igpu.evidence_code.as_str()), (CapabilityStatus::Degraded, "read_unavailable"));
+
+        // Not listed for the machine: never read
+        let mut model = loq();
+        let caps = model.capabilities.as_mut().unwrap();
+        (caps.overdrive, caps.hybrid_graphics) = (false, false);
+        let report = probe_capabilities(&model, &backend, "test");
+        assert_eq!(report.evidence(Capability::DisplayOverdrive).unwrap().evidence_code, "model_database_absent");
+        assert_eq!(report.status(Capability::HybridGraphicsMode), CapabilityStatus::Unsupported);
+    }
+
+    #[test]
+    fn four_zone_keyboard_needs_a_verified_hid_collection() {
+        let report = probe_capabilities(&loq(), &SimulatedBackend::new(), "test");
+        let keyboard = report.evidence(Capability::FourZoneRgbKeyboard).unwrap();
+        assert_eq!(keyboard.evidence_code, "hid_collection_verified");
+        assert_eq!(keyboard.detail.as_deref(), Some("ITE 048D:C993 usage FF89:00CC, 33-byte feature report"));
+
+        // Only C996 left: on the PID mask, but not on the allowlist
+        let mut state = SimulatedState::default();
+        state.hid_collections.retain(|c| c.product_id == 0xC996);
+        let report = probe_capabilities(&loq(), &SimulatedBackend::with_state(state), "test");
+        let keyboard = report.evidence(Capability::FourZoneRgbKeyboard).unwrap();
+        assert_eq!((keyboard.status, keyboard.evidence_code.as_str()), (CapabilityStatus::Unsupported, "hid_interface_not_found"));
+        assert!(keyboard.detail.as_deref().unwrap().contains("048D:C996"));
+
+        let backend = SimulatedBackend::new();
+        backend.inject("lighting_collections", Fault::AccessDenied).unwrap();
+        let report = probe_capabilities(&loq(), &backend, "test");
+        assert_eq!(report.evidence(Capability::FourZoneRgbKeyboard).unwrap().evidence_code, "read_access_denied");
+    }
+
+    #[test]
+    fn missing_thermal_modes_degrade_thermal_mode() {
+        let mut model = loq();
+        model.capabilities.as_mut().unwrap().thermal_modes.push(PowerProfile::Custom);
+        let report = probe_capabilities(&model, &SimulatedBackend::new(), "test");
+        let thermal = report.evidence(Capability::ThermalMode).unwrap();
+        assert_eq!(thermal.status, CapabilityStatus::Degraded);
+        assert_eq!(thermal.detail.as_deref(), Some("firmware does not offer Custom"));
+    }
+
+    #[test]
+    fn unlisted_machine_is_unknown_everywhere() {
+        let mut model = loq();
+        model.capabilities = None;
+        let report = probe_capabilities(&model, &SimulatedBackend::new(), "test");
+        assert!(Capability::ALL.iter().all(|&c| report.status(c) == CapabilityStatus::Unknown && !report.is_usable(c)));
+    }
+
+    #[test]
+    fn serialises_like_hardware_evidence() {
+        let report = probe_capabilities(&loq(), &SimulatedBackend::new(), "test");
+        let json = serde_json::to_value(report.evidence(Capability::SpectrumKeyboard).unwrap()).unwrap();
+        assert_eq!(json["capability"], "SpectrumKeyboard");
+        assert_eq!(json["status"], "Unsupported");
+        assert_eq!(json["evidenceCode"], "model_database_absent");
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Serialize, Deserialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A Lenovo BIOS version such as `NECN50WW`.
+///
+/// The first four characters name the firmware family (one per board design), the digits
+/// that follow are the build number, and the trailing letters are a region marker (`WW`).
+/// Builds are only comparable within the same family.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BiosVersion {
+    pub family: String,
+    pub build: u16,
+    pub suffix: String,
+}
+
+impl BiosVersion {
+    /// Parses `NECN50WW`, `J2CN38WW` or `GKCN58WW(V2.02)`; returns `None` for anything else.
+    pub fn parse(raw: &str) -> Option<Self> {
+        // Some firmware appends a human-readable version in parentheses.
+        let token = raw.trim().split(['(', ' ']).next()?.to_uppercase();
+        if token.len() < 5 || !token.is_ascii() {
+            return None;
+        }
+
+        let (family, rest) = token.split_at(4);
+        if !family.chars().all(|c| c.is_ascii_alphanumeric()) || !family.chars().any(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+
+        let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+        let suffix = &rest[digits..];
+        if digits == 0 || !suffix.chars().all(|c| c.is_ascii_uppercase()) {
+            return None;
+        }
+
+        Some(BiosVersion {
+            family: family.to_string(),
+            build: rest[..digits].parse().ok()?,
+            suffix: suffix.to_string(),
+        })
+    }
+}
+
+impl PartialOrd for BiosVersion {
+    /// Orders by build number; versions from different families are unordered.
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.family != other.family {
+            return None;
+        }
+        Some(self.build.cmp(&other.build).then_with(|| self.suffix.cmp(&other.suffix)))
+    }
+}
+
+impl fmt::Display for BiosVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{:02}{}", self.family, self.build, self.suffix)
+    }
+}
+
+/// A rule like "family NECN, build >= 45" used to gate features on firmware.
+///
+/// Both bounds are inclusive; an absent bound is open.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BiosRange {
+    pub family: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_build: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_build: Option<u16>,
+}
+
+impl BiosRange {
+    /// Every build of `family`.
+    #[cfg(test)]
+    pub fn family(family: &str) -> Self {
+        BiosRange { family: family.to_uppercase(), min_build: None, max_build: None }
+    }
+
+    #[cfg(test)]
+    pub fn at_least(mut self, build: u16) -> Self {
+        self.min_build = Some(build);
+        self
+    }
+
+    #[cfg(test)]
+    pub fn at_most(mut self, build: u16) -> Self {
+        self.max_build = Some(build);
+        self
+    }
+
+    pub fn matches(&self, version: &BiosVersion) -> bool {
+        version.family.eq_ignore_ascii_case(&self.family)
+            && self.min_build.is_none_or(|min| version.build >= min)
+            && self.max_build.is_none_or(|max| version.build <= max)
+    }
+}
+
+impl fmt::Display for BiosRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.min_build, self.max_build) {
+            (None, None) => write!(f, "{} (any build)", self.family),
+            (Some(min), None) => write!(f, "{} build >= {}", self.family, min),
+            (None, Some(max)) => write!(f, "{} build <= {}", self.family, max),
+            (Some(min), Some(max)) => write!(f, "{} build {}..={}", self.family, min, max),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(raw: &str) -> BiosVersion {
+        BiosVersion::parse(raw).unwrap()
+    }
+
+    #[test]
+    fn parses_lenovo_bios_strings() {
+        assert_eq!(v("NECN50WW"), BiosVersion { family: "NECN".into(), build: 50, suffix: "WW".into() });
+        assert_eq!(v("j2cn38ww\n").family, "J2CN");
+        assert_eq!(v("GKCN58WW(V2.02)").build, 58);
+        assert_eq!(v("HHCN31WW").to_string(), "HHCN31WW");
+    }
+
+    #[test]
+    fn rejects_non_lenovo_strings() {
+        assert_eq!(BiosVersion::parse(""), None);
+        assert_eq!(BiosVersion::parse("1.2.3"), None);
+        assert_eq!(BiosVersion::parse("F.15"), None);
+        assert_eq!(BiosVersion::parse("NECNWW"), None);
+        assert_eq!(BiosVersion::parse("Unknown"), None);
+    }
+
+    #[test]
+    fn orders_only_within_a_family() {
+        assert!(v("NECN50WW") > v("NECN45WW"));
+        assert!(v("NECN45WW") == v("NECN45WW"));
+        assert_eq!(v("NECN50WW").partial_cmp(&v("GKCN58WW")), None);
+    }
+
+    #[test]
+    fn range_matches_family_and_bounds() {
+        let rule = BiosRange::family("necn").at_least(45);
+        assert!(rule.matches(&v("NECN45WW")));
+        assert!(rule.matches(&v("NECN50WW")));
+        assert!(!rule.matches(&v("NECN44WW")));
+        assert!(!rule.matches(&v("GKCN58WW")));
+
+        let bounded = BiosRange::family("NECN").at_least(45).at_most(49);
+        assert!(!bounded.matches(&v("NECN50WW")));
+        assert_eq!(bounded.to_string(), "NECN build 45..=49");
+    }
+}
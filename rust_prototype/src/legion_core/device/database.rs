@@ -0,0 +1,214 @@
+use super::bios::{BiosRange, BiosVersion};
+use crate::legion_core::hw::battery::ChargeMode;
+use crate::legion_core::hw::power::PowerProfile;
+use crate::legion_core::paths;
+use log::{info, warn};
+use serde::{Serialize, Deserialize};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Schema of `data/models.json` and of override files. Bump on incompatible changes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Overrides the location of the user model file (default: `<config dir>/models.json`).
+pub const OVERRIDE_ENV: &str = "LEGION_MODEL_DB";
+
+const EMBEDDED: &str = include_str!("../../../data/models.json");
+
+/// Which keyboard backlight controller a model has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyboardBacklight {
+    #[default]
+    None,
+    /// Single-colour backlight driven through WMI.
+    White,
+    /// ITE 4-zone RGB controller (33-byte feature report).
+    FourZoneRgb,
+    /// Per-key "Legion Spectrum" RGB.
+    Spectrum,
+}
+
+/// What a model/BIOS combination is known to offer. Controls not listed here are not shown.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ModelCapabilities {
+    #[serde(default)]
+    pub thermal_modes: Vec<PowerProfile>,
+    #[serde(default)]
+    pub charge_modes: Vec<ChargeMode>,
+    #[serde(default)]
+    pub keyboard_backlight: KeyboardBacklight,
+    #[serde(default)]
+    pub fan_table: bool,
+    /// Display panel overdrive toggle (GameZone `SetODStatus`).
+    #[serde(default)]
+    pub overdrive: bool,
+    /// Switchable iGPU / hybrid / dGPU-only mode (GameZone `SetIGPUModeStatus`).
+    #[serde(default)]
+    pub hybrid_graphics: bool,
+}
+
+impl ModelCapabilities {
+    pub fn offers_thermal_mode(&self, profile: PowerProfile) -> bool {
+        self.thermal_modes.contains(&profile)
+    }
+
+    pub fn offers_charge_mode(&self, mode: ChargeMode) -> bool {
+        self.charge_modes.contains(&mode)
+    }
+
+    /// Firmware-reported profile choices narrowed to the ones this model is known to handle.
+    pub fn filter_thermal_modes(&self, choices: &[PowerProfile]) -> Vec<PowerProfile> {
+        choices.iter().copied().filter(|p| self.offers_thermal_mode(*p)).collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelEntry {
+    /// Four-character Lenovo machine type, e.g. `83DV`.
+    pub machine_type: String,
+    pub name: String,
+    /// Firmware this entry applies to; absent means every BIOS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bios: Option<BiosRange>,
+    pub capabilities: ModelCapabilities,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl ModelEntry {
+    fn matches(&self, machine_type: &str, bios: Option<&BiosVersion>) -> bool {
+        if !self.machine_type.eq_ignore_ascii_case(machine_type) {
+            return false;
+        }
+        match (&self.bios, bios) {
+            (None, _) => true,
+            (Some(range), Some(version)) => range.matches(version),
+            // A BIOS-specific entry never matches firmware we cannot parse.
+            (Some(_), None) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelDatabase {
+    pub schema_version: u32,
+    /// Data revision, e.g. `2026.10.1`; reported so bug reports say which data was used.
+    pub revision: String,
+    pub models: Vec<ModelEntry>,
+}
+
+impl ModelDatabase {
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let db: ModelDatabase = serde_json::from_str(json)?;
+        if db.schema_version != SCHEMA_VERSION {
+            return Err(format!("Model database schema {} is not supported (expected {})", db.schema_version, SCHEMA_VERSION).into());
+        }
+        Ok(db)
+    }
+
+    /// The database compiled into the binary.
+    pub fn embedded() -> Self {
+        Self::from_json(EMBEDDED).expect("embedded data/models.json is valid")
+    }
+
+    /// Puts `overrides` ahead of the current entries so they win for the same machine.
+    pub fn apply_overrides(&mut self, overrides: ModelDatabase) {
+        self.revision = format!("{}+{}", self.revision, overrides.revision);
+        let mut models = overrides.models;
+        models.append(&mut self.models);
+        self.models = models;
+    }
+
+    /// First entry for this machine type whose BIOS range (if any) covers `bios`.
+    pub fn lookup(&self, machine_type: &str, bios: Option<&BiosVersion>) -> Option<&ModelEntry> {
+        self.models.iter().find(|entry| entry.matches(machine_type, bios))
+    }
+}
+
+/// Where the user override file is read from.
+pub fn override_path() -> Option<PathBuf> {
+    match env::var_os(OVERRIDE_ENV) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => paths::config_dir().map(|dir| dir.join("models.json")),
+    }
+}
+
+/// Embedded database plus the user override file, loaded once per process.
+///
+/// A broken override file is logged and ignored rather than taking the app down.
+pub fn active() -> &'static ModelDatabase {
+    static DATABASE: OnceLock<ModelDatabase> = OnceLock::new();
+    DATABASE.get_or_init(|| {
+        let mut db = ModelDatabase::embedded();
+        if let Some(path) = override_path().filter(|p| p.exists()) {
+            match fs::read_to_string(&path).map_err(Box::<dyn Error>::from).and_then(|json| ModelDatabase::from_json(&json)) {
+                Ok(overrides) => {
+                    info!("Loaded {} model override(s) from {}", overrides.models.len(), path.display());
+                    db.apply_overrides(overrides);
+                }
+                Err(e) => warn!("Ignoring model override file {}: {}", path.display(), e),
+            }
+        }
+        db
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_database_covers_83dv_on_necn() {
+        let db = ModelDatabase::embedded();
+        let bios = BiosVersion::parse("NECN50WW");
+        let entry = db.lookup("83DV", bios.as_ref()).unwrap();
+        assert_eq!(entry.name, "LOQ 15IRX9");
+        assert_eq!(entry.capabilities.keyboard_backlight, KeyboardBacklight::FourZoneRgb);
+        assert!(entry.capabilities.offers_charge_mode(ChargeMode::Rapid));
+        assert!(!entry.capabilities.offers_thermal_mode(PowerProfile::Custom));
+        assert!(entry.capabilities.fan_table);
+
+        // Same machine type on foreign or unparseable firmware is not assumed to behave the same.
+        assert!(db.lookup("83DV", BiosVersion::parse("GKCN58WW").as_ref()).is_none());
+        assert!(db.lookup("83DV", None).is_none());
+        assert!(db.lookup("82RD", bios.as_ref()).is_none());
+    }
+
+    #[test]
+    fn overrides_take_precedence_and_respect_bios_ranges() {
+        let mut db = ModelDatabase::embedded();
+        db.apply_overrides(ModelDatabase::from_json(r#"{
+            "schema_version": 1,
+            "revision": "local",
+            "models": [
+                { "machine_type": "83DV", "name": "LOQ (old BIOS)", "bios": { "family": "NECN", "max_build": 44 },
+                  "capabilities": { "thermal_modes": ["quiet", "balanced"] } },
+                { "machine_type": "82rd", "name": "Legion 5 15ARH7H",
+                  "capabilities": { "thermal_modes": ["quiet", "balanced", "performance", "custom"], "keyboard_backlight": "white" } }
+            ]
+        }"#).unwrap());
+
+        let old = db.lookup("83DV", BiosVersion::parse("NECN40WW").as_ref()).unwrap();
+        assert_eq!(old.name, "LOQ (old BIOS)");
+        assert!(old.capabilities.charge_modes.is_empty());
+        assert_eq!(db.lookup("83DV", BiosVersion::parse("NECN50WW").as_ref()).unwrap().name, "LOQ 15IRX9");
+
+        let legion = db.lookup("82RD", None).unwrap();
+        assert_eq!(legion.capabilities.keyboard_backlight, KeyboardBacklight::White);
+        assert_eq!(
+            legion.capabilities.filter_thermal_modes(&[PowerProfile::Quiet, PowerProfile::BalancedPerformance, PowerProfile::Custom]),
+            vec![PowerProfile::Quiet, PowerProfile::Custom]
+        );
+        assert_eq!(db.revision, format!("{}+local", ModelDatabase::embedded().revision));
+    }
+
+    #[test]
+    fn rejects_unknown_schema() {
+        assert!(ModelDatabase::from_json(r#"{ "schema_version": 2, "revision": "x", "models": [] }"#).is_err());
+        assert!(ModelDatabase::from_json(r#"{ "schema_version": 1, "revision": "x", "models": [ { "machine_type": "83DV" } ] }"#).is_err());
+    }
+}
@@ -1,57 +1,160 @@
-use crate::platform::windows::WmiQueryHandler;
-use super::models::{LaptopModel, Series};
-use log::{info, warn};
-use std::error::Error;
-
-pub fn detect_device() -> Result<LaptopModel, Box<dyn Error>> {
-    let wmi = WmiQueryHandler::new()?;
-    
-    // 1. Query Manufacturer
-    let manufacturer = wmi.get_manufacturer()?;
-    let manuf_upper = manufacturer.to_uppercase();
-    
-    if !manuf_upper.contains("LENOVO") {
-        return Ok(LaptopModel {
-            manufacturer,
-            model_name: "Unknown".to_string(),
+use crate::legion_core::backend;
+use crate::legion_core::result::HardwareResult;
+use super::bios::BiosVersion;
+use super::database::{self, ModelDatabase};
+use super::models::{DeviceIdentity, LaptopModel, Series};
+use super::naming::parse_marketing_name;
+
+pub fn detect_device() -> HardwareResult<LaptopModel> {
+    let identity = backend::active().identity()?;
+    Ok(model_from_identity(identity, database::active()))
+}
+
+/// Builds a `LaptopModel` from raw identity strings and a model database. Pure, so it can be tested with fixtures.
+pub fn model_from_identity(identity: DeviceIdentity, db: &ModelDatabase) -> LaptopModel {
+    // 1. Check Manufacturer
+    if !identity.manufacturer.to_uppercase().contains("LENOVO") {
+        return LaptopModel {
+            manufacturer: identity.manufacturer,
+            machine_type: "Unknown".to_string(),
+            marketing_model: "Unknown".to_string(),
             bios_version: "Unknown".to_string(),
+            bios: None,
+            capabilities: None,
             series: Series::Unknown,
+            generation: None,
+            screen_size: None,
+            platform_code: None,
             supported: false,
-        });
+        };
     }
 
-    // 2. Query Model
-    let model_name = wmi.get_model()?;
-    let series = determine_series(&model_name);
-
-    // 3. Query BIOS
-    let bios_version = wmi.get_bios_version()?;
-
-    // 4. Mark Supported
-    let supported = match series {
-        Series::Legion | Series::LOQ => true,
-        _ => false,
+    // 2. Split machine type and marketing name
+    let machine_type = machine_type(&identity.product_name);
+    let marketing_model = marketing_name(&identity);
+    let parsed = parse_marketing_name(&marketing_model);
+    let series = match parsed.series {
+        // Machine types whose firmware does not fill in the marketing name.
+        Series::Unknown if machine_type == "83DV" => Series::LOQ,
+        series => series,
     };
 
-    Ok(LaptopModel {
-        manufacturer,
-        model_name,
+    // 3. Only machines listed in the model database are writable
+    let bios = BiosVersion::parse(&identity.bios_version);
+    let capabilities = db.lookup(&machine_type, bios.as_ref()).map(|entry| entry.capabilities.clone());
+    let supported = capabilities.is_some();
+
+    LaptopModel {
+        manufacturer: identity.manufacturer,
+        machine_type,
+        marketing_model,
         series,
-        bios_version,
+        generation: parsed.generation,
+        screen_size: parsed.screen_size,
+        platform_code: parsed.platform_code,
+        bios,
+        bios_version: identity.bios_version,
+        capabilities,
         supported,
-    })
+    }
 }
 
-fn determine_series(model: &str) -> Series {
-    let model_upper = model.to_uppercase();
-    
-    if model_upper.contains("LEGION") {
-        Series::Legion
-    } else if model_upper.contains("LOQ") || model_upper.contains("83DV") {
-        Series::LOQ
-    } else if model_upper.contains("IDEAPAD") {
-        Series::IdeaPad
+/// Lenovo MTMs are 4 characters of machine type followed by an optional model/region suffix.
+fn machine_type(product_name: &str) -> String {
+    let trimmed = product_name.trim();
+    if trimmed.len() >= 4 && trimmed.chars().all(|c| c.is_ascii_alphanumeric()) {
+        trimmed[..4].to_uppercase()
     } else {
-        Series::Unknown
+        trimmed.to_string()
+    }
+}
+
+/// Marketing name from `product_version`, falling back to the family, then the raw product name.
+fn marketing_name(identity: &DeviceIdentity) -> String {
+    // Firmware placeholders seen in the wild when the field is not programmed.
+    let usable = |s: &str| {
+        let upper = s.trim().to_uppercase();
+        !upper.is_empty() && upper != "LENOVO" && !upper.contains("TO BE FILLED") && upper != "NONE"
+    };
+
+    [Some(identity.product_version.as_str()), identity.product_family.as_deref()]
+        .into_iter()
+        .flatten()
+        .find(|s| usable(s))
+        .unwrap_or(&identity.product_name)
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model_from_identity_in_db(identity: DeviceIdentity) -> LaptopModel {
+        model_from_identity(identity, &ModelDatabase::embedded())
+    }
+
+    fn identity(product_name: &str, product_version: &str) -> DeviceIdentity {
+        DeviceIdentity {
+            manufacturer: "LENOVO".to_string(),
+            product_name: product_name.to_string(),
+            product_version: product_version.to_string(),
+            product_family: None,
+            bios_version: "NECN50WW".to_string(),
+        }
+    }
+
+    #[test]
+    fn splits_machine_type_and_marketing_name() {
+        let model = model_from_identity_in_db(identity("83DV00ABUS", "LOQ 15IRX9"));
+        assert_eq!(model.machine_type, "83DV");
+        assert_eq!(model.marketing_model, "LOQ 15IRX9");
+        assert_eq!(model.series, Series::LOQ);
+        assert_eq!(model.generation, Some(9));
+        assert_eq!(model.screen_size, Some(15));
+        assert_eq!(model.platform_code.as_deref(), Some("IRX"));
+        assert_eq!(model.bios.as_ref().map(|b| (b.family.as_str(), b.build)), Some(("NECN", 50)));
+        assert!(model.supported);
+    }
+
+    #[test]
+    fn falls_back_to_family_when_version_is_a_placeholder() {
+        let mut id = identity("82RD", "Lenovo");
+        id.product_family = Some("Legion 5 15ARH7H".to_string());
+        let model = model_from_identity_in_db(id);
+        assert_eq!(model.marketing_model, "Legion 5 15ARH7H");
+        assert_eq!(model.series, Series::Legion5);
+        // Recognised series alone does not make a machine writable.
+        assert!(!model.supported);
+    }
+
+    #[test]
+    fn capabilities_come_from_the_database() {
+        let model = model_from_identity_in_db(identity("83DV", "LOQ 15IRX9"));
+        let caps = model.capabilities.expect("83DV/NECN is in the embedded database");
+        assert!(caps.fan_table);
+
+        let mut id = identity("83DV", "LOQ 15IRX9");
+        id.bios_version = "XXCN10WW".to_string();
+        let model = model_from_identity_in_db(id);
+        assert_eq!(model.capabilities, None);
+        assert!(!model.supported);
+    }
+
+    #[test]
+    fn blank_loq_marketing_name_falls_back_to_machine_type() {
+        let model = model_from_identity_in_db(identity("83DV", ""));
+        assert_eq!(model.marketing_model, "83DV");
+        assert_eq!(model.series, Series::LOQ);
+        assert!(model.supported);
+    }
+
+    #[test]
+    fn non_lenovo_is_unsupported() {
+        let mut id = identity("Blade 15", "1.0");
+        id.manufacturer = "Razer".to_string();
+        let model = model_from_identity_in_db(id);
+        assert_eq!(model.series, Series::Unknown);
+        assert!(!model.supported);
     }
 }
@@ -1,2 +1,5 @@
 pub mod models;
 pub mod detect;
+pub mod naming;
+pub mod bios;
+pub mod database;
@@ -1,19 +1,65 @@
 use serde::{Serialize, Deserialize};
+use super::bios::BiosVersion;
+use super::database::ModelCapabilities;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Series {
+    Legion5,
+    /// "Legion 5 Pro" and its 2023+ rename "Legion Pro 5".
+    Legion5Pro,
+    /// "Legion 7", "Legion 7i" and "Legion Pro 7".
+    Legion7,
+    LegionSlim,
+    LegionGo,
+    /// Any other Legion line (Y-series, Legion 9, ...).
     Legion,
     LOQ,
     IdeaPad,
     Unknown,
 }
 
+impl Series {
+    pub fn is_legion(&self) -> bool {
+        matches!(self, Series::Legion5 | Series::Legion5Pro | Series::Legion7
+            | Series::LegionSlim | Series::LegionGo | Series::Legion)
+    }
+}
+
+/// Raw identity strings as the firmware reports them (SMBIOS / DMI).
+///
+/// Lenovo puts the machine type (e.g. `83DV`, sometimes the full MTM `83DV00ABUS`) in
+/// `product_name` and the marketing name (e.g. `LOQ 15IRX9`) in `product_version`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceIdentity {
+    pub manufacturer: String,
+    pub product_name: String,
+    pub product_version: String,
+    pub product_family: Option<String>,
+    pub bios_version: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LaptopModel {
     pub manufacturer: String,
-    pub model_name: String,
+    /// Four-character Lenovo machine type, e.g. `83DV`.
+    pub machine_type: String,
+    /// Marketing name, e.g. `LOQ 15IRX9`.
+    pub marketing_model: String,
     pub series: Series,
+    /// Model generation from the marketing name (`9` in `15IRX9`).
+    pub generation: Option<u8>,
+    /// Nominal screen size in inches (`15` in `15IRX9`).
+    pub screen_size: Option<u8>,
+    /// CPU platform code (`IRX` in `15IRX9`); the first letter is I(ntel) or A(MD).
+    pub platform_code: Option<String>,
+    /// Raw BIOS string as reported by the firmware, e.g. `NECN50WW`.
     pub bios_version: String,
+    /// `bios_version` parsed into family and build; `None` if it is not in Lenovo's format.
+    pub bios: Option<BiosVersion>,
+    /// Capabilities from the model database; `None` if this machine/BIOS is not listed.
+    pub capabilities: Option<ModelCapabilities>,
+    /// Set only for machines found in the model database.
     pub supported: bool,
 }
 
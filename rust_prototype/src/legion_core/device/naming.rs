@@ -0,0 +1,121 @@
+use super::models::Series;
+
+/// Fields encoded in a Lenovo marketing name such as `LOQ 15IRX9` or `Legion 5 Pro 16ACH6H`.
+///
+/// The trailing token is `<screen inches><platform code><generation><variant>`:
+/// `16` + `ACH` (AMD, H-series CPU) + `6` + `H`. Older names (`Legion Y540-15IRH`) omit the generation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedName {
+    pub series: Series,
+    pub screen_size: Option<u8>,
+    pub platform_code: Option<String>,
+    pub generation: Option<u8>,
+}
+
+pub fn parse_marketing_name(name: &str) -> ParsedName {
+    let upper = name.to_uppercase();
+    let words: Vec<&str> = upper.split(|c: char| c.is_whitespace() || c == '-').filter(|w| !w.is_empty()).collect();
+
+    let platform = words.iter().rev().find_map(|w| parse_platform_token(w));
+
+    ParsedName {
+        series: series_from_words(&words),
+        screen_size: platform.as_ref().map(|p| p.0),
+        platform_code: platform.as_ref().map(|p| p.1.clone()),
+        generation: platform.and_then(|p| p.2),
+    }
+}
+
+fn series_from_words(words: &[&str]) -> Series {
+    let has = |w: &str| words.contains(&w);
+
+    if has("LOQ") {
+        return Series::LOQ;
+    }
+    if !has("LEGION") {
+        return if has("IDEAPAD") { Series::IdeaPad } else { Series::Unknown };
+    }
+
+    if has("GO") {
+        Series::LegionGo
+    } else if has("SLIM") {
+        Series::LegionSlim
+    } else if has("PRO") && (has("5") || has("5I")) {
+        // "Legion 5 Pro" (2021-22) and its successor "Legion Pro 5" (2023+).
+        Series::Legion5Pro
+    } else if has("5") || has("5I") {
+        Series::Legion5
+    } else if has("7") || has("7I") {
+        // Includes "Legion Pro 7".
+        Series::Legion7
+    } else {
+        Series::Legion
+    }
+}
+
+/// Splits `15IRX9` / `16ACH6H` / `8APU1` / `15IRH` into (screen, platform, generation).
+fn parse_platform_token(token: &str) -> Option<(u8, String, Option<u8>)> {
+    let screen_len = token.chars().take_while(|c| c.is_ascii_digit()).count();
+    if !(1..=2).contains(&screen_len) {
+        return None;
+    }
+    let rest = &token[screen_len..];
+
+    let platform_len = rest.chars().take_while(|c| c.is_ascii_uppercase()).count();
+    if !(2..=4).contains(&platform_len) {
+        return None;
+    }
+    let after_platform = &rest[platform_len..];
+
+    let gen_len = after_platform.chars().take_while(|c| c.is_ascii_digit()).count();
+    if gen_len > 2 {
+        return None;
+    }
+    let variant = &after_platform[gen_len..];
+    if !variant.chars().all(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+
+    let screen = token[..screen_len].parse().ok()?;
+    let generation = if gen_len > 0 { after_platform[..gen_len].parse().ok() } else { None };
+    Some((screen, rest[..platform_len].to_string(), generation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(series: Series, screen: u8, platform: &str, generation: Option<u8>) -> ParsedName {
+        ParsedName {
+            series,
+            screen_size: Some(screen),
+            platform_code: Some(platform.to_string()),
+            generation,
+        }
+    }
+
+    #[test]
+    fn parses_current_names() {
+        assert_eq!(parse_marketing_name("LOQ 15IRX9"), parsed(Series::LOQ, 15, "IRX", Some(9)));
+        assert_eq!(parse_marketing_name("Legion 5 15ARH7H"), parsed(Series::Legion5, 15, "ARH", Some(7)));
+        assert_eq!(parse_marketing_name("Legion 5 Pro 16ACH6H"), parsed(Series::Legion5Pro, 16, "ACH", Some(6)));
+        assert_eq!(parse_marketing_name("Legion Pro 5 16IRX8"), parsed(Series::Legion5Pro, 16, "IRX", Some(8)));
+        assert_eq!(parse_marketing_name("Legion Pro 7 16IRX8H"), parsed(Series::Legion7, 16, "IRX", Some(8)));
+        assert_eq!(parse_marketing_name("Legion Slim 5 16APH8"), parsed(Series::LegionSlim, 16, "APH", Some(8)));
+        assert_eq!(parse_marketing_name("Legion Go 8APU1"), parsed(Series::LegionGo, 8, "APU", Some(1)));
+    }
+
+    #[test]
+    fn parses_older_names() {
+        assert_eq!(parse_marketing_name("Legion 5 15IMH05H"), parsed(Series::Legion5, 15, "IMH", Some(5)));
+        assert_eq!(parse_marketing_name("Legion Y540-15IRH"), parsed(Series::Legion, 15, "IRH", None));
+    }
+
+    #[test]
+    fn unparseable_names_keep_series_only() {
+        let name = parse_marketing_name("IdeaPad Gaming 3");
+        assert_eq!(name.series, Series::IdeaPad);
+        assert_eq!(name.platform_code, None);
+        assert_eq!(parse_marketing_name("83DV").series, Series::Unknown);
+    }
+}
@@ -1,67 +1,341 @@
-use crate::platform::windows::{WmiQueryHandler, EnergyDriver};
-use log::warn;
-use std::error::Error;
+use crate::legion_core::backend::{self, HardwareBackend};
+use crate::legion_core::result::{HardwareError, HardwareReadResult, HardwareResult, WriteError, WriteOutcome};
+use crate::legion_core::safety::guards::WriteToken;
+use crate::legion_core::safety::pipeline::verified_write;
+use crate::legion_core::safety::undo::{self, UndoChange, UndoHistory};
+use serde::{Serialize, Deserialize};
+use std::fmt;
 
-#[derive(Debug)]
+/// Lenovo battery charge modes. Conservation and Rapid are mutually exclusive in firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChargeMode {
+    Normal,
+    /// Holds the battery at ~60% to extend its lifespan.
+    Conservation,
+    Rapid,
+}
+
+/// `chargeModeBits` flags returned by the EnergyDrv charge-mode read (selector 0xFF).
+pub const ENERGY_CONSERVATION_BIT: u32 = 0x20;
+pub const ENERGY_RAPID_CHARGE_BIT: u32 = 0x04;
+
+impl ChargeMode {
+    /// Decodes the `chargeModeBits` returned by the EnergyDrv read. Other bits (e.g.
+    /// 0x80) are ignored; conservation and rapid together are rejected as invalid.
+    pub fn from_energy_bits(bits: u32) -> HardwareResult<Self> {
+        match (bits & ENERGY_CONSERVATION_BIT != 0, bits & ENERGY_RAPID_CHARGE_BIT != 0) {
+            (false, false) => Ok(ChargeMode::Normal),
+            (true, false) => Ok(ChargeMode::Conservation),
+            (false, true) => Ok(ChargeMode::Rapid),
+            (true, true) => Err(HardwareError::InvalidData(format!(
+                "EnergyDrv reported conservation and rapid charge together (bits 0x{:02X}, energy_battery_mode_conflict)", bits))),
+        }
+    }
+
+    /// Firmware steps from `self` to `target`. Only one mode may be on at a time, so a
+    /// switch between Conservation and Rapid turns the active one off first.
+    pub fn steps_to(self, target: ChargeMode) -> Vec<ChargeModeStep> {
+        if self == target {
+            return Vec::new();
+        }
+        let off = match self {
+            ChargeMode::Normal => None,
+            ChargeMode::Conservation => Some(ChargeModeStep::ConservationOff),
+            ChargeMode::Rapid => Some(ChargeModeStep::RapidOff),
+        };
+        let on = match target {
+            ChargeMode::Normal => None,
+            ChargeMode::Conservation => Some(ChargeModeStep::ConservationOn),
+            ChargeMode::Rapid => Some(ChargeModeStep::RapidOn),
+        };
+        off.into_iter().chain(on).collect()
+    }
+}
+
+/// One charge-mode write. On Windows each is a single EnergyDrv selector:
+/// ConservationOn 0x3, ConservationOff 0x5, RapidOn 0x7, RapidOff 0x8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChargeModeStep {
+    ConservationOn,
+    ConservationOff,
+    RapidOn,
+    RapidOff,
+}
+
+impl ChargeModeStep {
+    /// The step that undoes this one.
+    pub fn inverse(self) -> ChargeModeStep {
+        match self {
+            ChargeModeStep::ConservationOn => ChargeModeStep::ConservationOff,
+            ChargeModeStep::ConservationOff => ChargeModeStep::ConservationOn,
+            ChargeModeStep::RapidOn => ChargeModeStep::RapidOff,
+            ChargeModeStep::RapidOff => ChargeModeStep::RapidOn,
+        }
+    }
+
+    fn apply(self, backend: &dyn HardwareBackend) -> HardwareResult<()> {
+        match self {
+            ChargeModeStep::ConservationOn => backend.set_conservation_mode(true),
+            ChargeModeStep::ConservationOff => backend.set_conservation_mode(false),
+            ChargeModeStep::RapidOn => backend.set_rapid_charge(true),
+            ChargeModeStep::RapidOff => backend.set_rapid_charge(false),
+        }
+    }
+}
+
+impl fmt::Display for ChargeMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChargeMode::Normal => write!(f, "Normal"),
+            ChargeMode::Conservation => write!(f, "Conservation"),
+            ChargeMode::Rapid => write!(f, "Rapid Charge"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub enum ChargingState {
+    Charging,
+    Discharging,
+    Full,
+    /// On AC but not charging (e.g. held at the conservation limit).
+    NotCharging,
+    #[default]
+    Unknown,
+}
+
+impl fmt::Display for ChargingState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChargingState::Charging => write!(f, "Charging"),
+            ChargingState::Discharging => write!(f, "Discharging"),
+            ChargingState::Full => write!(f, "Full"),
+            ChargingState::NotCharging => write!(f, "Not Charging"),
+            ChargingState::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+/// Battery snapshot. `None` fields are not reported by the active backend.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct BatteryStatus {
     pub charge_percent: u16,
-    pub is_charging: bool,
+    pub state: ChargingState,
+    pub ac_online: Option<bool>,
+    pub energy_now_wh: Option<f32>,
+    pub energy_full_wh: Option<f32>,
+    pub energy_design_wh: Option<f32>,
+    pub voltage_v: Option<f32>,
+    /// Instantaneous charge or discharge power.
+    pub power_w: Option<f32>,
+    pub cycle_count: Option<u32>,
 }
 
-pub fn get_battery_status() -> Option<BatteryStatus> {
-    let wmi = match WmiQueryHandler::new() {
-        Ok(w) => w,
-        Err(e) => {
-            warn!("Failed to init WMI for battery: {}", e);
-            return None;
-        }
-    };
-
-    match wmi.get_battery_info() {
-        Ok((charge, status)) => {
-            // Win32_Battery: BatteryStatus 2 = AC Power (Charging or Charged)
-            let is_charging = status == 2; 
-            Some(BatteryStatus {
-                charge_percent: charge,
-                is_charging,
-            })
-        },
-        Err(e) => {
-            warn!("Failed to read battery: {}", e);
-            None
+impl BatteryStatus {
+    pub fn is_charging(&self) -> bool {
+        self.state == ChargingState::Charging
+    }
+
+    /// Full-charge capacity as a percentage of design capacity.
+    pub fn health_percent(&self) -> Option<f32> {
+        match (self.energy_full_wh, self.energy_design_wh) {
+            (Some(full), Some(design)) if design > 0.0 => Some(full / design * 100.0),
+            _ => None,
         }
     }
 }
 
-pub fn get_conservation_mode() -> Option<bool> {
-    // Reading not yet ported from LLT (requires IOCTL read logic).
-    // Returning None ensures GUI doesn't show false state.
-    None
+pub fn get_charge_mode() -> HardwareReadResult<ChargeMode> {
+    HardwareReadResult::logged("charge mode", backend::active().charge_mode())
+}
+
+pub fn get_conservation_mode() -> HardwareReadResult<bool> {
+    // A failed read keeps its status so the GUI never shows a guessed state.
+    HardwareReadResult::logged("conservation mode", backend::active().conservation_mode())
 }
 
-pub fn set_conservation_mode(enable: bool) -> Result<(), Box<dyn Error>> {
-    // 1. Safety Check: Global Write Lock
-    if !crate::legion_core::safety::guards::GlobalWriteLock::is_write_allowed() {
-        return Err("Write operations are locked. Use --set-conservation-mode explicitly.".into());
+/// `WriteToken` operation for every charge-mode setter.
+pub const CHARGE_MODE_OPERATION: &str = "Charge Mode";
+
+/// `expected` is the mode the caller last saw; a different current mode is a `Conflict`.
+pub fn set_charge_mode(mode: ChargeMode, expected: Option<ChargeMode>, token: &WriteToken) -> Result<WriteOutcome, WriteError> {
+    write_charge_mode(expected, token, |_| mode)
+}
+
+/// Conservation on/off through the charge-mode state machine; turning it off while
+/// another mode is active changes nothing.
+pub fn set_conservation_mode(enable: bool, expected: Option<ChargeMode>, token: &WriteToken) -> Result<WriteOutcome, WriteError> {
+    write_charge_mode(expected, token, |current| toggled(current, ChargeMode::Conservation, enable))
+}
+
+pub fn get_rapid_charge() -> HardwareReadResult<bool> {
+    HardwareReadResult::logged("rapid charge", backend::active().rapid_charge())
+}
+
+pub fn set_rapid_charge(enable: bool, expected: Option<ChargeMode>, token: &WriteToken) -> Result<WriteOutcome, WriteError> {
+    write_charge_mode(expected, token, |current| toggled(current, ChargeMode::Rapid, enable))
+}
+
+fn toggled(current: ChargeMode, mode: ChargeMode, enable: bool) -> ChargeMode {
+    match (enable, current == mode) {
+        (true, _) => mode,
+        (false, true) => ChargeMode::Normal,
+        (false, false) => current,
     }
+}
 
-    // 2. Execute via EnergyDriver (IOCTL)
-    let driver = EnergyDriver::new()?;
-    driver.set_conservation_mode(enable)?;
-    
-    Ok(())
+fn write_charge_mode(expected: Option<ChargeMode>, token: &WriteToken, target: impl FnOnce(ChargeMode) -> ChargeMode) -> Result<WriteOutcome, WriteError> {
+    // 1. Safety Check: a write token for this operation
+    token.authorize(CHARGE_MODE_OPERATION)?;
+
+    // 2. Execute via the active backend (EnergyDrv IOCTL on Windows)
+    change_charge_mode(backend::active(), undo::active(), token, expected, target)
+}
+
+/// The charge-mode state machine, run through `verified_write`: captures the current
+/// mode, writes the steps towards `target(current)` in order and verifies the result by
+/// readback. An unreadable or conflicting current state is refused before anything is written.
+/// If a later step fails, the steps already written are undone in reverse order to
+/// restore the captured mode, and the error is `PartiallyApplied`. A verified change is
+/// recorded in `history`.
+pub fn change_charge_mode(backend: &dyn HardwareBackend, history: Option<&UndoHistory>, token: &WriteToken, expected: Option<ChargeMode>, target: impl FnOnce(ChargeMode) -> ChargeMode) -> Result<WriteOutcome, WriteError> {
+    let mut change = None;
+    let result = verified_write(token, expected.as_ref(), || backend.charge_mode(), |current| {
+        let current = current.map_err(|e| match e {
+            HardwareError::InvalidData(detail) => WriteError::Rejected(format!("Current charge mode is not a legal state: {}", detail)),
+            other => WriteError::Hardware(other),
+        })?;
+        let target = target(current);
+        change = Some(UndoChange::ChargeMode { before: current, after: target });
+
+        let steps = current.steps_to(target);
+        for (i, step) in steps.iter().enumerate() {
+            if let Err(e) = step.apply(backend) {
+                if i == 0 {
+                    return Err(e.into());
+                }
+                return Err(roll_back(backend, current, target, &steps[..i], *step, e));
+            }
+        }
+        Ok(target)
+    });
+    undo::record(history, token, &result, change);
+    result
 }
 
-pub fn get_rapid_charge() -> Option<bool> {
-    None
+/// Undoes `applied` after `failed` went wrong, and describes what the hardware was left in.
+fn roll_back(backend: &dyn HardwareBackend, current: ChargeMode, target: ChargeMode, applied: &[ChargeModeStep], failed: ChargeModeStep, error: HardwareError) -> WriteError {
+    let prefix = format!("Charge mode {} -> {}: {:?} applied, {:?} failed ({}: {})",
+        current, target, applied, failed, error.code(), error);
+    let rollback = applied.iter().rev().try_for_each(|step| step.inverse().apply(backend));
+    WriteError::PartiallyApplied(match (rollback, backend.charge_mode()) {
+        (Ok(()), Ok(mode)) if mode == current => format!("{}; rolled back to {}", prefix, mode),
+        (Ok(()), Ok(mode)) => format!("{}; rolled back, but the mode reads {}", prefix, mode),
+        (Ok(()), Err(e)) => format!("{}; rolled back, mode unreadable ({})", prefix, e.code()),
+        (Err(e), Ok(mode)) => format!("{}; rollback failed ({}: {}), left at {}", prefix, e.code(), e, mode),
+        (Err(e), Err(read)) => format!("{}; rollback failed ({}: {}), mode unreadable ({})", prefix, e.code(), e, read.code()),
+    })
 }
 
-pub fn set_rapid_charge(enable: bool) -> Result<(), Box<dyn Error>> {
-    if !crate::legion_core::safety::guards::GlobalWriteLock::is_write_allowed() {
-        return Err("Write operations are locked. Use --rapid-charge explicitly.".into());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legion_core::backend::simulated::{Fault, SimulatedBackend};
+    use serde_json::Value;
+
+    fn token() -> WriteToken {
+        crate::legion_core::safety::lock::tests::use_test_lock_file();
+        WriteToken::grant(CHARGE_MODE_OPERATION, "test", "test")
+    }
+
+    #[test]
+    fn switching_between_modes_turns_the_active_one_off_first() {
+        use ChargeModeStep::*;
+        assert_eq!(ChargeMode::Rapid.steps_to(ChargeMode::Conservation), vec![RapidOff, ConservationOn]);
+        assert_eq!(ChargeMode::Conservation.steps_to(ChargeMode::Rapid), vec![ConservationOff, RapidOn]);
+        assert_eq!(ChargeMode::Normal.steps_to(ChargeMode::Rapid), vec![RapidOn]);
+        assert_eq!(ChargeMode::Conservation.steps_to(ChargeMode::Normal), vec![ConservationOff]);
+        assert!(ChargeMode::Rapid.steps_to(ChargeMode::Rapid).is_empty());
     }
 
-    let driver = EnergyDriver::new()?;
-    driver.set_rapid_charge(enable)?;
-    Ok(())
+    #[test]
+    fn change_is_verified_by_readback() {
+        let sim = SimulatedBackend::new();
+        sim.apply_state_spec("charge_mode=rapid").unwrap();
+        assert_eq!(change_charge_mode(&sim, None, &token(), Some(ChargeMode::Rapid), |_| ChargeMode::Conservation), Ok(WriteOutcome::Verified));
+        assert_eq!(sim.charge_mode().unwrap(), ChargeMode::Conservation);
+
+        // Turning rapid off while conservation is active writes nothing.
+        sim.inject("set_conservation_mode", Fault::Failed).unwrap();
+        sim.inject("set_rapid_charge", Fault::Failed).unwrap();
+        change_charge_mode(&sim, None, &token(), None, |current| toggled(current, ChargeMode::Rapid, false)).unwrap();
+        assert_eq!(sim.charge_mode().unwrap(), ChargeMode::Conservation);
+    }
+
+    #[test]
+    fn failed_second_step_is_rolled_back() {
+        let sim = SimulatedBackend::new();
+        sim.apply_state_spec("charge_mode=rapid").unwrap();
+        sim.inject("set_conservation_mode", Fault::Failed).unwrap();
+        let err = change_charge_mode(&sim, None, &token(), Some(ChargeMode::Rapid), |_| ChargeMode::Conservation).unwrap_err();
+        assert_eq!(err.code(), "partially_applied");
+        assert!(err.to_string().contains("rolled back to Rapid Charge"), "{}", err);
+        assert_eq!(sim.charge_mode().unwrap(), ChargeMode::Rapid);
+    }
+
+    #[test]
+    fn unexpected_current_mode_is_a_conflict() {
+        let sim = SimulatedBackend::new();
+        sim.apply_state_spec("charge_mode=conservation").unwrap();
+        let err = change_charge_mode(&sim, None, &token(), Some(ChargeMode::Normal), |_| ChargeMode::Rapid).unwrap_err();
+        assert!(matches!(err, WriteError::Conflict(_)));
+        assert_eq!(sim.charge_mode().unwrap(), ChargeMode::Conservation);
+    }
+
+    #[test]
+    fn conflicting_current_state_is_refused_without_writing() {
+        let sim = SimulatedBackend::new();
+        sim.apply_state_spec("charge_mode_bits=0x24").unwrap();
+        let err = change_charge_mode(&sim, None, &token(), None, |_| ChargeMode::Normal).unwrap_err();
+        assert!(matches!(err, WriteError::Rejected(_)));
+        assert!(sim.conservation_mode().unwrap() && sim.rapid_charge().unwrap());
+    }
+
+    #[test]
+    fn unreadable_current_state_keeps_its_status() {
+        let sim = SimulatedBackend::new();
+        sim.inject("charge_mode", Fault::AccessDenied).unwrap();
+        let err = change_charge_mode(&sim, None, &token(), None, |_| ChargeMode::Rapid).unwrap_err();
+        assert_eq!(err.code(), "access_denied");
+        assert!(!sim.rapid_charge().unwrap());
+    }
+
+    #[test]
+    fn energy_bits_map_to_charge_modes() {
+        assert_eq!(ChargeMode::from_energy_bits(0x00).unwrap(), ChargeMode::Normal);
+        assert_eq!(ChargeMode::from_energy_bits(0x80).unwrap(), ChargeMode::Normal);
+        assert_eq!(ChargeMode::from_energy_bits(0x20).unwrap(), ChargeMode::Conservation);
+        assert_eq!(ChargeMode::from_energy_bits(0x04).unwrap(), ChargeMode::Rapid);
+    }
+
+    #[test]
+    fn conflicting_energy_bits_are_invalid_data() {
+        let err = ChargeMode::from_energy_bits(0x24).unwrap_err();
+        assert!(matches!(err, HardwareError::InvalidData(_)));
+        assert!(err.detail().contains("energy_battery_mode_conflict"));
+    }
+
+    #[test]
+    fn decodes_recorded_energy_driver_reads() {
+        let evidence: Value = serde_json::from_str(include_str!("../../../../hardware-evidence/83DV/NECN50WW-state-elevated.json")).unwrap();
+        let read = evidence["reads"].as_array().unwrap().iter()
+            .find(|r| r["capability"] == "BatteryChargeMode").unwrap();
+        assert_eq!(read["operation"], "0x831020F8");
+        assert_eq!(read["readSelector"], 0xFF);
+
+        let mode = ChargeMode::from_energy_bits(read["chargeModeBits"].as_u64().unwrap() as u32).unwrap();
+        assert_eq!(format!("{:?}", mode), read["value"]);
+    }
 }
@@ -0,0 +1,35 @@
+use serde::{Serialize, Deserialize};
+use std::fmt;
+
+/// Which GPU drives the display (GameZone `GetIGPUModeStatus`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IgpuMode {
+    /// Hybrid graphics: iGPU drives the panel, dGPU renders on demand.
+    Hybrid,
+    /// dGPU powered off.
+    IntegratedOnly,
+    /// Firmware switches to IntegratedOnly on battery.
+    Auto,
+}
+
+impl IgpuMode {
+    /// Decodes a `GetIGPUModeStatus` value. Confirmed by hardware-evidence/83DV (rawValue 1).
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        match raw {
+            0 => Some(IgpuMode::Hybrid),
+            1 => Some(IgpuMode::IntegratedOnly),
+            2 => Some(IgpuMode::Auto),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for IgpuMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IgpuMode::Hybrid => write!(f, "Hybrid"),
+            IgpuMode::IntegratedOnly => write!(f, "Integrated Only"),
+            IgpuMode::Auto => write!(f, "Auto"),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! In-memory `HidTransport` for tests: scripted collections and feature report
+//! responses, with every open and sent report recorded.
+
+use crate::legion_core::hw::hid_transport::{HidHandle, HidTransport};
+use crate::legion_core::hw::ite_device::HidCollection;
+use crate::legion_core::result::{HardwareError, HardwareResult};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+#[derive(Default)]
+pub struct MockHidTransport {
+    collections: Vec<HidCollection>,
+    /// Answers to `get_feature_report`, in order.
+    responses: Mutex<VecDeque<HardwareResult<Vec<u8>>>>,
+    /// Errors by operation: `enumerate`, `open`, `send_feature_report`.
+    faults: Mutex<HashMap<&'static str, HardwareError>>,
+    opened: Mutex<Vec<String>>,
+    sent: Mutex<Vec<(String, Vec<u8>)>>,
+}
+
+impl MockHidTransport {
+    pub fn new(collections: Vec<HidCollection>) -> Self {
+        Self { collections, ..Default::default() }
+    }
+
+    pub fn respond(self, response: HardwareResult<Vec<u8>>) -> Self {
+        self.responses.lock().unwrap().push_back(response);
+        self
+    }
+
+    pub fn fail(self, operation: &'static str, error: HardwareError) -> Self {
+        self.faults.lock().unwrap().insert(operation, error);
+        self
+    }
+
+    /// Paths opened, in order.
+    pub fn opened(&self) -> Vec<String> {
+        self.opened.lock().unwrap().clone()
+    }
+
+    /// (path, report) for every report sent, in order.
+    pub fn sent(&self) -> Vec<(String, Vec<u8>)> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    fn check(&self, operation: &str) -> HardwareResult<()> {
+        match self.faults.lock().unwrap().get(operation) {
+            Some(e) => Err(e.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl HidTransport for MockHidTransport {
+    fn enumerate(&self, vendor_id: u16) -> HardwareResult<Vec<HidCollection>> {
+        self.check("enumerate")?;
+        Ok(self.collections.iter().filter(|c| c.vendor_id == vendor_id).cloned().collect())
+    }
+
+    fn open(&self, path: &str) -> HardwareResult<Box<dyn HidHandle + '_>> {
+        self.check("open")?;
+        if !self.collections.iter().any(|c| c.path == path) {
+            return Err(HardwareError::Unavailable(format!("{} not found", path)));
+        }
+        self.opened.lock().unwrap().push(path.to_string());
+        Ok(Box::new(MockHandle { transport: self, path: path.to_string() }))
+    }
+}
+
+struct MockHandle<'a> {
+    transport: &'a MockHidTransport,
+    path: String,
+}
+
+impl HidHandle for MockHandle<'_> {
+    fn send_feature_report(&self, report: &[u8]) -> HardwareResult<()> {
+        self.transport.sent.lock().unwrap().push((self.path.clone(), report.to_vec()));
+        self.transport.check("send_feature_report")
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> HardwareResult<usize> {
+        let response = self.transport.responses.lock().unwrap().pop_front()
+            .unwrap_or_else(|| Err(HardwareError::TimedOut("No scripted feature report".to_string())))?;
+        let len = response.len().min(buf.len());
+        buf[..len].copy_from_slice(&response[..len]);
+        Ok(len)
+    }
+}
@@ -0,0 +1,22 @@
+//! HID access behind a trait, so the lighting code in `ite_device` runs against the real
+//! controller (`platform::hid::HidApiTransport`) or an in-memory mock in tests.
+
+use crate::legion_core::hw::ite_device::HidCollection;
+use crate::legion_core::result::HardwareResult;
+
+pub trait HidTransport: Send + Sync {
+    /// Every collection of `vendor_id`, with the length of its lighting feature report
+    /// for those that pass `ite_device::check_enumerated`.
+    fn enumerate(&self, vendor_id: u16) -> HardwareResult<Vec<HidCollection>>;
+    /// Opens one collection by `HidCollection::path`.
+    fn open(&self, path: &str) -> HardwareResult<Box<dyn HidHandle + '_>>;
+}
+
+/// An open HID collection.
+pub trait HidHandle {
+    /// Sends `report` as is; its first byte is the report ID.
+    fn send_feature_report(&self, report: &[u8]) -> HardwareResult<()>;
+    /// Reads the feature report whose ID is in `buf[0]`, returning the bytes read
+    /// (including the ID).
+    fn get_feature_report(&self, buf: &mut [u8]) -> HardwareResult<usize>;
+}
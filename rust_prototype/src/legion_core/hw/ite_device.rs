@@ -0,0 +1,316 @@
+//! Which HID collection is the ITE 4-zone keyboard controller.
+//!
+//! A collection is used only if all of these hold: vendor `048D`, a product ID on the
+//! allowlist, and a feature report of exactly `ite_report::REPORT_LEN` bytes, as the C#
+//! `FourZoneKeyboardHid` selects it. The usage page/usage is only reported: no captured
+//! descriptor pins it down. Other `048D:C9xx` devices (Spectrum keyboards, the LOQ's
+//! `C996`) are never opened, not even to read their report descriptor.
+
+use crate::legion_core::hw::hid_transport::{HidHandle, HidTransport};
+use crate::legion_core::hw::ite_report;
+use crate::legion_core::result::{HardwareError, HardwareResult};
+use log::{debug, info};
+use std::fmt;
+
+pub const VENDOR_ID: u16 = 0x048D;
+
+/// 4-zone controllers and the report ID each expects. The first byte of the 0xCC 0x16
+/// report doubles as the report ID, so reports are sent as is: never prefixed with 0x00,
+/// and never retried in another framing.
+pub const FOUR_ZONE_CONTROLLERS: &[(u16, u8)] = &[
+    (0xC935, 0xCC),
+    (0xC955, 0xCC),
+    (0xC993, 0xCC),
+];
+
+/// Second byte of the identity report (`CC 05` + VID/PID) the LOQ 83DV's controller
+/// returns for GetFeature instead of its lighting state.
+pub const IDENTITY_REPORT: u8 = 0x05;
+
+/// One HID collection as enumerated, with the length of its lighting feature report
+/// taken from the report descriptor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HidCollection {
+    pub path: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub usage_page: u16,
+    pub usage: u16,
+    /// Bytes in the feature report with the controller's report ID, including the ID.
+    pub feature_report_len: HardwareResult<usize>,
+}
+
+impl fmt::Display for HidCollection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04X}:{:04X} usage {:04X}:{:04X}", self.vendor_id, self.product_id, self.usage_page, self.usage)?;
+        match &self.feature_report_len {
+            Ok(len) => write!(f, ", {}-byte feature report", len),
+            Err(e) => write!(f, ", feature report unknown [{}]", e.code()),
+        }
+    }
+}
+
+/// Why a collection was not selected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    ProductNotAllowed,
+    ReportLength(usize),
+    DescriptorUnreadable(HardwareError),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rejection::ProductNotAllowed => write!(f, "product ID not on the 4-zone allowlist"),
+            Rejection::ReportLength(len) => write!(f, "feature report is {} bytes, expected {}", len, ite_report::REPORT_LEN),
+            Rejection::DescriptorUnreadable(e) => write!(f, "report descriptor unreadable: {}", e),
+        }
+    }
+}
+
+/// Report ID `product_id` expects. A controller without one is `Unsupported` rather than probed.
+pub fn report_id(product_id: u16) -> HardwareResult<u8> {
+    FOUR_ZONE_CONTROLLERS.iter().find(|(pid, _)| *pid == product_id).map(|(_, id)| *id).ok_or_else(|| HardwareError::Unsupported(
+        format!("No report ID known for lighting controller PID {:04x}", product_id)))
+}
+
+/// The selection rule decidable from enumeration alone. `HidTransport::enumerate` opens
+/// a collection to read its report descriptor only if it passes this.
+pub fn check_enumerated(product_id: u16) -> Result<(), Rejection> {
+    match report_id(product_id) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(Rejection::ProductNotAllowed),
+    }
+}
+
+/// Checks one `048D` collection against every selection rule.
+pub fn check(collection: &HidCollection) -> Result<(), Rejection> {
+    check_enumerated(collection.product_id)?;
+    match &collection.feature_report_len {
+        Ok(len) if *len == ite_report::REPORT_LEN => Ok(()),
+        Ok(len) => Err(Rejection::ReportLength(*len)),
+        Err(e) => Err(Rejection::DescriptorUnreadable(e.clone())),
+    }
+}
+
+/// The first collection that passes `check`. Otherwise the error of an allowlisted
+/// lighting collection whose descriptor could not be read (e.g. `AccessDenied`), or
+/// `Unavailable` naming every `048D` collection and why it was passed over.
+pub fn select(collections: &[HidCollection]) -> HardwareResult<&HidCollection> {
+    let ite: Vec<&HidCollection> = collections.iter().filter(|c| c.vendor_id == VENDOR_ID).collect();
+    if let Some(selected) = ite.iter().find(|c| check(c).is_ok()) {
+        return Ok(selected);
+    }
+
+    let mut rejected = Vec::new();
+    for collection in &ite {
+        match check(collection) {
+            Err(Rejection::DescriptorUnreadable(e)) => return Err(e),
+            Err(rejection) => rejected.push(format!("{:04X}:{:04X} usage {:04X}:{:04X}: {}",
+                collection.vendor_id, collection.product_id, collection.usage_page, collection.usage, rejection)),
+            Ok(()) => {}
+        }
+    }
+    Err(HardwareError::Unavailable(if rejected.is_empty() {
+        "No ITE (048D) HID device found".to_string()
+    } else {
+        format!("No 4-zone lighting collection; {}", rejected.join("; "))
+    }))
+}
+
+/// `Unsupported` if `report` is the controller's identity reply: it has no lighting read-back.
+pub fn check_not_identity(report: &[u8]) -> HardwareResult<()> {
+    if report.get(1) == Some(&IDENTITY_REPORT) {
+        return Err(HardwareError::Unsupported("Lighting controller does not report lighting state".to_string()));
+    }
+    Ok(())
+}
+
+/// Checks `bytes` against the framing `product_id` expects.
+pub fn check_framing(product_id: u16, bytes: &[u8]) -> HardwareResult<()> {
+    let id = report_id(product_id)?;
+    if bytes.len() != ite_report::REPORT_LEN || bytes[0] != id {
+        return Err(HardwareError::InvalidData(format!(
+            "Lighting report for PID {:04x} must be {} bytes starting with report ID {:02x}",
+            product_id, ite_report::REPORT_LEN, id
+        )));
+    }
+    Ok(())
+}
+
+/// Opens the collection `select` picks among `transport`'s ITE collections.
+fn open(transport: &dyn HidTransport) -> HardwareResult<(Box<dyn HidHandle + '_>, u16)> {
+    let collections = transport.enumerate(VENDOR_ID)?;
+    let selected = select(&collections)?;
+    info!("Lighting controller: {} at {}", selected, selected.path);
+    Ok((transport.open(&selected.path)?, selected.product_id))
+}
+
+/// Sends one keyboard lighting report to the controller, once.
+pub fn send_report(transport: &dyn HidTransport, report: &[u8]) -> HardwareResult<()> {
+    let (device, product_id) = open(transport)?;
+    check_framing(product_id, report)?;
+    device.send_feature_report(report)
+}
+
+/// Reads the keyboard lighting feature report the controller currently holds. A
+/// controller that answers with its identity report has no read-back: `Unsupported`.
+pub fn read_report(transport: &dyn HidTransport) -> HardwareResult<Vec<u8>> {
+    let (device, product_id) = open(transport)?;
+    let mut report = vec![0u8; ite_report::REPORT_LEN];
+    report[0] = report_id(product_id)?;
+    let len = device.get_feature_report(&mut report)?;
+    report.truncate(len);
+    debug!("Lighting feature report: {:02x?}", report);
+    check_not_identity(&report)?;
+    check_framing(product_id, &report)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legion_core::hw::hid_mock::MockHidTransport;
+    use crate::legion_core::hw::lighting::{Brightness, KeyboardLighting, LightingEffect, Rgb};
+
+    /// Placeholder usages; selection does not look at them.
+    const USAGE_PAGE: u16 = 0xFF89;
+    const USAGE: u16 = 0x00CC;
+
+    fn collection(product_id: u16, usage: u16, len: HardwareResult<usize>) -> HidCollection {
+        HidCollection {
+            path: format!("/dev/hidraw-{:04x}-{:04x}", product_id, usage),
+            vendor_id: VENDOR_ID,
+            product_id,
+            usage_page: USAGE_PAGE,
+            usage,
+            feature_report_len: len,
+        }
+    }
+
+    /// The LOQ 83DV inventory: C993 has 17, 33, 65 and 192-byte collections; C996 has no 33-byte one.
+    fn loq_83dv() -> Vec<HidCollection> {
+        vec![
+            HidCollection { path: "/dev/hidraw-other-vendor".into(), vendor_id: 0x046D, ..collection(0xC993, USAGE, Ok(33)) },
+            collection(0xC996, 0x0001, Ok(17)),
+            collection(0xC993, 0x0001, Ok(17)),
+            collection(0xC993, USAGE, Ok(33)),
+            collection(0xC993, 0x0002, Ok(65)),
+            collection(0xC993, 0x0003, Ok(192)),
+        ]
+    }
+
+    #[test]
+    fn selects_the_33_byte_lighting_collection_of_an_allowlisted_controller() {
+        let devices = loq_83dv();
+        assert_eq!(select(&devices).unwrap(), &devices[3]);
+        assert_eq!(select(&devices).unwrap().to_string(), "048D:C993 usage FF89:00CC, 33-byte feature report");
+    }
+
+    #[test]
+    fn every_rule_rejects_on_its_own() {
+        assert_eq!(check(&collection(0xC996, USAGE, Ok(33))), Err(Rejection::ProductNotAllowed));
+        assert_eq!(check(&collection(0xC965, USAGE, Ok(33))), Err(Rejection::ProductNotAllowed));
+        assert_eq!(check(&collection(0xC955, USAGE, Ok(65))), Err(Rejection::ReportLength(65)));
+        assert_eq!(check(&collection(0xC935, USAGE, Ok(33))), Ok(()));
+    }
+
+    #[test]
+    fn usage_is_reported_but_not_required() {
+        let other_usage = HidCollection { usage_page: 0xFF12, ..collection(0xC993, 0x0001, Ok(33)) };
+        assert_eq!(check(&other_usage), Ok(()));
+        assert_eq!(select(std::slice::from_ref(&other_usage)).unwrap().to_string(), "048D:C993 usage FF12:0001, 33-byte feature report");
+    }
+
+    #[test]
+    fn enumeration_rules_need_no_descriptor() {
+        assert_eq!(check_enumerated(0xC996), Err(Rejection::ProductNotAllowed));
+        assert_eq!(check_enumerated(0xC993), Ok(()));
+        // A collection rejected before its descriptor was read is not reported as unreadable
+        let skipped = collection(0xC996, USAGE, Err(HardwareError::Unsupported("not opened".into())));
+        assert_eq!(check(&skipped), Err(Rejection::ProductNotAllowed));
+    }
+
+    #[test]
+    fn no_match_is_unavailable_and_names_each_rejection() {
+        let devices = vec![collection(0xC996, USAGE, Ok(33)), collection(0xC993, USAGE, Ok(65))];
+        let err = select(&devices).unwrap_err();
+        assert_eq!(err.code(), "unavailable");
+        assert!(err.to_string().contains("048D:C996 usage FF89:00CC: product ID not on the 4-zone allowlist"), "{}", err);
+        assert!(err.to_string().contains("048D:C993 usage FF89:00CC: feature report is 65 bytes, expected 33"), "{}", err);
+        assert_eq!(select(&[]).unwrap_err().code(), "unavailable");
+    }
+
+    #[test]
+    fn unreadable_descriptor_keeps_its_status() {
+        let devices = vec![collection(0xC993, USAGE, Err(HardwareError::AccessDenied("/dev/hidraw3".into())))];
+        assert_eq!(select(&devices).unwrap_err().code(), "access_denied");
+    }
+
+    #[test]
+    fn known_controllers_take_the_report_unprefixed() {
+        let report = KeyboardLighting::default().to_report().unwrap();
+        for (pid, _) in FOUR_ZONE_CONTROLLERS {
+            assert_eq!(check_framing(*pid, &report), Ok(()));
+        }
+        let prefixed = [&[0x00], report.as_slice()].concat();
+        assert_eq!(check_framing(0xC935, &prefixed).unwrap_err().code(), "invalid_data");
+        assert_eq!(check_framing(0xC935, &report[..32]).unwrap_err().code(), "invalid_data");
+        assert_eq!(check_framing(0xC965, &report).unwrap_err().code(), "unsupported");
+    }
+
+    #[test]
+    fn encoded_lighting_reaches_only_the_selected_collection() {
+        let hid = MockHidTransport::new(loq_83dv());
+        let lighting = KeyboardLighting { effect: LightingEffect::Breath, speed: 2, brightness: Brightness::Low, zones: [Rgb::new(255, 0, 0); 4], ..Default::default() };
+        send_report(&hid, &lighting.to_report().unwrap()).unwrap();
+
+        let path = loq_83dv()[3].path.clone();
+        assert_eq!(hid.opened(), vec![path.clone()]);
+        let sent = hid.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, path);
+        assert_eq!(sent[0].1[..8], [0xCC, 0x16, 0x03, 0x02, 0x01, 0xFF, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn failed_send_is_reported_without_a_retry() {
+        let hid = MockHidTransport::new(loq_83dv()).fail("send_feature_report", HardwareError::Failed("EPIPE".into()));
+        let report = KeyboardLighting::default().to_report().unwrap();
+        assert_eq!(send_report(&hid, &report).unwrap_err().code(), "failed");
+        assert_eq!(hid.sent().len(), 1);
+
+        let hid = MockHidTransport::new(loq_83dv()).fail("open", HardwareError::AccessDenied("/dev/hidraw3".into()));
+        assert_eq!(send_report(&hid, &report).unwrap_err().code(), "access_denied");
+        assert!(hid.sent().is_empty());
+
+        // Nothing on the allowlist: nothing is opened
+        let hid = MockHidTransport::new(vec![collection(0xC996, USAGE, Ok(33))]);
+        assert_eq!(send_report(&hid, &report).unwrap_err().code(), "unavailable");
+        assert!(hid.opened().is_empty());
+
+        // A malformed report is refused before it reaches the device
+        let hid = MockHidTransport::new(loq_83dv());
+        assert_eq!(send_report(&hid, &report[1..]).unwrap_err().code(), "invalid_data");
+        assert!(hid.sent().is_empty());
+    }
+
+    #[test]
+    fn read_back_is_checked_before_it_is_decoded() {
+        let report = KeyboardLighting::default().to_report().unwrap();
+        let hid = MockHidTransport::new(loq_83dv()).respond(Ok(report.clone()));
+        assert_eq!(KeyboardLighting::from_report(&read_report(&hid).unwrap()).unwrap(), KeyboardLighting::default());
+
+        // The LOQ answers GetFeature with an identity report (CC 05 + VID/PID), not lighting state
+        let mut identity = vec![0xCC, 0x05, 0xFF, 0x0A, 0x00, 0x8D, 0x04, 0x93, 0xC9, 0x00, 0x03];
+        identity.resize(ite_report::REPORT_LEN, 0);
+        let hid = MockHidTransport::new(loq_83dv()).respond(Ok(identity));
+        let err = read_report(&hid).unwrap_err();
+        assert_eq!((err.code(), err.detail()), ("unsupported", "Lighting controller does not report lighting state"));
+
+        let hid = MockHidTransport::new(loq_83dv()).respond(Ok(report[..17].to_vec()));
+        assert_eq!(read_report(&hid).unwrap_err().code(), "invalid_data");
+        let hid = MockHidTransport::new(loq_83dv()).respond(Err(HardwareError::TimedOut("hidraw".into())));
+        assert_eq!(read_report(&hid).unwrap_err().code(), "timed_out");
+    }
+}
@@ -0,0 +1,215 @@
+//! Byte layout of the ITE 4-zone keyboard feature report (`0xCC 0x16`, 33 bytes).
+//!
+//! The only place lighting state is turned into bytes or back: writes use `encode`,
+//! readback uses `decode`.
+//!
+//! | Offset | Field                                   |
+//! |--------|-----------------------------------------|
+//! | 0-1    | Header `0xCC 0x16` (0xCC is the report ID) |
+//! | 2      | Effect: 0 Off, 1 Static, 3 Breath, 4 Wave, 6 Smooth |
+//! | 3      | Speed 1-4                               |
+//! | 4      | Brightness: 0 Off, 1 Low, 2 High        |
+//! | 5-16   | Zone 1-4 colors, RGB                    |
+//! | 17     | Padding (0)                             |
+//! | 18, 19 | Wave left-to-right / right-to-left flag |
+//! | 20-32  | Unused (0)                              |
+
+use crate::legion_core::hw::lighting::{Brightness, KeyboardLighting, LightingEffect, Rgb, WaveDirection};
+use crate::legion_core::result::{HardwareError, HardwareResult, WriteError};
+
+pub const REPORT_LEN: usize = 33;
+pub const HEADER: [u8; 2] = [0xCC, 0x16];
+
+const EFFECT: usize = 2;
+const SPEED: usize = 3;
+const BRIGHTNESS: usize = 4;
+const ZONES: usize = 5;
+const WAVE_LTR: usize = 18;
+const WAVE_RTL: usize = 19;
+
+/// Effect byte of the Off report other writers (the C# app, Vantage) send: `CC 16 00 01 00`.
+const EFFECT_OFF: u8 = 0;
+
+fn effect_code(effect: LightingEffect) -> u8 {
+    match effect {
+        LightingEffect::Static => 1,
+        LightingEffect::Breath => 3,
+        LightingEffect::Wave => 4,
+        LightingEffect::Smooth => 6,
+    }
+}
+
+fn brightness_level(brightness: Brightness) -> u8 {
+    match brightness {
+        Brightness::Off => 0,
+        Brightness::Low => 1,
+        Brightness::High => 2,
+    }
+}
+
+/// The report for `lighting`. Refuses (`Rejected`) a speed outside 1-4; every other
+/// field is a closed type.
+pub fn encode(lighting: &KeyboardLighting) -> Result<[u8; REPORT_LEN], WriteError> {
+    if !KeyboardLighting::SPEEDS.contains(&lighting.speed) {
+        return Err(WriteError::Rejected(format!("Lighting speed {} is outside 1-4", lighting.speed)));
+    }
+
+    let mut report = [0u8; REPORT_LEN];
+    report[..2].copy_from_slice(&HEADER);
+    report[EFFECT] = effect_code(lighting.effect);
+    report[SPEED] = lighting.speed;
+    report[BRIGHTNESS] = brightness_level(lighting.brightness);
+    for (i, zone) in lighting.zones.iter().enumerate() {
+        report[ZONES + 3 * i..ZONES + 3 * i + 3].copy_from_slice(&[zone.r, zone.g, zone.b]);
+    }
+    if lighting.effect == LightingEffect::Wave {
+        let flag = match lighting.direction {
+            WaveDirection::LeftToRight => WAVE_LTR,
+            WaveDirection::RightToLeft => WAVE_RTL,
+        };
+        report[flag] = 1;
+    }
+    Ok(report)
+}
+
+/// Lighting state from a report read back from the controller. Effect 0 is Off; anything
+/// else `encode` could not have produced, apart from the padding and unused bytes, is `InvalidData`.
+pub fn decode(report: &[u8]) -> HardwareResult<KeyboardLighting> {
+    let invalid = |what: String| Err(HardwareError::InvalidData(format!("Keyboard lighting report: {}", what)));
+    if report.len() != REPORT_LEN {
+        return invalid(format!("{} bytes, expected {}", report.len(), REPORT_LEN));
+    }
+    if report[..2] != HEADER {
+        return invalid(format!("header {:02x} {:02x}, expected cc 16", report[0], report[1]));
+    }
+
+    // Off carries no effect, so the speed, zone and wave bytes mean nothing
+    if report[EFFECT] == EFFECT_OFF {
+        return Ok(KeyboardLighting { brightness: Brightness::Off, ..Default::default() });
+    }
+    let Some(effect) = LightingEffect::ALL.into_iter().find(|e| effect_code(*e) == report[EFFECT]) else {
+        return invalid(format!("unknown effect {}", report[EFFECT]));
+    };
+    let speed = report[SPEED];
+    if !KeyboardLighting::SPEEDS.contains(&speed) {
+        return invalid(format!("speed {} is outside 1-4", speed));
+    }
+    let Some(brightness) = Brightness::ALL.into_iter().find(|b| brightness_level(*b) == report[BRIGHTNESS]) else {
+        return invalid(format!("brightness {} is outside 0-2", report[BRIGHTNESS]));
+    };
+
+    let direction = match (report[WAVE_LTR], report[WAVE_RTL]) {
+        (0, 0) if effect != LightingEffect::Wave => WaveDirection::LeftToRight,
+        (1, 0) => WaveDirection::LeftToRight,
+        (0, 1) => WaveDirection::RightToLeft,
+        (ltr, rtl) => return invalid(format!("wave flags ltr={} rtl={} for {}", ltr, rtl, effect)),
+    };
+
+    let zone = |i: usize| Rgb::new(report[ZONES + 3 * i], report[ZONES + 3 * i + 1], report[ZONES + 3 * i + 2]);
+    Ok(KeyboardLighting { effect, speed, brightness, zones: [zone(0), zone(1), zone(2), zone(3)], direction })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Static blue on all four zones at high brightness, as the firmware leaves the controller.
+    const STATIC_BLUE: [u8; REPORT_LEN] = [
+        0xCC, 0x16, 0x01, 0x01, 0x02,
+        0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF,
+        0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// `--zone 2=#ff8800 --effect wave --direction rtl --speed 3 --brightness low`
+    const WAVE_RTL_REPORT: [u8; REPORT_LEN] = [
+        0xCC, 0x16, 0x04, 0x03, 0x01,
+        0x00, 0x00, 0xFF, 0xFF, 0x88, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF,
+        0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// `FourZoneKeyboardPacket.BuildLighting` in the C# app for Off.
+    const CSHARP_OFF: [u8; REPORT_LEN] = [
+        0xCC, 0x16, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    fn wave_rtl() -> KeyboardLighting {
+        KeyboardLighting {
+            effect: LightingEffect::Wave,
+            speed: 3,
+            brightness: Brightness::Low,
+            zones: [Rgb::BLUE, Rgb::new(0xFF, 0x88, 0x00), Rgb::BLUE, Rgb::BLUE],
+            direction: WaveDirection::RightToLeft,
+        }
+    }
+
+    #[test]
+    fn golden_reports_encode_byte_for_byte() {
+        assert_eq!(encode(&KeyboardLighting::default()).unwrap(), STATIC_BLUE);
+        assert_eq!(encode(&wave_rtl()).unwrap(), WAVE_RTL_REPORT);
+
+        let breath = KeyboardLighting { effect: LightingEffect::Breath, speed: 4, brightness: Brightness::Off, ..Default::default() };
+        assert_eq!(encode(&breath).unwrap()[..5], [0xCC, 0x16, 0x03, 0x04, 0x00]);
+        let smooth = KeyboardLighting { effect: LightingEffect::Smooth, direction: WaveDirection::RightToLeft, ..Default::default() };
+        assert_eq!(encode(&smooth).unwrap()[EFFECT..=BRIGHTNESS], [0x06, 0x01, 0x02]);
+        assert_eq!(encode(&smooth).unwrap()[WAVE_LTR..=WAVE_RTL], [0, 0]);
+    }
+
+    #[test]
+    fn golden_reports_decode_and_round_trip() {
+        assert_eq!(decode(&STATIC_BLUE).unwrap(), KeyboardLighting::default());
+        assert_eq!(decode(&WAVE_RTL_REPORT).unwrap(), wave_rtl());
+        for effect in LightingEffect::ALL {
+            for speed in KeyboardLighting::SPEEDS {
+                let lighting = KeyboardLighting { effect, speed, ..wave_rtl() };
+                let decoded = decode(&encode(&lighting).unwrap()).unwrap();
+                assert_eq!(encode(&decoded).unwrap(), encode(&lighting).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn effect_zero_decodes_as_off() {
+        let off = KeyboardLighting { brightness: Brightness::Off, ..Default::default() };
+        assert_eq!(decode(&CSHARP_OFF).unwrap(), off);
+
+        // Speed, zone and wave bytes are ignored when the effect is Off
+        let mut stale = WAVE_RTL_REPORT;
+        stale[EFFECT] = EFFECT_OFF;
+        stale[SPEED] = 0;
+        stale[WAVE_LTR] = 1;
+        assert_eq!(decode(&stale).unwrap(), off);
+    }
+
+    #[test]
+    fn out_of_range_speed_is_not_encoded() {
+        for speed in [0, 5, 255] {
+            let err = encode(&KeyboardLighting { speed, ..Default::default() }).unwrap_err();
+            assert_eq!(err.code(), "rejected");
+        }
+    }
+
+    #[test]
+    fn every_field_is_range_checked_on_decode() {
+        let corrupt = |offset: usize, value: u8| {
+            let mut report = WAVE_RTL_REPORT;
+            report[offset] = value;
+            decode(&report).unwrap_err().code()
+        };
+        assert_eq!(corrupt(0, 0x00), "invalid_data");
+        assert_eq!(corrupt(1, 0x17), "invalid_data");
+        assert_eq!(corrupt(EFFECT, 2), "invalid_data");
+        assert_eq!(corrupt(SPEED, 0), "invalid_data");
+        assert_eq!(corrupt(SPEED, 5), "invalid_data");
+        assert_eq!(corrupt(BRIGHTNESS, 3), "invalid_data");
+        assert_eq!(corrupt(WAVE_LTR, 1), "invalid_data");
+        assert_eq!(corrupt(WAVE_RTL, 0), "invalid_data");
+        assert_eq!(corrupt(WAVE_RTL, 2), "invalid_data");
+        assert!(decode(&WAVE_RTL_REPORT[..32]).is_err());
+        assert!(decode(&[WAVE_RTL_REPORT.as_slice(), &[0]].concat()).is_err());
+    }
+}
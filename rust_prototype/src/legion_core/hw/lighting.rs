@@ -1,53 +1,188 @@
-use hidapi::{HidApi, HidDevice};
-use std::error::Error;
-use log::{info, warn};
-
-// Constants from LLT
-const VENDOR_ID: u16 = 0x048D;
-const PRODUCT_ID_MASKED: u16 = 0xC900;
-const PRODUCT_ID_MASK: u16 = 0xFF00;
-#[allow(dead_code)]
-const DESCRIPTOR_LENGTH: u16 = 0x21; // 33 bytes
-
-#[repr(C, packed)]
-struct LenovoRgbKeyboardState {
-    header: [u8; 2],
-    effect: u8,
-    speed: u8,
-    brightness: u8,
-    zone1_rgb: [u8; 3],
-    zone2_rgb: [u8; 3],
-    zone3_rgb: [u8; 3],
-    zone4_rgb: [u8; 3],
-    padding: u8,
-    wave_ltr: u8,
-    wave_rtl: u8,
-    unused: [u8; 13],
-}
-
-impl Default for LenovoRgbKeyboardState {
+use crate::legion_core::backend::{self, HardwareBackend};
+use crate::legion_core::hw::ite_report;
+use crate::legion_core::result::{HardwareReadResult, HardwareResult, WriteError, WriteOutcome};
+use crate::legion_core::safety::guards::WriteToken;
+use crate::legion_core::safety::pipeline::verified_write;
+use crate::legion_core::safety::undo::{self, UndoChange, UndoHistory};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// One zone color.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const BLUE: Rgb = Rgb { r: 0, g: 0, b: 255 };
+
+    pub fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Parses `#ff8800` or `ff8800`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let hex = s.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Self { r: channel(0)?, g: channel(2)?, b: channel(4)? })
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// Hex, so conflict messages and journal entries stay readable.
+impl fmt::Debug for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Firmware lighting effects of the ITE 4-zone controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightingEffect {
+    #[default]
+    Static,
+    Breath,
+    Wave,
+    /// Smooth color cycling; zone colors are ignored.
+    Smooth,
+}
+
+impl LightingEffect {
+    pub const ALL: [LightingEffect; 4] = [LightingEffect::Static, LightingEffect::Breath, LightingEffect::Wave, LightingEffect::Smooth];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "static" => Some(LightingEffect::Static),
+            "breath" | "breathing" => Some(LightingEffect::Breath),
+            "wave" => Some(LightingEffect::Wave),
+            "smooth" => Some(LightingEffect::Smooth),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LightingEffect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Wave effect direction; ignored by the other effects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaveDirection {
+    #[default]
+    LeftToRight,
+    RightToLeft,
+}
+
+impl WaveDirection {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "ltr" | "left-to-right" => Some(WaveDirection::LeftToRight),
+            "rtl" | "right-to-left" => Some(WaveDirection::RightToLeft),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Brightness {
+    Off,
+    Low,
+    #[default]
+    High,
+}
+
+impl Brightness {
+    pub const ALL: [Brightness; 3] = [Brightness::Off, Brightness::Low, Brightness::High];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "off" | "0" => Some(Brightness::Off),
+            "low" | "1" => Some(Brightness::Low),
+            "high" | "2" => Some(Brightness::High),
+            _ => None,
+        }
+    }
+}
+
+/// Complete state of a 4-zone keyboard, applied in one report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyboardLighting {
+    pub effect: LightingEffect,
+    /// Animation speed, 1 (slow) to 4 (fast).
+    pub speed: u8,
+    pub brightness: Brightness,
+    /// Zone colors, left to right.
+    pub zones: [Rgb; 4],
+    pub direction: WaveDirection,
+}
+
+impl Default for KeyboardLighting {
     fn default() -> Self {
         Self {
-            header: [0xCC, 0x16],
-            effect: 1, // Static
+            effect: LightingEffect::Static,
             speed: 1,
-            brightness: 2, // High
-            zone1_rgb: [0, 0, 255], // Blue
-            zone2_rgb: [0, 0, 255],
-            zone3_rgb: [0, 0, 255],
-            zone4_rgb: [0, 0, 255],
-            padding: 0,
-            wave_ltr: 0,
-            wave_rtl: 0,
-            unused: [0; 13],
+            brightness: Brightness::High,
+            zones: [Rgb::BLUE; 4],
+            direction: WaveDirection::LeftToRight,
+        }
+    }
+}
+
+impl KeyboardLighting {
+    pub const SPEEDS: std::ops::RangeInclusive<u8> = 1..=4;
+
+    /// The 0xCC 0x16 output report for this state (see `ite_report::encode`).
+    pub fn to_report(self) -> Result<Vec<u8>, WriteError> {
+        Ok(ite_report::encode(&self)?.to_vec())
+    }
+
+    /// State carried by a report read back from the controller (see `ite_report::decode`).
+    pub fn from_report(report: &[u8]) -> HardwareResult<Self> {
+        ite_report::decode(report)
+    }
+}
+
+impl fmt::Display for KeyboardLighting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.effect)?;
+        if self.effect == LightingEffect::Wave {
+            write!(f, " {}", if self.direction == WaveDirection::LeftToRight { "ltr" } else { "rtl" })?;
+        }
+        if self.effect != LightingEffect::Static {
+            write!(f, " speed {}", self.speed)?;
         }
+        write!(f, ", {:?}", self.brightness)?;
+        if self.effect != LightingEffect::Smooth {
+            let zones: Vec<String> = self.zones.iter().map(Rgb::to_string).collect();
+            write!(f, ", {}", zones.join(" "))?;
+        }
+        Ok(())
     }
 }
 
+/// `WriteToken` operation for keyboard lighting reports.
+pub const LIGHTING_OPERATION: &str = "Keyboard Lighting";
+/// `WriteToken` operation for `set_light_control_owner`.
+pub const LIGHTING_OWNER_OPERATION: &str = "Lighting Control";
+
 pub struct LightingController {
-    // We don't keep the device open to avoid locking it? Or should we?
-    // LLT opens/closes or keeps open? LLT uses SafeFileHandle.
-    // HidApi recommends keeping the api instance.
+    // The transport (open/send/get/close) lives in the hardware backend.
 }
 
 impl LightingController {
@@ -55,99 +190,167 @@ impl LightingController {
         Self {}
     }
 
-    fn find_device(api: &HidApi) -> Result<HidDevice, Box<dyn Error>> {
-        for device in api.device_list() {
-            if device.vendor_id() == VENDOR_ID {
-                // Check Product ID Mask
-                if (device.product_id() & PRODUCT_ID_MASK) == PRODUCT_ID_MASKED {
-                    info!("Found potential Lighting Device: VID={:04x}, PID={:04x}", device.vendor_id(), device.product_id());
-                    // Ideally we check UsagePage/Usage or Descriptor length, relying on PID mask for now as hidapi listing might not give full descriptor len easily without opening.
-                    return Ok(api.open_path(device.path())?);
-                }
-            }
-        }
-        Err("Lighting device not found".into())
+    /// What the keyboard is showing now, read back from the controller.
+    pub fn read_state(&self) -> HardwareReadResult<KeyboardLighting> {
+        HardwareReadResult::logged("keyboard lighting", read_lighting(backend::active()))
     }
 
-    pub fn set_static_color(&self, r: u8, g: u8, b: u8) -> Result<(), Box<dyn Error>> {
-        if !crate::legion_core::safety::guards::GlobalWriteLock::is_write_allowed() {
-            return Err("Global Write Lock is active. Cannot write to lighting.".into());
-        }
+    /// Applies effect, speed, brightness, zone colors and direction in one report.
+    /// With `expected`, the write is refused (`Conflict`) unless the controller reads
+    /// back as that state first.
+    pub fn apply(&self, lighting: &KeyboardLighting, expected: Option<&KeyboardLighting>, token: &WriteToken) -> Result<WriteOutcome, WriteError> {
+        token.authorize(LIGHTING_OPERATION)?;
+        change_lighting(backend::active(), undo::active(), token, expected, lighting)
+    }
+}
+
+/// Sends `lighting` as one report through `verified_write`, comparing the decoded
+/// readback with the state sent, and records it in `history`. Backends without read-back
+/// make the write Unverified.
+pub fn change_lighting(backend: &dyn HardwareBackend, history: Option<&UndoHistory>, token: &WriteToken, expected: Option<&KeyboardLighting>, lighting: &KeyboardLighting) -> Result<WriteOutcome, WriteError> {
+    let report = lighting.to_report()?;
+    let after = canonical(lighting)?;
+    let expected = expected.map(canonical).transpose()?;
+    let mut before = None;
+    let result = verified_write(token, expected.as_ref(), || read_lighting(backend), |current| {
+        before = current.ok();
+        backend.send_lighting_report(&report)?;
+        Ok(after)
+    });
+    undo::record(history, token, &result, before.map(|before| UndoChange::KeyboardLighting { before, after }));
+    result
+}
 
-        let api = HidApi::new()?;
-        let device = Self::find_device(&api)?;
-
-        let mut state = LenovoRgbKeyboardState::default();
-        state.effect = 1; // Static
-        state.brightness = 2; // High
-        state.zone1_rgb = [r, g, b];
-        state.zone2_rgb = [r, g, b];
-        state.zone3_rgb = [r, g, b];
-        state.zone4_rgb = [r, g, b];
-
-        // Ensure header is correct
-        state.header = [0xCC, 0x16];
-
-        // Serialize to bytes
-        // unsafe due to packed struct, but we are just reading bytes
-        let bytes = unsafe {
-            std::slice::from_raw_parts(
-                &state as *const _ as *const u8,
-                std::mem::size_of::<LenovoRgbKeyboardState>()
-            )
+/// Decodes the report `backend` reads back from the keyboard.
+pub fn read_lighting(backend: &dyn HardwareBackend) -> HardwareResult<KeyboardLighting> {
+    KeyboardLighting::from_report(&backend.lighting_report()?)
+}
+
+/// `lighting` as the controller reads it back: fields the effect does not use (the
+/// direction of a non-Wave effect) take their decoded value, so states compare equal.
+fn canonical(lighting: &KeyboardLighting) -> Result<KeyboardLighting, WriteError> {
+    Ok(KeyboardLighting::from_report(&lighting.to_report()?)?)
+}
+
+/// Hands keyboard lighting to the app (true) or back to the firmware (false).
+pub fn set_light_control_owner(app_control: bool, token: &WriteToken) -> Result<WriteOutcome, WriteError> {
+    token.authorize(LIGHTING_OWNER_OPERATION)?;
+    change_light_control_owner(backend::active(), token, app_control)
+}
+
+/// Writes lighting ownership through `verified_write`. The firmware has no ownership
+/// read, so the write is journaled but always Unverified.
+pub fn change_light_control_owner(backend: &dyn HardwareBackend, token: &WriteToken, app_control: bool) -> Result<WriteOutcome, WriteError> {
+    verified_write(token, None, || backend::unsupported(backend.name(), "Lighting ownership read-back"), |_| {
+        backend.set_light_control_owner(app_control)?;
+        Ok(app_control)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legion_core::backend::simulated::{Fault, SimulatedBackend, SimulatedState};
+
+    #[test]
+    fn parses_colors_and_names() {
+        assert_eq!(Rgb::parse("#ff8800"), Some(Rgb::new(0xff, 0x88, 0x00)));
+        assert_eq!(Rgb::parse("00FF7f"), Some(Rgb::new(0, 0xff, 0x7f)));
+        assert_eq!(Rgb::parse("#ff88"), None);
+        assert_eq!(Rgb::parse("#gg0000"), None);
+        assert_eq!(Rgb::new(1, 2, 255).to_string(), "#0102ff");
+        assert_eq!(LightingEffect::parse("Wave"), Some(LightingEffect::Wave));
+        assert_eq!(WaveDirection::parse("rtl"), Some(WaveDirection::RightToLeft));
+        assert_eq!(Brightness::parse("low"), Some(Brightness::Low));
+    }
+
+    #[test]
+    fn one_report_carries_every_field() {
+        let lighting = KeyboardLighting {
+            effect: LightingEffect::Wave,
+            speed: 3,
+            brightness: Brightness::Low,
+            zones: [Rgb::new(1, 2, 3), Rgb::new(0xff, 0x88, 0), Rgb::new(7, 8, 9), Rgb::new(10, 11, 12)],
+            direction: WaveDirection::RightToLeft,
         };
+        let report = lighting.to_report().unwrap();
+        assert_eq!(report.len(), 33);
+        assert_eq!(report[..5], [0xCC, 0x16, 4, 3, 1]);
+        assert_eq!(report[5..17], [1, 2, 3, 0xff, 0x88, 0, 7, 8, 9, 10, 11, 12]);
+        assert_eq!(report[18..20], [0, 1]);
+        assert_eq!(lighting.to_string(), "Wave rtl speed 3, Low, #010203 #ff8800 #070809 #0a0b0c");
+    }
 
-        // HIDAPI expects report ID as first byte if numbered reports are used.
-        // LLT sends 33 bytes with header [0xCC, 0x16].
-        // hidapi `send_feature_report` needs Report ID as first byte.
-        // The first byte 0xCC may be the Report ID for this device.
-        
-        match device.send_feature_report(bytes) {
-            Ok(_) => Ok(()),
-            Err(e) => {
-               // Fallback: Try prepending 0?
-               warn!("Standard feature report failed ({}), trying with 0x00 prefix...", e);
-               let mut prefixed = vec![0u8];
-               prefixed.extend_from_slice(bytes);
-               device.send_feature_report(&prefixed)?;
-               Ok(())
-            }
-        }
+    #[test]
+    fn direction_is_only_sent_for_wave() {
+        let breath = KeyboardLighting { effect: LightingEffect::Breath, direction: WaveDirection::RightToLeft, ..Default::default() };
+        assert_eq!(breath.to_report().unwrap()[18..20], [0, 0]);
     }
 
-    pub fn set_brightness(&self, level: u8) -> Result<(), Box<dyn Error>> {
-        // level: 0 = Off, 1 = Low, 2 = High
-        self.set_effect_params(1, level)
+    #[test]
+    fn speed_outside_the_firmware_range_is_rejected() {
+        let fast = KeyboardLighting { speed: 5, ..Default::default() };
+        assert_eq!(fast.to_report().unwrap_err().code(), "rejected");
     }
 
-    fn set_effect_params(&self, effect: u8, brightness: u8) -> Result<(), Box<dyn Error>> {
-         if !crate::legion_core::safety::guards::GlobalWriteLock::is_write_allowed() {
-            return Err("Global Write Lock is active.".into());
-        }
+    fn token() -> WriteToken {
+        crate::legion_core::safety::lock::tests::use_test_lock_file();
+        WriteToken::grant(LIGHTING_OPERATION, "test", "test")
+    }
 
-        let api = HidApi::new()?;
-        let device = Self::find_device(&api)?;
+    /// A controller with read-back, showing the firmware's static blue.
+    fn with_read_back() -> SimulatedBackend {
+        SimulatedBackend::with_state(SimulatedState { lighting_report: KeyboardLighting::default().to_report().unwrap().to_vec(), ..Default::default() })
+    }
 
-        let mut state = LenovoRgbKeyboardState::default();
-        state.effect = effect;
-        state.brightness = brightness;
-        state.header = [0xCC, 0x16];
+    #[test]
+    fn state_is_read_back_from_the_controller() {
+        let sim = with_read_back();
+        assert_eq!(read_lighting(&sim).unwrap(), KeyboardLighting::default());
 
-        let bytes = unsafe {
-            std::slice::from_raw_parts(
-                &state as *const _ as *const u8,
-                std::mem::size_of::<LenovoRgbKeyboardState>()
-            )
-        };
-        
-        match device.send_feature_report(bytes) {
-            Ok(_) => Ok(()),
-            Err(_) => {
-               let mut prefixed = vec![0u8];
-               prefixed.extend_from_slice(bytes);
-               device.send_feature_report(&prefixed)?;
-               Ok(())
-            }
-        }
+        let breath = KeyboardLighting { effect: LightingEffect::Breath, speed: 2, direction: WaveDirection::RightToLeft, ..Default::default() };
+        assert_eq!(change_lighting(&sim, None, &token(), Some(&KeyboardLighting::default()), &breath), Ok(WriteOutcome::Verified));
+        // The direction of a non-Wave effect is not part of the report
+        assert_eq!(read_lighting(&sim).unwrap(), KeyboardLighting { direction: WaveDirection::LeftToRight, ..breath });
+    }
+
+    #[test]
+    fn write_is_refused_unless_the_keyboard_shows_the_expected_state() {
+        let sim = with_read_back();
+        let red = KeyboardLighting { zones: [Rgb::new(255, 0, 0); 4], ..Default::default() };
+        let err = change_lighting(&sim, None, &token(), Some(&red), &KeyboardLighting::default()).unwrap_err();
+        assert_eq!(err.code(), "conflict");
+
+        let garbled = SimulatedBackend::with_state(SimulatedState { lighting_report: vec![0xCC, 0x16, 9], ..Default::default() });
+        assert_eq!(read_lighting(&garbled).unwrap_err().code(), "invalid_data");
+        let err = change_lighting(&garbled, None, &token(), Some(&KeyboardLighting::default()), &red).unwrap_err();
+        assert_eq!(err.code(), "conflict");
+        assert_eq!(garbled.lighting_report().unwrap(), vec![0xCC, 0x16, 9]);
+    }
+
+    #[test]
+    fn controller_without_read_back_is_unverified() {
+        // The simulated 83DV answers GetFeature with its identity report
+        let sim = SimulatedBackend::new();
+        assert_eq!(read_lighting(&sim).unwrap_err().code(), "unsupported");
+        let red = KeyboardLighting { zones: [Rgb::new(255, 0, 0); 4], ..Default::default() };
+        let outcome = change_lighting(&sim, None, &token(), None, &red).unwrap();
+        assert!(matches!(outcome, WriteOutcome::Unverified(ref e) if e.code() == "unsupported"));
+        assert_eq!(sim.snapshot().sent_lighting_report, Some(red.to_report().unwrap().to_vec()));
+        assert_eq!(read_lighting(&sim).unwrap_err().code(), "unsupported");
+    }
+
+    #[test]
+    fn ownership_goes_through_the_write_pipeline() {
+        let sim = SimulatedBackend::new();
+        let token = WriteToken::grant(LIGHTING_OWNER_OPERATION, "APP", "test");
+        crate::legion_core::safety::lock::tests::use_test_lock_file();
+        let outcome = change_light_control_owner(&sim, &token, true).unwrap();
+        assert!(matches!(outcome, WriteOutcome::Unverified(ref e) if e.code() == "unsupported"));
+        assert!(sim.snapshot().light_control_owner_app);
+
+        sim.inject("set_light_control_owner", Fault::AccessDenied).unwrap();
+        assert_eq!(change_light_control_owner(&sim, &token, false).unwrap_err().code(), "access_denied");
+        assert!(sim.snapshot().light_control_owner_app);
     }
 }
@@ -1,4 +1,11 @@
 pub mod battery;
+pub mod gpu;
+#[cfg(test)]
+pub(crate) mod hid_mock;
+pub mod hid_transport;
+pub mod ite_device;
+pub mod ite_report;
 pub mod power;
 pub mod lighting;
 pub mod thermal;
+pub mod toggles;
@@ -1,12 +1,21 @@
-use crate::platform::windows::WmiQueryHandler;
-use log::warn;
+use crate::legion_core::backend::{self, HardwareBackend};
+use crate::legion_core::result::{HardwareReadResult, WriteError, WriteOutcome};
+use crate::legion_core::safety::guards::WriteToken;
+use crate::legion_core::safety::pipeline::verified_write;
+use crate::legion_core::safety::undo::{self, UndoChange, UndoHistory};
+use serde::{Serialize, Deserialize};
 use std::fmt;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum PowerProfile {
     Performance,
+    /// Between Balanced and Performance; only some Legions expose it (Linux `balanced-performance`).
+    BalancedPerformance,
     Balanced,
     Quiet,
+    /// User-defined limits (LLT "God Mode").
+    Custom,
     Unknown,
 }
 
@@ -14,50 +23,75 @@ impl fmt::Display for PowerProfile {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             PowerProfile::Performance => write!(f, "Performance (Red)"),
+            PowerProfile::BalancedPerformance => write!(f, "Balanced-Performance"),
             PowerProfile::Balanced => write!(f, "Balanced (White)"),
             PowerProfile::Quiet => write!(f, "Quiet (Blue)"),
+            PowerProfile::Custom => write!(f, "Custom (Purple)"),
             PowerProfile::Unknown => write!(f, "Unknown"),
         }
     }
 }
 
-pub fn get_power_profile() -> Option<PowerProfile> {
-    let wmi = match WmiQueryHandler::new() {
-        Ok(w) => w,
-        Err(e) => {
-            warn!("Failed to init WMI for power profile: {}", e);
-            return None;
-        }
-    };
-
-    match wmi.get_thermal_mode() {
-        Ok(mode_int) => Some(match mode_int {
-            3 => PowerProfile::Performance, // Assuming 3 based on offset. Might be 4.
+impl PowerProfile {
+    /// Decodes a `LENOVO_GAMEZONE_DATA` SmartFanMode value.
+    pub fn from_smart_fan_mode(mode: u32) -> Self {
+        match mode {
+            3 => PowerProfile::Performance, // Confirmed by hardware-evidence/83DV (rawValue 3).
             2 => PowerProfile::Balanced,
             1 => PowerProfile::Quiet,
+            255 => PowerProfile::Custom,
             _ => PowerProfile::Unknown,
-        }),
-        Err(e) => {
-            warn!("Failed to read power profile: {}", e);
-            None
         }
     }
+
+    /// SmartFanMode value for this profile, if the WMI interface has one.
+    pub fn to_smart_fan_mode(self) -> Option<u32> {
+        match self {
+            PowerProfile::Quiet => Some(1),
+            PowerProfile::Balanced => Some(2),
+            PowerProfile::Performance => Some(3),
+            PowerProfile::Custom => Some(255),
+            PowerProfile::BalancedPerformance | PowerProfile::Unknown => None,
+        }
+    }
+}
+
+pub fn get_power_profile() -> HardwareReadResult<PowerProfile> {
+    HardwareReadResult::logged("power profile", backend::active().power_profile())
 }
 
-pub fn set_power_profile(profile: PowerProfile) -> Result<(), Box<dyn std::error::Error>> {
-    if !crate::legion_core::safety::guards::GlobalWriteLock::is_write_allowed() {
-         return Err("Write operations locked.".into());
+/// Profiles this machine offers, in display order (Quiet → Performance → Custom).
+pub fn get_available_profiles() -> HardwareReadResult<Vec<PowerProfile>> {
+    HardwareReadResult::logged("power profile choices", backend::active().power_profile_choices())
+}
+
+/// `WriteToken` operation for `set_power_profile`.
+pub const POWER_PROFILE_OPERATION: &str = "Power Profile";
+
+/// `expected` is the profile the caller last saw; a different current profile is a `Conflict`.
+pub fn set_power_profile(profile: PowerProfile, expected: Option<PowerProfile>, token: &WriteToken) -> Result<WriteOutcome, WriteError> {
+    token.authorize(POWER_PROFILE_OPERATION)?;
+    change_power_profile(backend::active(), undo::active(), token, expected, profile)
+}
+
+/// Writes `profile` through `verified_write`, recording it in `history`; a profile the
+/// firmware does not offer is `Rejected`.
+pub fn change_power_profile(backend: &dyn HardwareBackend, history: Option<&UndoHistory>, token: &WriteToken, expected: Option<PowerProfile>, profile: PowerProfile) -> Result<WriteOutcome, WriteError> {
+    if profile == PowerProfile::Unknown {
+        return Err(WriteError::Rejected("Unsupported mode".to_string()));
+    }
+
+    let choices = backend.power_profile_choices()?;
+    if !choices.contains(&profile) {
+        return Err(WriteError::Rejected(format!("Profile '{}' is not offered by this machine", profile)));
     }
 
-    let mode_int = match profile {
-        PowerProfile::Quiet => 1,
-        PowerProfile::Balanced => 2,
-        PowerProfile::Performance => 3, // Assuming offset 1 from Enum 2. If fails, try 4.
-        _ => return Err("Unsupported mode".into()),
-    };
-
-    let wmi = crate::platform::windows::WmiQueryHandler::new()?;
-    wmi.set_thermal_mode(mode_int)?;
-    
-    Ok(())
+    let mut before = None;
+    let result = verified_write(token, expected.as_ref(), || backend.power_profile(), |current| {
+        before = current.ok();
+        backend.set_power_profile(profile)?;
+        Ok(profile)
+    });
+    undo::record(history, token, &result, before.map(|before| UndoChange::PowerProfile { before, after: profile }));
+    result
 }
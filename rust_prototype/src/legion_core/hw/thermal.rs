@@ -1,7 +1,93 @@
-pub fn get_cpu_temp() -> Option<f32> {
-    None
+use crate::legion_core::backend;
+use crate::legion_core::result::HardwareReadResult;
+use serde::Serialize;
+use std::fmt;
+
+/// What a temperature sensor measures.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+pub enum SensorKind {
+    CpuPackage,
+    CpuCore,
+    Gpu,
+    Ssd,
+    Ambient,
+    Other,
+}
+
+impl fmt::Display for SensorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SensorKind::CpuPackage => write!(f, "CPU Package"),
+            SensorKind::CpuCore => write!(f, "CPU Core"),
+            SensorKind::Gpu => write!(f, "GPU"),
+            SensorKind::Ssd => write!(f, "SSD"),
+            SensorKind::Ambient => write!(f, "Ambient"),
+            SensorKind::Other => write!(f, "Other"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TemperatureReading {
+    pub kind: SensorKind,
+    /// Driver or zone the reading came from (e.g. "coretemp", "amdgpu", "acpitz").
+    pub source: String,
+    /// Sensor label as reported by the driver (e.g. "Package id 0", "edge").
+    pub label: String,
+    pub celsius: f32,
+}
+
+/// Every temperature sensor the backend can see.
+pub fn get_temperatures() -> HardwareReadResult<Vec<TemperatureReading>> {
+    HardwareReadResult::logged("temperatures", backend::active().temperatures())
+}
+
+/// CPU package temperature, falling back to the hottest core.
+pub fn cpu_temp(readings: &[TemperatureReading]) -> Option<f32> {
+    readings.iter()
+        .find(|r| r.kind == SensorKind::CpuPackage)
+        .map(|r| r.celsius)
+        .or_else(|| hottest(readings, SensorKind::CpuCore))
+}
+
+/// Hottest GPU sensor (dGPU edge/junction or iGPU, whichever is reported).
+pub fn gpu_temp(readings: &[TemperatureReading]) -> Option<f32> {
+    hottest(readings, SensorKind::Gpu)
+}
+
+fn hottest(readings: &[TemperatureReading], kind: SensorKind) -> Option<f32> {
+    readings.iter()
+        .filter(|r| r.kind == kind)
+        .map(|r| r.celsius)
+        .reduce(f32::max)
 }
 
-pub fn get_gpu_temp() -> Option<f32> {
-    None
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(kind: SensorKind, celsius: f32) -> TemperatureReading {
+        TemperatureReading { kind, source: "test".to_string(), label: String::new(), celsius }
+    }
+
+    #[test]
+    fn cpu_prefers_package_over_cores() {
+        let readings = [
+            reading(SensorKind::CpuCore, 70.0),
+            reading(SensorKind::CpuPackage, 65.0),
+        ];
+        assert_eq!(cpu_temp(&readings), Some(65.0));
+        assert_eq!(cpu_temp(&readings[..1]), Some(70.0));
+    }
+
+    #[test]
+    fn gpu_reports_hottest_sensor_or_none() {
+        let readings = [
+            reading(SensorKind::Gpu, 48.0),
+            reading(SensorKind::Gpu, 61.0),
+            reading(SensorKind::Ssd, 80.0),
+        ];
+        assert_eq!(gpu_temp(&readings), Some(61.0));
+        assert_eq!(gpu_temp(&readings[2..]), None);
+    }
 }
@@ -0,0 +1,78 @@
+use crate::legion_core::backend::{self, HardwareBackend};
+use crate::legion_core::result::{HardwareReadResult, WriteError, WriteOutcome};
+use crate::legion_core::safety::guards::WriteToken;
+use crate::legion_core::safety::pipeline::verified_write;
+use crate::legion_core::safety::undo::{self, UndoChange, UndoHistory};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// On/off firmware switches that sit next to the battery and thermal controls.
+/// On Linux these map 1:1 onto `ideapad_acpi` sysfs attributes.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Toggle {
+    /// Fn key lock (F1-F12 act as function keys without holding Fn).
+    FnLock,
+    /// "Always On USB": charge devices from USB while the laptop sleeps.
+    UsbCharging,
+    /// Webcam power (the electronic camera kill switch).
+    CameraPower,
+}
+
+impl Toggle {
+    pub const ALL: [Toggle; 3] = [Toggle::FnLock, Toggle::UsbCharging, Toggle::CameraPower];
+
+    /// Stable snake_case key, shared by the sysfs attribute, CLI flag and simulator.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Toggle::FnLock => "fn_lock",
+            Toggle::UsbCharging => "usb_charging",
+            Toggle::CameraPower => "camera_power",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Toggle::FnLock => "Fn Lock",
+            Toggle::UsbCharging => "USB Charging",
+            Toggle::CameraPower => "Camera Power",
+        }
+    }
+
+    /// CLI flag that sets this toggle, e.g. `--fn-lock`.
+    pub fn cli_flag(&self) -> &'static str {
+        match self {
+            Toggle::FnLock => "--fn-lock",
+            Toggle::UsbCharging => "--usb-charging",
+            Toggle::CameraPower => "--camera-power",
+        }
+    }
+}
+
+impl fmt::Display for Toggle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+pub fn get_toggle(toggle: Toggle) -> HardwareReadResult<bool> {
+    HardwareReadResult::logged(toggle.label(), backend::active().toggle(toggle))
+}
+
+/// The `WriteToken` operation is the toggle's label.
+pub fn set_toggle(toggle: Toggle, enable: bool, expected: Option<bool>, token: &WriteToken) -> Result<WriteOutcome, WriteError> {
+    token.authorize(toggle.label())?;
+    change_toggle(backend::active(), undo::active(), token, toggle, expected, enable)
+}
+
+/// Switches `toggle` through `verified_write`, recording it in `history`.
+pub fn change_toggle(backend: &dyn HardwareBackend, history: Option<&UndoHistory>, token: &WriteToken, toggle: Toggle, expected: Option<bool>, enable: bool) -> Result<WriteOutcome, WriteError> {
+    let mut before = None;
+    let result = verified_write(token, expected.as_ref(), || backend.toggle(toggle), |current| {
+        before = current.ok();
+        backend.set_toggle(toggle, enable)?;
+        Ok(enable)
+    });
+    undo::record(history, token, &result, before.map(|before| UndoChange::Toggle { toggle, before, after: enable }));
+    result
+}
@@ -1,4 +1,9 @@
+pub mod backend;
+pub mod capability;
 pub mod device;
 pub mod hw;
+pub mod paths;
+pub mod result;
 pub mod safety;
-
+pub mod snapshot;
+pub mod time;
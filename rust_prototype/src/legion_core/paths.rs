@@ -0,0 +1,31 @@
+use std::env;
+use std::path::PathBuf;
+
+const APP_DIR: &str = "legion-loq-control";
+
+/// Per-user configuration directory (`%APPDATA%\legion-loq-control` on Windows,
+/// `$XDG_CONFIG_HOME/legion-loq-control` or `~/.config/legion-loq-control` elsewhere).
+///
+/// `None` if the environment gives no usable home; callers then skip user files.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|dir| dir.join(APP_DIR))
+}
+
+/// Machine-wide directory for files every instance must agree on, whichever user runs it
+/// (`%ProgramData%` on Windows, `/run/lock` elsewhere, the temp directory as a fallback).
+pub fn runtime_dir() -> PathBuf {
+    let shared = if cfg!(windows) {
+        env::var_os("ProgramData").map(PathBuf::from)
+    } else {
+        Some(PathBuf::from("/run/lock"))
+    };
+    shared.filter(|dir| dir.is_dir()).unwrap_or_else(env::temp_dir)
+}
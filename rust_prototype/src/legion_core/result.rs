@@ -0,0 +1,427 @@
+use log::warn;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Outcome of a hardware read. Names match the `status` values in
+/// `hardware-evidence/*/*-state-*.json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum ReadStatus {
+    Success,
+    Unsupported,
+    AccessDenied,
+    Unavailable,
+    InvalidData,
+    Failed,
+    TimedOut,
+}
+
+impl ReadStatus {
+    /// Process exit code for a CLI command that ended with this status.
+    ///
+    /// Stable: scripts may rely on these values. `2`, `8`, `9`, `11` and `12` are
+    /// reserved for write failures (rejected, unauthorized, conflict, busy, partially
+    /// applied) and `10` for unverified writes; see `WriteError::exit_code` and
+    /// `WriteOutcome::exit_code`.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ReadStatus::Success => 0,
+            ReadStatus::Failed => 1,
+            ReadStatus::Unsupported => 3,
+            ReadStatus::AccessDenied => 4,
+            ReadStatus::Unavailable => 5,
+            ReadStatus::InvalidData => 6,
+            ReadStatus::TimedOut => 7,
+        }
+    }
+}
+
+impl fmt::Display for ReadStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Why a backend call failed. Every variant carries a human-readable detail and maps
+/// to a stable, machine-readable `code()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HardwareError {
+    /// This backend or machine does not implement the feature at all.
+    Unsupported(String),
+    /// The provider rejected this execution context (usually: not elevated / no permission).
+    /// Says nothing about whether the feature exists.
+    AccessDenied(String),
+    /// The interface exists in principle but is not reachable now (driver not loaded,
+    /// device missing, read path not ported yet).
+    Unavailable(String),
+    /// The hardware answered with something that cannot be decoded.
+    InvalidData(String),
+    Failed(String),
+    TimedOut(String),
+}
+
+pub type HardwareResult<T> = Result<T, HardwareError>;
+
+impl HardwareError {
+    pub fn status(&self) -> ReadStatus {
+        match self {
+            HardwareError::Unsupported(_) => ReadStatus::Unsupported,
+            HardwareError::AccessDenied(_) => ReadStatus::AccessDenied,
+            HardwareError::Unavailable(_) => ReadStatus::Unavailable,
+            HardwareError::InvalidData(_) => ReadStatus::InvalidData,
+            HardwareError::Failed(_) => ReadStatus::Failed,
+            HardwareError::TimedOut(_) => ReadStatus::TimedOut,
+        }
+    }
+
+    /// Stable error code for JSON output and logs.
+    pub fn code(&self) -> &'static str {
+        match self {
+            HardwareError::Unsupported(_) => "unsupported",
+            HardwareError::AccessDenied(_) => "access_denied",
+            HardwareError::Unavailable(_) => "unavailable",
+            HardwareError::InvalidData(_) => "invalid_data",
+            HardwareError::Failed(_) => "failed",
+            HardwareError::TimedOut(_) => "timed_out",
+        }
+    }
+
+    pub fn detail(&self) -> &str {
+        match self {
+            HardwareError::Unsupported(d)
+            | HardwareError::AccessDenied(d)
+            | HardwareError::Unavailable(d)
+            | HardwareError::InvalidData(d)
+            | HardwareError::Failed(d)
+            | HardwareError::TimedOut(d) => d,
+        }
+    }
+
+    /// Classifies an I/O error, prefixing the detail with `context` (usually a path).
+    pub fn from_io(context: &str, e: io::Error) -> Self {
+        let detail = format!("{}: {}", context, e);
+        match e.kind() {
+            io::ErrorKind::PermissionDenied => HardwareError::AccessDenied(detail),
+            io::ErrorKind::NotFound => HardwareError::Unavailable(detail),
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => HardwareError::TimedOut(detail),
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => HardwareError::InvalidData(detail),
+            io::ErrorKind::Unsupported => HardwareError::Unsupported(detail),
+            _ => HardwareError::Failed(detail),
+        }
+    }
+}
+
+impl fmt::Display for HardwareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.detail())
+    }
+}
+
+impl Error for HardwareError {}
+
+impl From<io::Error> for HardwareError {
+    fn from(e: io::Error) -> Self {
+        let kind_context = e.kind().to_string();
+        HardwareError::from_io(&kind_context, e)
+    }
+}
+
+/// For platform helpers that still return `Box<dyn Error>` (WMI, COM, HID).
+impl From<Box<dyn Error>> for HardwareError {
+    fn from(e: Box<dyn Error>) -> Self {
+        let e = match e.downcast::<HardwareError>() {
+            Ok(hardware) => return *hardware,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<io::Error>() {
+            Ok(io) => return HardwareError::from(*io),
+            Err(e) => e,
+        };
+        let detail = e.to_string();
+        // E_ACCESSDENIED / WBEM_E_ACCESS_DENIED surface as text through the wmi crate.
+        let lower = detail.to_lowercase();
+        if lower.contains("access is denied") || lower.contains("access denied")
+            || lower.contains("0x80070005") || lower.contains("0x80041003") {
+            HardwareError::AccessDenied(detail)
+        } else {
+            HardwareError::Failed(detail)
+        }
+    }
+}
+
+impl Serialize for HardwareError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry("status", &self.status())?;
+        map.serialize_entry("errorCode", self.code())?;
+        map.serialize_entry("detail", self.detail())?;
+        map.end()
+    }
+}
+
+/// Result of one hardware read, as returned by every reader in `legion_core::hw`.
+///
+/// Unlike `Option`, a failure keeps its status, so callers can tell "not implemented"
+/// from "access denied" from "timed out".
+#[derive(Debug, Clone, PartialEq)]
+pub enum HardwareReadResult<T> {
+    Success(T),
+    Failure(HardwareError),
+}
+
+impl<T> HardwareReadResult<T> {
+    /// Wraps a backend result, logging failures as warnings.
+    pub fn logged(what: &str, result: HardwareResult<T>) -> Self {
+        match result {
+            Ok(value) => HardwareReadResult::Success(value),
+            Err(e) => {
+                warn!("Failed to read {} ({}): {}", what, e.code(), e);
+                HardwareReadResult::Failure(e)
+            }
+        }
+    }
+
+    pub fn status(&self) -> ReadStatus {
+        match self {
+            HardwareReadResult::Success(_) => ReadStatus::Success,
+            HardwareReadResult::Failure(e) => e.status(),
+        }
+    }
+
+    pub fn value(&self) -> Option<&T> {
+        match self {
+            HardwareReadResult::Success(value) => Some(value),
+            HardwareReadResult::Failure(_) => None,
+        }
+    }
+
+    pub fn ok(self) -> Option<T> {
+        match self {
+            HardwareReadResult::Success(value) => Some(value),
+            HardwareReadResult::Failure(_) => None,
+        }
+    }
+}
+
+impl<T> From<HardwareResult<T>> for HardwareReadResult<T> {
+    fn from(result: HardwareResult<T>) -> Self {
+        match result {
+            Ok(value) => HardwareReadResult::Success(value),
+            Err(e) => HardwareReadResult::Failure(e),
+        }
+    }
+}
+
+/// `{"status": "Success", "value": ...}` or `{"status": "AccessDenied", "errorCode": "access_denied", "detail": ...}`.
+impl<T: Serialize> Serialize for HardwareReadResult<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            HardwareReadResult::Success(value) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("status", &self.status())?;
+                map.serialize_entry("value", value)?;
+                map.end()
+            }
+            HardwareReadResult::Failure(e) => e.serialize(serializer),
+        }
+    }
+}
+
+/// Why a hardware write did not happen or did not succeed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteError {
+    /// No valid `WriteToken` for this write (wrong operation, or already used).
+    Unauthorized(String),
+    /// Refused before touching hardware: value not offered, illegal combination, ...
+    Rejected(String),
+    /// The current value is not the one the caller expected; nothing was written.
+    Conflict(String),
+    /// The backend call itself failed.
+    Hardware(HardwareError),
+    /// The write went through but the value read back afterwards is not the one requested.
+    ReadbackMismatch(String),
+    /// Another process held the machine-wide write lock for the whole wait; carries its PID if known.
+    Busy(Option<u32>),
+    /// A multi-step write failed after its first step; the detail says which steps went
+    /// through and the state the hardware was left in.
+    PartiallyApplied(String),
+}
+
+impl WriteError {
+    /// Status name for JSON output; hardware failures use the `ReadStatus` name.
+    pub fn status(&self) -> String {
+        match self {
+            WriteError::Unauthorized(_) => "Unauthorized".to_string(),
+            WriteError::Rejected(_) => "Rejected".to_string(),
+            WriteError::Conflict(_) => "Conflict".to_string(),
+            WriteError::Hardware(e) => e.status().to_string(),
+            WriteError::ReadbackMismatch(_) => ReadStatus::Failed.to_string(),
+            WriteError::Busy(_) => "Busy".to_string(),
+            WriteError::PartiallyApplied(_) => "PartiallyApplied".to_string(),
+        }
+    }
+
+    /// Stable error code for JSON output and logs.
+    pub fn code(&self) -> &'static str {
+        match self {
+            WriteError::Unauthorized(_) => "unauthorized",
+            WriteError::Rejected(_) => "rejected",
+            WriteError::Conflict(_) => "conflict",
+            WriteError::Hardware(e) => e.code(),
+            WriteError::ReadbackMismatch(_) => "readback_mismatch",
+            WriteError::Busy(_) => "busy",
+            WriteError::PartiallyApplied(_) => "partially_applied",
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            WriteError::Rejected(_) => 2,
+            WriteError::Unauthorized(_) => 8,
+            WriteError::Conflict(_) => 9,
+            WriteError::Hardware(e) => e.status().exit_code(),
+            WriteError::ReadbackMismatch(_) => ReadStatus::Failed.exit_code(),
+            WriteError::Busy(_) => 11,
+            WriteError::PartiallyApplied(_) => 12,
+        }
+    }
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WriteError::Unauthorized(detail) => write!(f, "{}", detail),
+            WriteError::Rejected(reason) => write!(f, "{}", reason),
+            WriteError::Conflict(detail) => write!(f, "{}", detail),
+            WriteError::Hardware(e) => write!(f, "{}", e),
+            WriteError::ReadbackMismatch(detail) => write!(f, "{}", detail),
+            WriteError::PartiallyApplied(detail) => write!(f, "{}", detail),
+            WriteError::Busy(Some(pid)) => write!(f, "Another instance (pid {}) is writing to the hardware; nothing was written", pid),
+            WriteError::Busy(None) => write!(f, "Another instance is writing to the hardware; nothing was written"),
+        }
+    }
+}
+
+impl Error for WriteError {}
+
+impl From<HardwareError> for WriteError {
+    fn from(e: HardwareError) -> Self {
+        WriteError::Hardware(e)
+    }
+}
+
+impl Serialize for WriteError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("status", &self.status())?;
+        map.serialize_entry("errorCode", self.code())?;
+        map.serialize_entry("detail", &self.to_string())?;
+        if let WriteError::Busy(Some(pid)) = self {
+            map.serialize_entry("holderPid", pid)?;
+        }
+        map.end()
+    }
+}
+
+/// A write that reached the hardware. Failures are a `WriteError`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteOutcome {
+    /// The value read back afterwards is the one requested.
+    Verified,
+    /// The write call succeeded but the value could not be read back.
+    Unverified(HardwareError),
+}
+
+impl WriteOutcome {
+    pub fn status(&self) -> &'static str {
+        match self {
+            WriteOutcome::Verified => "Verified",
+            WriteOutcome::Unverified(_) => "Unverified",
+        }
+    }
+
+    /// 0 for Verified; Unverified gets its own code so scripts can tell it from success.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            WriteOutcome::Verified => 0,
+            WriteOutcome::Unverified(_) => 10,
+        }
+    }
+}
+
+impl fmt::Display for WriteOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WriteOutcome::Verified => write!(f, "Verified"),
+            WriteOutcome::Unverified(e) => write!(f, "Unverified (readback {}: {})", e.code(), e),
+        }
+    }
+}
+
+impl Serialize for WriteOutcome {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("status", self.status())?;
+        if let WriteOutcome::Unverified(e) = self {
+            map.serialize_entry("errorCode", e.code())?;
+            map.serialize_entry("detail", e.detail())?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_errors_are_classified() {
+        let denied = HardwareError::from_io("/sys/x", io::Error::from(io::ErrorKind::PermissionDenied));
+        assert_eq!(denied.status(), ReadStatus::AccessDenied);
+        assert!(denied.detail().starts_with("/sys/x: "));
+        assert_eq!(HardwareError::from(io::Error::from(io::ErrorKind::NotFound)).code(), "unavailable");
+        assert_eq!(HardwareError::from(io::Error::from(io::ErrorKind::TimedOut)).code(), "timed_out");
+    }
+
+    #[test]
+    fn boxed_errors_keep_their_type() {
+        let boxed: Box<dyn Error> = Box::new(HardwareError::InvalidData("bad".into()));
+        assert_eq!(HardwareError::from(boxed), HardwareError::InvalidData("bad".into()));
+        let wmi: Box<dyn Error> = "HRESULT Call failed with: 0x80041003".into();
+        assert_eq!(HardwareError::from(wmi).status(), ReadStatus::AccessDenied);
+        let other: Box<dyn Error> = "No battery found".into();
+        assert_eq!(HardwareError::from(other).status(), ReadStatus::Failed);
+    }
+
+    #[test]
+    fn exit_codes_are_distinct_and_stable() {
+        let statuses = [
+            ReadStatus::Success, ReadStatus::Failed, ReadStatus::Unsupported, ReadStatus::AccessDenied,
+            ReadStatus::Unavailable, ReadStatus::InvalidData, ReadStatus::TimedOut,
+        ];
+        let codes: Vec<i32> = statuses.iter().map(|s| s.exit_code()).collect();
+        assert_eq!(codes, vec![0, 1, 3, 4, 5, 6, 7]);
+        assert_eq!(WriteError::Rejected("no".into()).exit_code(), 2);
+        assert_eq!(WriteError::Unauthorized("u".into()).exit_code(), 8);
+        assert_eq!(WriteError::Conflict("c".into()).exit_code(), 9);
+        assert_eq!(WriteOutcome::Unverified(HardwareError::Unsupported("u".into())).exit_code(), 10);
+        assert_eq!(WriteError::Busy(None).exit_code(), 11);
+        assert_eq!(WriteError::PartiallyApplied("p".into()).exit_code(), 12);
+        assert_eq!(WriteError::from(HardwareError::TimedOut("t".into())).exit_code(), 7);
+    }
+
+    #[test]
+    fn serialises_status_and_code() {
+        let ok: HardwareReadResult<bool> = HardwareReadResult::Success(true);
+        assert_eq!(serde_json::to_value(&ok).unwrap(), serde_json::json!({ "status": "Success", "value": true }));
+
+        let denied: HardwareReadResult<bool> = Err(HardwareError::AccessDenied("nope".into())).into();
+        assert_eq!(serde_json::to_value(&denied).unwrap(),
+            serde_json::json!({ "status": "AccessDenied", "errorCode": "access_denied", "detail": "nope" }));
+
+        assert_eq!(serde_json::to_value(WriteError::Unauthorized("u".into())).unwrap()["errorCode"], "unauthorized");
+        let busy = serde_json::to_value(WriteError::Busy(Some(4242))).unwrap();
+        assert_eq!((busy["errorCode"].as_str(), busy["holderPid"].as_u64()), (Some("busy"), Some(4242)));
+    }
+}
@@ -1,24 +1,176 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use crate::legion_core::result::WriteError;
+use log::info;
+use serde::Serialize;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
 
-// Global static override for write operations. Defaults to FALSE (Safe/Read-only).
-static WRITE_ENABLED: AtomicBool = AtomicBool::new(false);
+/// Grants kept in the audit log; older entries are dropped first.
+const AUDIT_CAPACITY: usize = 200;
 
-pub struct GlobalWriteLock;
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static AUDIT_LOG: Mutex<Vec<AuditEntry>> = Mutex::new(Vec::new());
 
-impl GlobalWriteLock {
-    /// Request permission to perform write operations.
-    /// This should be called when the user explicitly provides a --force or --set flag.
-    pub fn request_write_access() {
-        WRITE_ENABLED.store(true, Ordering::SeqCst);
+/// Authorization for exactly one hardware write.
+///
+/// Every setter takes a `&WriteToken` granted for its operation. A token can be used
+/// once and is revoked when dropped; both the grant and the revocation are recorded
+/// in the in-memory audit log.
+#[derive(Debug)]
+pub struct WriteToken {
+    id: u64,
+    operation: String,
+    target: String,
+    reason: String,
+    /// Granted by `undo`: the write restores an earlier value and is not itself undoable.
+    restore: bool,
+    used: AtomicBool,
+}
+
+impl WriteToken {
+    /// Grants a token for one write of `operation` (e.g. `battery::CHARGE_MODE_OPERATION`)
+    /// to `target`, and records why.
+    pub fn grant(operation: &str, target: impl Display, reason: &str) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let token = Self {
+            id,
+            operation: operation.to_string(),
+            target: target.to_string(),
+            reason: reason.to_string(),
+            restore: false,
+            used: AtomicBool::new(false),
+        };
+        info!("Write token #{} granted: {} -> {} ({})", id, token.operation, token.target, token.reason);
+
+        let mut log = AUDIT_LOG.lock().unwrap();
+        if log.len() >= AUDIT_CAPACITY {
+            log.remove(0);
+        }
+        log.push(AuditEntry {
+            id,
+            operation: token.operation.clone(),
+            target: token.target.clone(),
+            reason: token.reason.clone(),
+            granted_at: SystemTime::now(),
+            used: false,
+            revoked_at: None,
+        });
+        token
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
     }
 
-    /// Check if write operations are currently allowed.
-    pub fn is_write_allowed() -> bool {
-        WRITE_ENABLED.load(Ordering::SeqCst)
+    pub fn operation(&self) -> &str {
+        &self.operation
+    }
+
+    /// The value the write was granted for, as shown to the user.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// Marks the write as an undo, so it is not added to the undo history.
+    pub fn restoring(mut self) -> Self {
+        self.restore = true;
+        self
+    }
+
+    pub fn is_restore(&self) -> bool {
+        self.restore
+    }
+
+    /// Consumes the token's single use for `operation`. Called by the setters.
+    pub fn authorize(&self, operation: &str) -> Result<(), WriteError> {
+        if self.operation != operation {
+            return Err(WriteError::Unauthorized(format!(
+                "Write token #{} was granted for {}, not {}", self.id, self.operation, operation)));
+        }
+        if self.used.swap(true, Ordering::SeqCst) {
+            return Err(WriteError::Unauthorized(format!("Write token #{} was already used", self.id)));
+        }
+        update_entry(self.id, |e| e.used = true);
+        Ok(())
+    }
+}
+
+impl Drop for WriteToken {
+    fn drop(&mut self) {
+        update_entry(self.id, |e| e.revoked_at = Some(SystemTime::now()));
+    }
+}
+
+fn update_entry(id: u64, f: impl FnOnce(&mut AuditEntry)) {
+    if let Some(entry) = AUDIT_LOG.lock().unwrap().iter_mut().find(|e| e.id == id) {
+        f(entry);
+    }
+}
+
+/// One token grant in the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: u64,
+    pub operation: String,
+    pub target: String,
+    pub reason: String,
+    #[serde(serialize_with = "serialize_time")]
+    pub granted_at: SystemTime,
+    /// The token reached a setter.
+    pub used: bool,
+    #[serde(serialize_with = "serialize_optional_time")]
+    pub revoked_at: Option<SystemTime>,
+}
+
+fn serialize_time<S: serde::Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&crate::legion_core::time::format_utc(*time))
+}
+
+fn serialize_optional_time<S: serde::Serializer>(time: &Option<SystemTime>, serializer: S) -> Result<S::Ok, S::Error> {
+    match time {
+        Some(time) => serialize_time(time, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Token grants of this process, oldest first.
+pub fn audit_log() -> Vec<AuditEntry> {
+    AUDIT_LOG.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64) -> AuditEntry {
+        audit_log().into_iter().find(|e| e.id == id).expect("grant is logged")
+    }
+
+    #[test]
+    fn grant_and_revocation_are_audited() {
+        let token = WriteToken::grant("Power Profile", "Quiet", "test");
+        let id = token.id();
+        let granted = entry(id);
+        assert_eq!((granted.operation.as_str(), granted.target.as_str(), granted.reason.as_str()), ("Power Profile", "Quiet", "test"));
+        assert!(!granted.used && granted.revoked_at.is_none());
+
+        token.authorize("Power Profile").unwrap();
+        drop(token);
+        let revoked = entry(id);
+        assert!(revoked.used && revoked.revoked_at.is_some());
     }
 
-    /// Revoke write access (lock down).
-    pub fn revoke_access() {
-        WRITE_ENABLED.store(false, Ordering::SeqCst);
+    #[test]
+    fn token_covers_one_write_of_one_operation() {
+        let token = WriteToken::grant("Fn Lock", "ON", "test");
+        assert_eq!(token.authorize("Camera Power").unwrap_err().code(), "unauthorized");
+        token.authorize("Fn Lock").unwrap();
+        assert!(matches!(token.authorize("Fn Lock"), Err(WriteError::Unauthorized(_))));
     }
 }
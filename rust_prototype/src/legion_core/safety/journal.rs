@@ -0,0 +1,362 @@
+//! Append-only record of hardware writes: an intent line before each write and a
+//! result line after it, so a write interrupted by a crash is visible on the next start.
+//!
+//! Reconciliation only reports: it reads the current value and marks the intent as
+//! seen, but never repeats a write.
+
+use crate::legion_core::backend::HardwareBackend;
+use crate::legion_core::hw::{battery, lighting, power};
+use crate::legion_core::hw::toggles::Toggle;
+use crate::legion_core::paths;
+use crate::legion_core::result::{HardwareResult, WriteError, WriteOutcome};
+use crate::legion_core::safety::guards::WriteToken;
+use crate::legion_core::time::format_utc;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt::Debug;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable that moves the journal file.
+pub const JOURNAL_ENV: &str = "LEGION_JOURNAL";
+
+static ACTIVE: OnceLock<Journal> = OnceLock::new();
+
+/// A write about to be attempted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Intent {
+    pub id: String,
+    pub at_utc: String,
+    pub pid: u32,
+    /// `HardwareBackend::name` the write went to.
+    pub backend: String,
+    pub operation: String,
+    pub target: String,
+    pub reason: String,
+    /// Value read just before the write, if the read succeeded.
+    pub before: Option<String>,
+}
+
+/// How a journaled write ended.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Completion {
+    pub id: String,
+    pub at_utc: String,
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// An interrupted write found on a later start, with the value read at that time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reconciliation {
+    pub id: String,
+    pub at_utc: String,
+    /// Current value, or `Unknown [code]` if it could not be read.
+    pub current: String,
+}
+
+/// One line of the journal file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum Record {
+    Intent(Intent),
+    Result(Completion),
+    Reconciled(Reconciliation),
+}
+
+/// An intent with whatever was recorded about it afterwards.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    #[serde(flatten)]
+    pub intent: Intent,
+    pub result: Option<Completion>,
+    pub reconciled: Option<Reconciliation>,
+}
+
+impl JournalEntry {
+    /// No result was recorded: the process stopped between intent and result.
+    pub fn is_incomplete(&self) -> bool {
+        self.result.is_none()
+    }
+}
+
+pub struct Journal {
+    path: PathBuf,
+    backend: String,
+}
+
+impl Journal {
+    /// A journal at `path` for writes to `backend`; nothing is touched until the first record.
+    pub fn at(path: impl Into<PathBuf>, backend: &str) -> Self {
+        Self { path: path.into(), backend: backend.to_string() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records the intent to write `token`'s target and returns its id.
+    pub fn begin(&self, token: &WriteToken, before: Option<String>) -> String {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or_default();
+        let id = format!("{}-{}-{}", millis, std::process::id(), token.id());
+        self.append(&Record::Intent(Intent {
+            id: id.clone(),
+            at_utc: format_utc(SystemTime::now()),
+            pid: std::process::id(),
+            backend: self.backend.clone(),
+            operation: token.operation().to_string(),
+            target: token.target().to_string(),
+            reason: token.reason().to_string(),
+            before,
+        }));
+        id
+    }
+
+    /// Records how the write started by `begin` ended.
+    pub fn finish(&self, id: &str, result: &Result<WriteOutcome, WriteError>) {
+        let (status, error_code, detail) = match result {
+            Ok(WriteOutcome::Verified) => ("Verified".to_string(), None, None),
+            Ok(WriteOutcome::Unverified(e)) => ("Unverified".to_string(), Some(e.code().to_string()), Some(e.to_string())),
+            Err(e) => (e.status(), Some(e.code().to_string()), Some(e.to_string())),
+        };
+        self.append(&Record::Result(Completion {
+            id: id.to_string(),
+            at_utc: format_utc(SystemTime::now()),
+            status,
+            error_code,
+            detail,
+        }));
+    }
+
+    /// A failed append is logged, not fatal: the journal must never block a write.
+    fn append(&self, record: &Record) {
+        let result = (|| {
+            if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)?;
+            }
+            let mut file = OpenOptions::new().create(true).read(true).append(true).open(&self.path)?;
+            let mut line = serde_json::to_string(record).map_err(io::Error::other)?;
+            // A crash can leave a torn last line; do not glue this record onto it.
+            if file.metadata()?.len() > 0 {
+                let mut last = [0u8; 1];
+                file.seek(SeekFrom::End(-1))?;
+                file.read_exact(&mut last)?;
+                if last[0] != b'\n' {
+                    line.insert(0, '\n');
+                }
+            }
+            writeln!(file, "{}", line)?;
+            file.sync_data()
+        })();
+        if let Err(e) = result {
+            warn!("Could not write journal {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Every intent in the file, oldest first. A missing file is an empty journal;
+    /// unreadable lines (e.g. torn by a crash) are skipped.
+    pub fn entries(&self) -> io::Result<Vec<JournalEntry>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut entries: Vec<JournalEntry> = Vec::new();
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            match serde_json::from_str::<Record>(line) {
+                Ok(Record::Intent(intent)) => entries.push(JournalEntry { intent, result: None, reconciled: None }),
+                Ok(Record::Result(result)) => {
+                    if let Some(entry) = entries.iter_mut().find(|e| e.intent.id == result.id) {
+                        entry.result = Some(result);
+                    }
+                }
+                Ok(Record::Reconciled(reconciled)) => {
+                    if let Some(entry) = entries.iter_mut().find(|e| e.intent.id == reconciled.id) {
+                        entry.reconciled = Some(reconciled);
+                    }
+                }
+                Err(e) => warn!("Skipping unreadable journal line in {}: {}", self.path.display(), e),
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Finds incomplete intents not reported before, reads the current value of each
+    /// and records it. Intents of a process that is still running, or for another
+    /// backend, are left alone. Nothing is written to the hardware.
+    pub fn reconcile(&self, backend: &dyn HardwareBackend, is_running: impl Fn(u32) -> bool) -> Vec<JournalEntry> {
+        let entries = match self.entries() {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Could not read journal {}: {}", self.path.display(), e);
+                return Vec::new();
+            }
+        };
+
+        let mut found = Vec::new();
+        for mut entry in entries {
+            let intent = &entry.intent;
+            if !entry.is_incomplete() || entry.reconciled.is_some() || intent.backend != backend.name()
+                || intent.pid == std::process::id() || is_running(intent.pid) {
+                continue;
+            }
+            let reconciled = Reconciliation {
+                id: intent.id.clone(),
+                at_utc: format_utc(SystemTime::now()),
+                current: current_value(backend, &intent.operation),
+            };
+            self.append(&Record::Reconciled(reconciled.clone()));
+            entry.reconciled = Some(reconciled);
+            found.push(entry);
+        }
+        found
+    }
+}
+
+/// The value `operation` controls, formatted like the journal's `before`.
+fn current_value(backend: &dyn HardwareBackend, operation: &str) -> String {
+    fn show<T: Debug>(read: HardwareResult<T>) -> String {
+        match read {
+            Ok(value) => format!("{:?}", value),
+            Err(e) => format!("Unknown [{}]", e.code()),
+        }
+    }
+
+    match operation {
+        battery::CHARGE_MODE_OPERATION => show(backend.charge_mode()),
+        power::POWER_PROFILE_OPERATION => show(backend.power_profile()),
+        lighting::LIGHTING_OPERATION => show(lighting::read_lighting(backend)),
+        _ => match Toggle::ALL.into_iter().find(|t| t.label() == operation) {
+            Some(toggle) => show(backend.toggle(toggle)),
+            None => "Unknown [unsupported]".to_string(),
+        },
+    }
+}
+
+/// Journal file: `LEGION_JOURNAL`, or `write-journal.jsonl` in the config directory.
+pub fn path() -> Option<PathBuf> {
+    match env::var_os(JOURNAL_ENV) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => paths::config_dir().map(|dir| dir.join("write-journal.jsonl")),
+    }
+}
+
+/// Opens the process-wide journal for `backend`. Until this is called (as in unit
+/// tests) writes are not journaled.
+pub fn init(backend: &dyn HardwareBackend) -> Option<&'static Journal> {
+    let path = path()?;
+    Some(ACTIVE.get_or_init(|| Journal::at(path, backend.name())))
+}
+
+pub fn active() -> Option<&'static Journal> {
+    ACTIVE.get()
+}
+
+/// Whether process `pid` exists, so its in-flight intents are not reported.
+pub fn process_is_running(pid: u32) -> bool {
+    let mut system = sysinfo::System::new();
+    system.refresh_process_specifics(sysinfo::Pid::from_u32(pid), sysinfo::ProcessRefreshKind::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legion_core::backend::simulated::SimulatedBackend;
+    use crate::legion_core::hw::power::PowerProfile;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    fn journal() -> Journal {
+        let path = env::temp_dir().join(format!("legion-journal-{}-{}.jsonl", std::process::id(), NEXT.fetch_add(1, Ordering::SeqCst)));
+        let _ = fs::remove_file(&path);
+        Journal::at(path, "simulated")
+    }
+
+    /// An intent left behind by a process that died before recording the result.
+    fn interrupted(journal: &Journal, pid: u32) {
+        journal.append(&Record::Intent(Intent {
+            id: format!("1-{}-1", pid),
+            at_utc: "2026-10-18T07:00:00Z".to_string(),
+            pid,
+            backend: "simulated".to_string(),
+            operation: power::POWER_PROFILE_OPERATION.to_string(),
+            target: "Performance".to_string(),
+            reason: "CLI --set-profile".to_string(),
+            before: Some("Balanced".to_string()),
+        }));
+    }
+
+    #[test]
+    fn intent_and_result_are_paired() {
+        let journal = journal();
+        let token = WriteToken::grant(power::POWER_PROFILE_OPERATION, "Quiet", "test");
+        let id = journal.begin(&token, Some("Balanced".to_string()));
+        journal.finish(&id, &Ok(WriteOutcome::Verified));
+
+        let entries = journal.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].intent.target.as_str(), entries[0].intent.before.as_deref()), ("Quiet", Some("Balanced")));
+        assert_eq!(entries[0].result.as_ref().unwrap().status, "Verified");
+        assert!(!entries[0].is_incomplete());
+        journal.clear().unwrap();
+        assert!(journal.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn failed_write_records_its_code() {
+        let journal = journal();
+        let token = WriteToken::grant("X", "2", "test");
+        let id = journal.begin(&token, None);
+        journal.finish(&id, &Err(WriteError::Conflict("c".into())));
+        let result = journal.entries().unwrap().remove(0).result.unwrap();
+        assert_eq!((result.status.as_str(), result.error_code.as_deref()), ("Conflict", Some("conflict")));
+        journal.clear().unwrap();
+    }
+
+    #[test]
+    fn interrupted_write_is_reported_once_and_not_replayed() {
+        let journal = journal();
+        interrupted(&journal, 999_999);
+        // A torn last line from the crash is skipped.
+        OpenOptions::new().append(true).open(journal.path()).unwrap().write_all(b"{\"kind\":\"res").unwrap();
+
+        let sim = SimulatedBackend::new();
+        let found = journal.reconcile(&sim, |_| false);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].intent.target, "Performance");
+        assert_eq!(found[0].reconciled.as_ref().unwrap().current, format!("{:?}", sim.power_profile().unwrap()));
+        assert_eq!(sim.power_profile().unwrap(), PowerProfile::Balanced);
+
+        assert!(journal.reconcile(&sim, |_| false).is_empty());
+        assert!(journal.entries().unwrap()[0].reconciled.is_some());
+        journal.clear().unwrap();
+    }
+
+    #[test]
+    fn intents_of_running_processes_are_left_alone() {
+        let journal = journal();
+        interrupted(&journal, 4242);
+        assert!(journal.reconcile(&SimulatedBackend::new(), |pid| pid == 4242).is_empty());
+        journal.clear().unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+//! Machine-wide exclusive lock around hardware writes, so the GUI, the CLI and
+//! scripts never interleave writes to EnergyDrv, WMI or the keyboard controller.
+//!
+//! Only writes take the lock; reads never wait for it.
+
+use crate::legion_core::paths;
+use crate::legion_core::result::{HardwareError, WriteError};
+use log::{debug, warn};
+use std::cell::Cell;
+use std::env;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Environment variable that moves the lock file.
+pub const LOCK_ENV: &str = "LEGION_LOCK_FILE";
+
+/// How long a write waits for another instance before giving up with `Busy`.
+pub const WAIT: Duration = Duration::from_secs(5);
+const POLL: Duration = Duration::from_millis(50);
+
+thread_local! {
+    /// Whether this thread holds the lock through `acquire`.
+    static HELD: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Held while one write runs; released on drop.
+#[derive(Debug)]
+pub struct WriteLock {
+    /// `None` for a nested `acquire`, which leaves the lock to the outer guard.
+    _file: Option<File>,
+    /// Taken by `acquire` (not `acquire_at`): clears `HELD` on drop.
+    outermost: bool,
+}
+
+impl Drop for WriteLock {
+    fn drop(&mut self) {
+        if self.outermost {
+            HELD.with(|held| held.set(false));
+        }
+    }
+}
+
+/// Lock file: `LEGION_LOCK_FILE`, or `legion-loq-control.lock` in the machine-wide runtime directory.
+pub fn path() -> PathBuf {
+    if let Some(path) = env::var_os(LOCK_ENV) {
+        return PathBuf::from(path);
+    }
+    paths::runtime_dir().join("legion-loq-control.lock")
+}
+
+/// The holder writes its PID next to the lock file so a waiting process can name it.
+fn owner_path(lock: &Path) -> PathBuf {
+    let mut name = lock.as_os_str().to_owned();
+    name.push(".owner");
+    PathBuf::from(name)
+}
+
+/// Takes the machine-wide write lock, waiting up to `WAIT`. A thread that already holds
+/// it gets a nested guard, so a step that needs the lock can run inside a larger one.
+pub fn acquire() -> Result<WriteLock, WriteError> {
+    if HELD.with(|held| held.get()) {
+        return Ok(WriteLock { _file: None, outermost: false });
+    }
+    let mut lock = acquire_at(&path(), WAIT)?;
+    lock.outermost = true;
+    HELD.with(|held| held.set(true));
+    Ok(lock)
+}
+
+pub fn acquire_at(path: &Path, wait: Duration) -> Result<WriteLock, WriteError> {
+    let file = open_shared(path).map_err(|e| HardwareError::from_io(&path.display().to_string(), e))?;
+    let deadline = Instant::now() + wait;
+    loop {
+        match file.try_lock() {
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => thread::sleep(POLL),
+            Err(TryLockError::WouldBlock) => {
+                let holder = fs::read_to_string(owner_path(path)).ok().and_then(|pid| pid.trim().parse().ok());
+                warn!("Write lock {} still held by pid {:?} after {:?}", path.display(), holder, wait);
+                return Err(WriteError::Busy(holder));
+            }
+            Err(TryLockError::Error(e)) => return Err(HardwareError::from_io(&path.display().to_string(), e).into()),
+        }
+    }
+
+    // Best effort: a stale or missing owner file only makes a Busy message less precise.
+    if let Err(e) = open_shared(&owner_path(path)).and_then(|owner| {
+        owner.set_len(0)?;
+        io::Write::write_all(&mut &owner, std::process::id().to_string().as_bytes())
+    }) {
+        debug!("Could not record write lock owner: {}", e);
+    }
+    Ok(WriteLock { _file: Some(file), outermost: false })
+}
+
+/// Opens (creating if needed) a file other users can lock too. A file another user
+/// created read-only is still lockable through a read-only handle.
+fn open_shared(path: &Path) -> io::Result<File> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create(true).truncate(false);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o666);
+    match options.open(path) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => File::open(path),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::Once;
+
+    /// Points `LEGION_LOCK_FILE` at a per-process file, so tests that write never wait on
+    /// (or block) a running app. Call before the first write.
+    pub(crate) fn use_test_lock_file() {
+        static SET: Once = Once::new();
+        SET.call_once(|| env::set_var(LOCK_ENV, env::temp_dir().join(format!("legion-loq-control-test-{}.lock", std::process::id()))));
+    }
+
+    fn lock_file(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("legion-lock-{}-{}.lock", std::process::id(), name))
+    }
+
+    #[test]
+    fn second_writer_is_busy_and_names_the_holder() {
+        let path = lock_file("busy");
+        let held = acquire_at(&path, Duration::ZERO).unwrap();
+
+        let started = Instant::now();
+        let err = acquire_at(&path, Duration::from_millis(120)).unwrap_err();
+        assert!(started.elapsed() >= Duration::from_millis(120));
+        assert_eq!(err, WriteError::Busy(Some(std::process::id())));
+        assert!(err.to_string().contains(&format!("pid {}", std::process::id())));
+
+        drop(held);
+        assert!(acquire_at(&path, Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn nested_acquire_keeps_the_outer_lock() {
+        use_test_lock_file();
+        let outer = acquire().unwrap();
+        drop(acquire().unwrap());
+        let other = thread::spawn(|| acquire_at(&path(), Duration::from_millis(50))).join().unwrap();
+        assert!(matches!(other, Err(WriteError::Busy(_))));
+        drop(outer);
+    }
+
+    #[test]
+    fn released_lock_is_taken_while_waiting() {
+        let path = lock_file("wait");
+        let held = acquire_at(&path, Duration::ZERO).unwrap();
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            drop(held);
+        });
+        assert!(acquire_at(&path, Duration::from_secs(5)).is_ok());
+        releaser.join().unwrap();
+    }
+}
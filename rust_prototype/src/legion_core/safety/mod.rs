@@ -1 +1,5 @@
 pub mod guards;
+pub mod journal;
+pub mod lock;
+pub mod pipeline;
+pub mod undo;
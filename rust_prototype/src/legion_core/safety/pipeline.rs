@@ -0,0 +1,105 @@
+use crate::legion_core::result::{HardwareResult, WriteError, WriteOutcome};
+use crate::legion_core::safety::guards::WriteToken;
+use crate::legion_core::safety::{journal, lock};
+use std::fmt::Debug;
+
+/// Read-before-write and readback verification around one hardware write, all under
+/// the machine-wide write lock (`Busy` if another instance keeps it past `lock::WAIT`).
+///
+/// 1. `read` captures the current value. If the caller passed `expected` and the
+///    hardware disagrees (or cannot be read), the write is refused with `Conflict`.
+/// 2. The intent (the token's operation and target) goes to the write journal, if
+///    one is open; `write` gets the captured value and returns the value it wrote.
+/// 3. `read` runs again: the written value → `Verified`, another value →
+///    `ReadbackMismatch`, a failed read → `Unverified`. The journal gets the result.
+pub fn verified_write<T: PartialEq + Debug>(
+    token: &WriteToken,
+    expected: Option<&T>,
+    read: impl Fn() -> HardwareResult<T>,
+    write: impl FnOnce(HardwareResult<T>) -> Result<T, WriteError>,
+) -> Result<WriteOutcome, WriteError> {
+    let what = token.operation();
+    let _lock = lock::acquire()?;
+    let current = read();
+    if let Some(expected) = expected {
+        match &current {
+            Ok(current) if current != expected => return Err(WriteError::Conflict(
+                format!("{} is {:?}, expected {:?}; nothing was written", what, current, expected))),
+            Err(e) => return Err(WriteError::Conflict(
+                format!("{} could not be read ({}), expected {:?}; nothing was written", what, e.code(), expected))),
+            Ok(_) => {}
+        }
+    }
+
+    let journal = journal::active();
+    let intent = journal.map(|j| j.begin(token, current.as_ref().ok().map(|v| format!("{:?}", v))));
+
+    let result = write(current).and_then(|target| match read() {
+        Ok(actual) if actual == target => Ok(WriteOutcome::Verified),
+        Ok(actual) => Err(WriteError::ReadbackMismatch(
+            format!("{} reads back as {:?} after writing {:?}", what, actual, target))),
+        Err(e) => Ok(WriteOutcome::Unverified(e)),
+    });
+
+    if let (Some(journal), Some(intent)) = (journal, intent) {
+        journal.finish(&intent, &result);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legion_core::result::HardwareError;
+    use std::cell::Cell;
+
+    fn token() -> WriteToken {
+        lock::tests::use_test_lock_file();
+        WriteToken::grant("X", "test value", "test")
+    }
+
+    fn write_to(cell: &Cell<u32>, value: u32) -> impl FnOnce(HardwareResult<u32>) -> Result<u32, WriteError> + '_ {
+        move |_| { cell.set(value); Ok(value) }
+    }
+
+    #[test]
+    fn readback_of_the_written_value_is_verified() {
+        let hw = Cell::new(1);
+        assert_eq!(verified_write(&token(), Some(&1), || Ok(hw.get()), write_to(&hw, 2)), Ok(WriteOutcome::Verified));
+        assert_eq!(hw.get(), 2);
+    }
+
+    #[test]
+    fn unexpected_current_value_is_a_conflict() {
+        let hw = Cell::new(3);
+        let err = verified_write(&token(), Some(&1), || Ok(hw.get()), write_to(&hw, 2)).unwrap_err();
+        assert!(matches!(err, WriteError::Conflict(_)));
+        assert_eq!(hw.get(), 3);
+
+        let err = verified_write(&token(), Some(&1), || Err(HardwareError::TimedOut("t".into())), write_to(&hw, 2)).unwrap_err();
+        assert_eq!(err.code(), "conflict");
+        assert_eq!(hw.get(), 3);
+    }
+
+    #[test]
+    fn ignored_write_is_a_readback_mismatch() {
+        let hw = Cell::new(1);
+        let err = verified_write(&token(), None, || Ok(hw.get()), |_| Ok(2)).unwrap_err();
+        assert_eq!(err.code(), "readback_mismatch");
+    }
+
+    #[test]
+    fn unreadable_value_is_unverified() {
+        let written = Cell::new(false);
+        let outcome = verified_write(&token(), None, || Err(HardwareError::Unsupported("no read".into())),
+            |_| { written.set(true); Ok(5) }).unwrap();
+        assert!(written.get());
+        assert_eq!(outcome.status(), "Unverified");
+    }
+
+    #[test]
+    fn failed_write_is_reported() {
+        let err = verified_write(&token(), None, || Ok(1), |_| Err(HardwareError::AccessDenied("d".into()).into())).unwrap_err();
+        assert_eq!(err.code(), "access_denied");
+    }
+}
@@ -0,0 +1,354 @@
+//! Bounded, persisted undo history: every verified write that changed a control stores
+//! the value it replaced, and `undo_last` writes that value back through the same setter.
+
+use crate::legion_core::backend::HardwareBackend;
+use crate::legion_core::hw::battery::{self, ChargeMode};
+use crate::legion_core::hw::lighting::{self, KeyboardLighting};
+use crate::legion_core::hw::power::{self, PowerProfile};
+use crate::legion_core::hw::toggles::{self, Toggle};
+use crate::legion_core::paths;
+use crate::legion_core::result::{WriteError, WriteOutcome};
+use crate::legion_core::safety::guards::WriteToken;
+use crate::legion_core::safety::lock;
+use crate::legion_core::time::format_utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+/// Environment variable that moves the history file.
+pub const UNDO_ENV: &str = "LEGION_UNDO_FILE";
+
+/// Changes kept; the oldest is dropped first.
+pub const HISTORY_LIMIT: usize = 20;
+
+static ACTIVE: OnceLock<UndoHistory> = OnceLock::new();
+
+/// One control's value before and after a verified write.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "control", rename_all = "camelCase")]
+pub enum UndoChange {
+    ChargeMode { before: ChargeMode, after: ChargeMode },
+    PowerProfile { before: PowerProfile, after: PowerProfile },
+    Toggle { toggle: Toggle, before: bool, after: bool },
+    KeyboardLighting { before: KeyboardLighting, after: KeyboardLighting },
+}
+
+impl UndoChange {
+    fn is_noop(&self) -> bool {
+        match self {
+            UndoChange::ChargeMode { before, after } => before == after,
+            UndoChange::PowerProfile { before, after } => before == after,
+            UndoChange::Toggle { before, after, .. } => before == after,
+            UndoChange::KeyboardLighting { before, after } => before == after,
+        }
+    }
+
+    pub fn operation(&self) -> &'static str {
+        match self {
+            UndoChange::ChargeMode { .. } => battery::CHARGE_MODE_OPERATION,
+            UndoChange::PowerProfile { .. } => power::POWER_PROFILE_OPERATION,
+            UndoChange::Toggle { toggle, .. } => toggle.label(),
+            UndoChange::KeyboardLighting { .. } => lighting::LIGHTING_OPERATION,
+        }
+    }
+
+    /// The value an undo writes back, as shown to the user.
+    pub fn restored_value(&self) -> String {
+        match self {
+            UndoChange::ChargeMode { before, .. } => before.to_string(),
+            UndoChange::PowerProfile { before, .. } => format!("{:?}", before),
+            UndoChange::Toggle { before, .. } => if *before { "ON" } else { "OFF" }.to_string(),
+            UndoChange::KeyboardLighting { before, .. } => before.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for UndoChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UndoChange::ChargeMode { before, after } => write!(f, "Charge Mode {} → {}", before, after),
+            UndoChange::PowerProfile { before, after } => write!(f, "Power Profile {:?} → {:?}", before, after),
+            UndoChange::Toggle { toggle, before, after } => write!(f, "{} {} → {}", toggle, on_off(*before), on_off(*after)),
+            UndoChange::KeyboardLighting { before, after } => write!(f, "Keyboard Lighting {} → {}", before, after),
+        }
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled { "ON" } else { "OFF" }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoEntry {
+    pub at_utc: String,
+    /// `HardwareBackend::name` the change was made on.
+    pub backend: String,
+    #[serde(flatten)]
+    pub change: UndoChange,
+}
+
+/// Every read-modify-write of the file runs under the machine-wide write lock, since
+/// the GUI and the CLI share it.
+pub struct UndoHistory {
+    path: PathBuf,
+    backend: String,
+}
+
+impl UndoHistory {
+    /// History in `path` for changes made on `backend`; nothing is touched until the first change.
+    pub fn at(path: impl Into<PathBuf>, backend: &str) -> Self {
+        Self { path: path.into(), backend: backend.to_string() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// All changes in the file, oldest first. A missing or unreadable file is an empty history.
+    pub fn entries(&self) -> Vec<UndoEntry> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Vec::new(),
+            Err(e) => {
+                warn!("Could not read undo history {}: {}", self.path.display(), e);
+                return Vec::new();
+            }
+        };
+        serde_json::from_str(&text).unwrap_or_else(|e| {
+            warn!("Ignoring unreadable undo history {}: {}", self.path.display(), e);
+            Vec::new()
+        })
+    }
+
+    /// Most recent change made on this backend.
+    pub fn last(&self) -> Option<UndoEntry> {
+        self.entries().into_iter().rev().find(|e| e.backend == self.backend)
+    }
+
+    fn push(&self, change: UndoChange) {
+        let _lock = match lock::acquire() {
+            Ok(lock) => lock,
+            Err(e) => {
+                warn!("Could not record {} in the undo history: {}", change, e);
+                return;
+            }
+        };
+        let mut entries = self.entries();
+        entries.push(UndoEntry { at_utc: format_utc(SystemTime::now()), backend: self.backend.clone(), change });
+        let excess = entries.len().saturating_sub(HISTORY_LIMIT);
+        entries.drain(..excess);
+        self.save(&entries);
+    }
+
+    /// Drops `entry` (the most recent change on this backend). The caller holds the write lock.
+    fn remove(&self, entry: &UndoEntry) {
+        let mut entries = self.entries();
+        if let Some(i) = entries.iter().rposition(|e| e == entry) {
+            entries.remove(i);
+            self.save(&entries);
+        }
+    }
+
+    /// Replaces the file in one rename so a crash never leaves half a history.
+    fn save(&self, entries: &[UndoEntry]) {
+        let result = (|| {
+            if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)?;
+            }
+            let temp = self.path.with_extension("tmp");
+            fs::write(&temp, serde_json::to_string_pretty(entries).map_err(io::Error::other)?)?;
+            fs::rename(&temp, &self.path)
+        })();
+        if let Err(e) = result {
+            warn!("Could not save undo history {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Writes back, on `backend`, the value the most recent change replaced, expecting the
+    /// value that change wrote. Returns `None` if there is nothing to undo. The entry is
+    /// dropped once the write went through, or if the control has changed since
+    /// (`Conflict`); other failures keep it for another try.
+    pub fn undo_last(&self, backend: &dyn HardwareBackend, reason: &str) -> Option<(UndoEntry, Result<WriteOutcome, WriteError>)> {
+        // Held from picking the entry to dropping it, so two instances never undo the same change
+        let _lock = match lock::acquire() {
+            Ok(lock) => lock,
+            Err(e) => return self.last().map(|entry| (entry, Err(e))),
+        };
+        let entry = self.last()?;
+        let token = WriteToken::grant(entry.change.operation(), entry.change.restored_value(), reason).restoring();
+        info!("Undoing {} ({})", entry.change, entry.at_utc);
+
+        let result = token.authorize(entry.change.operation()).and_then(|()| match &entry.change {
+            UndoChange::ChargeMode { before, after } => battery::change_charge_mode(backend, Some(self), &token, Some(*after), |_| *before),
+            UndoChange::PowerProfile { before, after } => power::change_power_profile(backend, Some(self), &token, Some(*after), *before),
+            UndoChange::Toggle { toggle, before, after } => toggles::change_toggle(backend, Some(self), &token, *toggle, Some(*after), *before),
+            UndoChange::KeyboardLighting { before, after } => lighting::change_lighting(backend, Some(self), &token, Some(after), before),
+        });
+        if matches!(result, Ok(_) | Err(WriteError::Conflict(_))) {
+            self.remove(&entry);
+        }
+        Some((entry, result))
+    }
+}
+
+/// Adds `change` to `history` if the write was Verified, actually changed the control,
+/// and was not itself an undo.
+pub fn record(history: Option<&UndoHistory>, token: &WriteToken, result: &Result<WriteOutcome, WriteError>, change: Option<UndoChange>) {
+    if let (Some(history), Ok(WriteOutcome::Verified), Some(change)) = (history, result, change) {
+        if !token.is_restore() && !change.is_noop() {
+            history.push(change);
+        }
+    }
+}
+
+/// History file: `LEGION_UNDO_FILE`, or `undo-history.json` in the config directory.
+pub fn path() -> Option<PathBuf> {
+    match env::var_os(UNDO_ENV) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => paths::config_dir().map(|dir| dir.join("undo-history.json")),
+    }
+}
+
+/// Opens the process-wide history for `backend`. Until this is called (as in unit
+/// tests) writes are not recorded.
+pub fn init(backend: &dyn HardwareBackend) -> Option<&'static UndoHistory> {
+    let path = path()?;
+    Some(ACTIVE.get_or_init(|| UndoHistory::at(path, backend.name())))
+}
+
+pub fn active() -> Option<&'static UndoHistory> {
+    ACTIVE.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legion_core::backend::simulated::SimulatedBackend;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    fn set_profile(sim: &SimulatedBackend, history: &UndoHistory, profile: PowerProfile) -> Result<WriteOutcome, WriteError> {
+        let token = WriteToken::grant(power::POWER_PROFILE_OPERATION, format!("{:?}", profile), "test");
+        power::change_power_profile(sim, Some(history), &token, None, profile)
+    }
+
+    fn history() -> UndoHistory {
+        lock::tests::use_test_lock_file();
+        let path = env::temp_dir().join(format!("legion-undo-{}-{}.json", std::process::id(), NEXT.fetch_add(1, Ordering::SeqCst)));
+        let _ = fs::remove_file(&path);
+        UndoHistory::at(path, "simulated")
+    }
+
+    fn profile(before: PowerProfile, after: PowerProfile) -> UndoChange {
+        UndoChange::PowerProfile { before, after }
+    }
+
+    #[test]
+    fn history_is_bounded_and_survives_reopening() {
+        let history = history();
+        for _ in 0..HISTORY_LIMIT + 3 {
+            history.push(profile(PowerProfile::Quiet, PowerProfile::Balanced));
+        }
+        history.push(UndoChange::Toggle { toggle: Toggle::FnLock, before: false, after: true });
+
+        let reopened = UndoHistory::at(history.path(), "simulated");
+        let entries = reopened.entries();
+        assert_eq!(entries.len(), HISTORY_LIMIT);
+        assert_eq!(reopened.last().unwrap().change, UndoChange::Toggle { toggle: Toggle::FnLock, before: false, after: true });
+        let _ = fs::remove_file(history.path());
+    }
+
+    #[test]
+    fn concurrent_writers_keep_every_change() {
+        let path = history().path().to_path_buf();
+        let writers: Vec<_> = (0..4).map(|_| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let history = UndoHistory::at(path, "simulated");
+                for _ in 0..3 {
+                    history.push(profile(PowerProfile::Quiet, PowerProfile::Balanced));
+                }
+            })
+        }).collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(UndoHistory::at(&path, "simulated").entries().len(), 12);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn changes_on_another_backend_are_not_undone_here() {
+        let history = history();
+        history.push(profile(PowerProfile::Quiet, PowerProfile::Balanced));
+        assert!(UndoHistory::at(history.path(), "windows").last().is_none());
+        let _ = fs::remove_file(history.path());
+    }
+
+    #[test]
+    fn undo_writes_the_replaced_value_and_drops_the_entry() {
+        let history = history();
+        let sim = SimulatedBackend::new();
+        assert_eq!(sim.power_profile().unwrap(), PowerProfile::Balanced);
+        assert_eq!(set_profile(&sim, &history, PowerProfile::Performance), Ok(WriteOutcome::Verified));
+        assert_eq!(history.entries().len(), 1);
+
+        let (entry, result) = history.undo_last(&sim, "test").unwrap();
+        assert_eq!(entry.change, profile(PowerProfile::Balanced, PowerProfile::Performance));
+        assert_eq!(result, Ok(WriteOutcome::Verified));
+        assert_eq!(sim.power_profile().unwrap(), PowerProfile::Balanced);
+        assert!(history.entries().is_empty());
+        assert!(history.undo_last(&sim, "test").is_none());
+        let _ = fs::remove_file(history.path());
+    }
+
+    #[test]
+    fn restoring_write_is_not_recorded_again() {
+        let history = history();
+        let sim = SimulatedBackend::new();
+        let token = WriteToken::grant(Toggle::FnLock.label(), "ON", "test");
+        toggles::change_toggle(&sim, Some(&history), &token, Toggle::FnLock, Some(false), true).unwrap();
+        set_profile(&sim, &history, PowerProfile::Quiet).unwrap();
+
+        history.undo_last(&sim, "test").unwrap().1.unwrap();
+        let entries = history.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].change, UndoChange::Toggle { toggle: Toggle::FnLock, before: false, after: true });
+
+        history.undo_last(&sim, "test").unwrap().1.unwrap();
+        assert!(!sim.toggle(Toggle::FnLock).unwrap());
+        assert!(history.entries().is_empty());
+        let _ = fs::remove_file(history.path());
+    }
+
+    #[test]
+    fn control_changed_since_is_a_conflict_and_drops_the_entry() {
+        let history = history();
+        let sim = SimulatedBackend::new();
+        set_profile(&sim, &history, PowerProfile::Performance).unwrap();
+        // Changed outside the app after the recorded write
+        sim.set_power_profile(PowerProfile::Quiet).unwrap();
+
+        let (_, result) = history.undo_last(&sim, "test").unwrap();
+        assert!(matches!(result, Err(WriteError::Conflict(_))), "{:?}", result);
+        assert_eq!(sim.power_profile().unwrap(), PowerProfile::Quiet);
+        assert!(history.entries().is_empty());
+        let _ = fs::remove_file(history.path());
+    }
+
+    #[test]
+    fn entries_serialize_with_the_control_name() {
+        let entry = UndoEntry { at_utc: "t".into(), backend: "simulated".into(), change: profile(PowerProfile::Quiet, PowerProfile::Performance) };
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!((json["control"].as_str(), json["before"].as_str()), (Some("powerProfile"), Some("quiet")));
+        assert_eq!(serde_json::from_value::<UndoEntry>(json).unwrap(), entry);
+    }
+}
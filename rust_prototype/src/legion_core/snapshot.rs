@@ -0,0 +1,232 @@
+use crate::legion_core::backend::{self, HardwareBackend};
+use crate::legion_core::device::database::{self, ModelDatabase};
+use crate::legion_core::device::detect::model_from_identity;
+use crate::legion_core::device::models::LaptopModel;
+use crate::legion_core::hw::battery::{BatteryStatus, ChargeMode};
+use crate::legion_core::hw::gpu::IgpuMode;
+use crate::legion_core::hw::lighting::{self, KeyboardLighting};
+use crate::legion_core::hw::power::PowerProfile;
+use crate::legion_core::hw::thermal::TemperatureReading;
+use crate::legion_core::hw::toggles::Toggle;
+use crate::legion_core::result::{HardwareReadResult, ReadStatus};
+use crate::legion_core::time;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::{json, Value};
+use std::time::SystemTime;
+
+/// Schema of the serialised snapshot; matches `hardware-evidence/*/*-state-*.json`.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Everything the status views show, read in one pass. Each field keeps its own
+/// outcome, so one failing read never hides the others.
+#[derive(Debug, Clone)]
+pub struct HardwareStateSnapshot {
+    pub captured_at: SystemTime,
+    /// `HardwareBackend::name` of the backend that was read.
+    pub backend: &'static str,
+    pub device: HardwareReadResult<LaptopModel>,
+    pub battery: HardwareReadResult<BatteryStatus>,
+    pub conservation_mode: HardwareReadResult<bool>,
+    pub rapid_charge: HardwareReadResult<bool>,
+    /// Unified mode from `HardwareBackend::charge_mode` (one EnergyDrv read on Windows).
+    pub charge_mode: HardwareReadResult<ChargeMode>,
+    pub thermal_mode: HardwareReadResult<PowerProfile>,
+    pub thermal_mode_choices: HardwareReadResult<Vec<PowerProfile>>,
+    pub overdrive: HardwareReadResult<bool>,
+    pub igpu_mode: HardwareReadResult<IgpuMode>,
+    /// Keyboard lighting decoded from the controller's feature report.
+    pub lighting: HardwareReadResult<KeyboardLighting>,
+    pub toggles: Vec<(Toggle, HardwareReadResult<bool>)>,
+    pub temperatures: HardwareReadResult<Vec<TemperatureReading>>,
+}
+
+impl HardwareStateSnapshot {
+    /// Reads the active backend, resolving the machine against the active model database.
+    pub fn capture() -> Self {
+        Self::capture_from(backend::active(), database::active())
+    }
+
+    pub fn capture_from(backend: &dyn HardwareBackend, db: &ModelDatabase) -> Self {
+        let captured_at = SystemTime::now();
+        let device = HardwareReadResult::logged("device identity",
+            backend.identity().map(|identity| model_from_identity(identity, db)));
+        Self {
+            captured_at,
+            backend: backend.name(),
+            device,
+            battery: HardwareReadResult::logged("battery", backend.battery_status()),
+            conservation_mode: HardwareReadResult::logged("conservation mode", backend.conservation_mode()),
+            rapid_charge: HardwareReadResult::logged("rapid charge", backend.rapid_charge()),
+            charge_mode: HardwareReadResult::logged("charge mode", backend.charge_mode()),
+            thermal_mode: HardwareReadResult::logged("power profile", backend.power_profile()),
+            thermal_mode_choices: HardwareReadResult::logged("power profile choices", backend.power_profile_choices()),
+            overdrive: HardwareReadResult::logged("display overdrive", backend.overdrive()),
+            igpu_mode: HardwareReadResult::logged("iGPU mode", backend.igpu_mode()),
+            lighting: HardwareReadResult::logged("keyboard lighting", lighting::read_lighting(backend)),
+            toggles: Toggle::ALL.iter()
+                .map(|&toggle| (toggle, HardwareReadResult::logged(toggle.label(), backend.toggle(toggle))))
+                .collect(),
+            temperatures: HardwareReadResult::logged("temperatures", backend.temperatures()),
+        }
+    }
+
+    /// One entry per field, shaped like the `reads` array of the hardware evidence files.
+    pub fn reads(&self) -> Vec<StateRead> {
+        let enabled = |on: &bool| json!(if *on { "Enabled" } else { "Disabled" });
+        let debug_name = |v: &dyn std::fmt::Debug| json!(format!("{:?}", v));
+
+        let mut reads = vec![
+            StateRead::new("Battery", &self.battery, |b| serde_json::to_value(b).unwrap_or(Value::Null)),
+            StateRead::new("BatteryChargeMode", &self.charge_mode, |m| debug_name(m)),
+            StateRead::new("BatteryConservationMode", &self.conservation_mode, enabled),
+            StateRead::new("BatteryRapidCharge", &self.rapid_charge, enabled),
+            StateRead::new("ThermalMode", &self.thermal_mode, |p| debug_name(p)),
+            StateRead::new("ThermalModeChoices", &self.thermal_mode_choices,
+                |choices| Value::Array(choices.iter().map(|p| debug_name(p)).collect())),
+            StateRead::new("DisplayOverdrive", &self.overdrive, enabled),
+            StateRead::new("IntegratedGpuMode", &self.igpu_mode, |m| debug_name(m)),
+            StateRead::new("KeyboardLighting", &self.lighting, |l| serde_json::to_value(l).unwrap_or(Value::Null)),
+        ];
+        reads.extend(self.toggles.iter().map(|(toggle, result)| StateRead::new(&format!("{:?}", toggle), result, enabled)));
+        reads.push(StateRead::new("Temperatures", &self.temperatures, |t| serde_json::to_value(t).unwrap_or(Value::Null)));
+        reads
+    }
+}
+
+/// One field of a snapshot. Failures carry `evidenceCode` (the `HardwareError::code`) and detail.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateRead {
+    pub capability: String,
+    pub status: ReadStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evidence_code: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl StateRead {
+    fn new<T>(capability: &str, result: &HardwareReadResult<T>, value: impl FnOnce(&T) -> Value) -> Self {
+        let (value, error) = match result {
+            HardwareReadResult::Success(v) => (Some(value(v)), None),
+            HardwareReadResult::Failure(e) => (None, Some(e)),
+        };
+        Self {
+            capability: capability.to_string(),
+            status: result.status(),
+            value,
+            evidence_code: error.map(|e| e.code()),
+            detail: error.map(|e| e.detail().to_string()),
+        }
+    }
+}
+
+/// Serialises in the shape of `hardware-evidence/83DV/NECN50WW-state-*.json`, plus the
+/// parsed `device` for scripts that used the old `--json` output.
+impl Serialize for HardwareStateSnapshot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("schemaVersion", &SCHEMA_VERSION)?;
+        map.serialize_entry("capturedAtUtc", &time::format_utc(self.captured_at))?;
+        match &self.device {
+            HardwareReadResult::Success(device) => map.serialize_entry("machine", &json!({
+                "manufacturer": device.manufacturer,
+                "machineType": device.machine_type,
+                "model": device.marketing_model,
+                "biosVersion": device.bios_version,
+            }))?,
+            HardwareReadResult::Failure(e) => map.serialize_entry("machine", e)?,
+        }
+        map.serialize_entry("execution", &json!({ "backend": self.backend, "writesAttempted": false }))?;
+        map.serialize_entry("reads", &self.reads())?;
+        if let HardwareReadResult::Success(device) = &self.device {
+            map.serialize_entry("device", device)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legion_core::backend::simulated::{Fault, SimulatedBackend};
+
+    const ELEVATED: &str = include_str!("../../../hardware-evidence/83DV/NECN50WW-state-elevated.json");
+    const UNELEVATED: &str = include_str!("../../../hardware-evidence/83DV/NECN50WW-state-unelevated.json");
+
+    fn capture(backend: &SimulatedBackend) -> Value {
+        serde_json::to_value(HardwareStateSnapshot::capture_from(backend, &ModelDatabase::embedded())).unwrap()
+    }
+
+    /// Every read in the evidence file appears in `snapshot` with the same status and value.
+    fn assert_matches_evidence(snapshot: &Value, evidence: &str) {
+        let evidence: Value = serde_json::from_str(evidence).unwrap();
+        assert_eq!(snapshot["schemaVersion"], evidence["schemaVersion"]);
+        for expected in evidence["reads"].as_array().unwrap() {
+            let actual = snapshot["reads"].as_array().unwrap().iter()
+                .find(|r| r["capability"] == expected["capability"])
+                .unwrap_or_else(|| panic!("no read for {}", expected["capability"]));
+            assert_eq!(actual["status"], expected["status"], "{}", expected["capability"]);
+            assert_eq!(actual["value"], expected["value"], "{}", expected["capability"]);
+        }
+    }
+
+    #[test]
+    fn matches_elevated_evidence() {
+        let backend = SimulatedBackend::new();
+        backend.apply_state_spec("thermal_mode=3").unwrap();
+        let snapshot = capture(&backend);
+
+        assert_matches_evidence(&snapshot, ELEVATED);
+        let evidence: Value = serde_json::from_str(ELEVATED).unwrap();
+        assert_eq!(snapshot["machine"], evidence["machine"]);
+        assert!(snapshot["capturedAtUtc"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn matches_unelevated_evidence() {
+        let backend = SimulatedBackend::new();
+        backend.apply_fault_spec("charge_mode=unavailable,power_profile=access_denied,overdrive=access_denied,igpu_mode=access_denied").unwrap();
+        let snapshot = capture(&backend);
+
+        assert_matches_evidence(&snapshot, UNELEVATED);
+        let thermal = snapshot["reads"].as_array().unwrap().iter().find(|r| r["capability"] == "ThermalMode").unwrap();
+        assert_eq!(thermal["evidenceCode"], "access_denied");
+        assert!(thermal.get("value").is_none());
+    }
+
+    #[test]
+    fn one_failed_read_keeps_the_others() {
+        let backend = SimulatedBackend::new();
+        backend.inject("identity", Fault::Timeout).unwrap();
+        backend.inject("temperatures", Fault::InvalidData).unwrap();
+        let snapshot = HardwareStateSnapshot::capture_from(&backend, &ModelDatabase::embedded());
+
+        assert_eq!(snapshot.device.status(), ReadStatus::TimedOut);
+        assert_eq!(snapshot.temperatures.status(), ReadStatus::InvalidData);
+        assert_eq!(snapshot.battery.status(), ReadStatus::Success);
+        assert!(snapshot.toggles.iter().all(|(_, result)| result.status() == ReadStatus::Success));
+        assert_eq!(serde_json::to_value(&snapshot).unwrap()["machine"]["errorCode"], "timed_out");
+    }
+
+    #[test]
+    fn charge_mode_is_decoded_from_energy_bits() {
+        let backend = SimulatedBackend::new();
+        backend.apply_state_spec("charge_mode=rapid").unwrap();
+        let snapshot = HardwareStateSnapshot::capture_from(&backend, &ModelDatabase::embedded());
+        assert_eq!(snapshot.charge_mode, HardwareReadResult::Success(ChargeMode::Rapid));
+
+        backend.apply_state_spec("charge_mode_bits=0x24").unwrap();
+        let snapshot = HardwareStateSnapshot::capture_from(&backend, &ModelDatabase::embedded());
+        assert_eq!(snapshot.charge_mode.status(), ReadStatus::InvalidData);
+
+        // A failed unified read leaves the separate flags readable.
+        backend.apply_state_spec("charge_mode=conservation").unwrap();
+        backend.inject("charge_mode", Fault::AccessDenied).unwrap();
+        let snapshot = HardwareStateSnapshot::capture_from(&backend, &ModelDatabase::embedded());
+        assert_eq!(snapshot.charge_mode.status(), ReadStatus::AccessDenied);
+        assert_eq!(snapshot.conservation_mode, HardwareReadResult::Success(true));
+    }
+}
@@ -1,5 +1,15 @@
 use log::{info, error};
 use std::env;
+use legion_core::capability::{Capability, CapabilityReport, CapabilityStatus};
+use legion_core::device::database::ModelCapabilities;
+use legion_core::device::database::KeyboardBacklight;
+use legion_core::hw::battery::ChargeMode;
+use legion_core::hw::lighting::{Brightness, KeyboardLighting, LightingEffect, Rgb, WaveDirection};
+use legion_core::hw::toggles::Toggle;
+use legion_core::result::{HardwareReadResult, WriteError, WriteOutcome};
+use legion_core::safety::guards::{AuditEntry, WriteToken};
+use legion_core::safety::journal::{self, JournalEntry};
+use legion_core::safety::undo;
 
 mod legion_core;
 mod platform;
@@ -9,23 +19,229 @@ fn print_help() {
     println!("Legion + LOQ Control v0.2.0");
     println!("A lightweight Lenovo Vantage replacement for Legion & LOQ laptops.\n");
     println!("USAGE:");
-    println!("  legion-loq-control [OPTIONS]\n");
+    println!("  legion-loq-control [OPTIONS]");
+    println!("  legion-loq-control journal [list|clear]   Show or clear the write journal\n");
     println!("OPTIONS:");
     println!("  --gui                       Launch graphical interface");
-    println!("  --json                      Output device info as JSON");
+    println!("  --json                      Output a timestamped hardware state snapshot as JSON");
     println!("  --dry-run                   Preview changes without applying");
+    println!("  --sensors                   List every temperature sensor");
+    println!("  --capabilities              Show per-control capability evidence (with --json: as JSON)");
+    println!("  --simulate                  Use the in-memory virtual LOQ 83DV backend");
     println!("  --set-conservation-mode <on|off>  Toggle battery conservation");
     println!("  --rapid-charge <on|off>     Toggle rapid charging");
+    println!("  --charge-mode <mode>        Set battery charge mode: normal, conservation, rapid");
     println!("  --set-profile <quiet|balanced|perf>  Set thermal profile");
+    println!("                              (also balanced-performance, custom where offered)");
+    println!("  --fn-lock <on|off>          Toggle Fn lock");
+    println!("  --usb-charging <on|off>     Toggle Always On USB charging");
+    println!("  --camera-power <on|off>     Toggle webcam power");
+    println!("  --effect <static|breath|wave|smooth>  4-zone keyboard effect");
+    println!("  --zone <1-4|all>=<#rrggbb>  Zone color (repeatable), e.g. --zone 2=#ff8800");
+    println!("  --speed <1-4>               Effect speed");
+    println!("  --brightness <off|low|high> Keyboard brightness");
+    println!("  --direction <ltr|rtl>       Wave direction");
+    println!("                              (lighting flags edit the current state, sent as one report;");
+    println!("                              if it cannot be read, unset fields use the default)");
+    println!("  --undo                      Restore the value the last change replaced (verified, like any write)");
+    println!("  --audit                     With a write: also print the write-token audit log");
     println!("  -V, --version               Show version");
     println!("  -h, --help                  Show this help\n");
     println!("EXAMPLES:");
     println!("  legion-loq-control --gui");
     println!("  legion-loq-control --set-profile perf");
-    println!("  legion-loq-control --dry-run --set-conservation-mode on\n");
+    println!("  legion-loq-control --dry-run --set-conservation-mode on");
+    println!("  legion-loq-control --zone 2=#ff8800 --effect wave --direction rtl\n");
+    println!("ENVIRONMENT:");
+    println!("  LEGION_BACKEND=simulated    Same as --simulate");
+    println!("  LEGION_SIM_STATE=<k=v,...>  Initial simulated state (battery, charge_mode, thermal_mode, fn_lock, ...)");
+    println!("  LEGION_SIM_FAULTS=<op=f,..> Inject faults (access_denied, timeout, invalid_data, unavailable, unsupported,");
+    println!("                              failed), e.g. set_power_profile=timeout");
+    println!("  LEGION_SYSFS_ROOT=<dir>     Linux: read sysfs from <dir> instead of /sys");
+    println!("  LEGION_MODEL_DB=<file>      Model database override (default: <config dir>/models.json)");
+    println!("  LEGION_JOURNAL=<file>       Write journal (default: <config dir>/write-journal.jsonl)");
+    println!("  LEGION_UNDO_FILE=<file>     Undo history (default: <config dir>/undo-history.json)");
+    println!("  LEGION_LOCK_FILE=<file>     Machine-wide write lock (default: /run/lock or %ProgramData%)\n");
+    println!("EXIT CODES:");
+    println!("  0 success   1 failed   2 rejected   3 unsupported   4 access denied");
+    println!("  5 unavailable   6 invalid data   7 timed out   8 unauthorized   9 conflict");
+    println!("  10 written but not verified by readback   11 busy (another instance is writing)");
+    println!("  12 partially applied (a multi-step write failed midway)\n");
     println!("NOTE: Run as Administrator for all features to work.");
 }
 
+/// Refuses a write the model database or the capability probe does not allow for this
+/// machine. Dry runs only warn; Degraded capabilities are allowed with a warning.
+fn require_offered(what: &str, capability: Option<Capability>, offered: impl FnOnce(&ModelCapabilities) -> bool, dry_run: bool) {
+    let device = legion_core::device::detect::detect_device().ok();
+    let reason = match device.as_ref().and_then(|d| Some((d, d.capabilities.as_ref()?))) {
+        None => "this machine/BIOS is not in the model database".to_string(),
+        Some((_, caps)) if !offered(caps) => format!("the model database does not list {} for this machine", what),
+        Some((device, _)) => {
+            let report = legion_core::capability::probe::probe(device);
+            match capability.and_then(|c| report.evidence(c)) {
+                Some(e) if e.status == CapabilityStatus::Degraded => {
+                    println!("WARNING: {} is degraded ({}): {}", e.capability, e.evidence_code, e.detail.as_deref().unwrap_or("no detail"));
+                    return;
+                }
+                Some(e) if e.status != CapabilityStatus::Supported => format!("{} is {} ({})", e.capability, e.status, e.evidence_code),
+                _ => return,
+            }
+        }
+    };
+
+    if dry_run {
+        println!("WARNING: {}. The write would be refused.", reason);
+        return;
+    }
+    eprintln!("Error: Refusing to set {}: {}.", what, reason);
+    if let Some(path) = legion_core::device::database::override_path() {
+        eprintln!("To enable it, describe this machine in {}.", path.display());
+    }
+    std::process::exit(WriteError::Rejected(reason).exit_code());
+}
+
+/// Applies one lighting flag (`--effect`, `--zone`, ...) to `lighting`; `None` if `val` is invalid.
+fn edit_lighting(lighting: &mut KeyboardLighting, flag: &str, val: &str) -> Option<()> {
+    match flag {
+        "--effect" => LightingEffect::parse(val).map(|e| lighting.effect = e),
+        "--speed" => val.parse().ok().filter(|s| KeyboardLighting::SPEEDS.contains(s)).map(|s| lighting.speed = s),
+        "--brightness" => Brightness::parse(val).map(|b| lighting.brightness = b),
+        "--direction" => WaveDirection::parse(val).map(|d| lighting.direction = d),
+        _ => val.split_once('=').and_then(|(zone, color)| {
+            let color = Rgb::parse(color)?;
+            match zone {
+                "all" => lighting.zones = [color; 4],
+                _ => *lighting.zones.get_mut(zone.parse::<usize>().ok()?.checked_sub(1)?)? = color,
+            }
+            Some(())
+        }),
+    }
+}
+
+/// Dry run: shows the current value of a control next to the requested one.
+fn print_dry_run<T: PartialEq>(label: &str, current: HardwareReadResult<T>, target: &T, show: impl Fn(&T) -> String) {
+    match current {
+        HardwareReadResult::Success(current) => {
+            println!("Current {}: {}", label, show(&current));
+            if current == *target {
+                println!("Result: No change needed (values match).");
+            } else {
+                println!("Result: State would change.");
+            }
+        }
+        HardwareReadResult::Failure(e) => {
+            println!("Current {}: Unknown [{}]", label, e.code());
+            println!("WARNING: Unable to verify current state ({}). Write might be unsafe.", e);
+        }
+    }
+}
+
+/// Grants a write token for one CLI write and revokes it once the setter returns.
+fn with_token<T>(operation: &str, target: &str, flag: &str, write: impl FnOnce(&WriteToken) -> T) -> T {
+    let token = WriteToken::grant(operation, target, &format!("CLI {}", flag));
+    write(&token)
+}
+
+fn print_audit(entries: &[AuditEntry]) {
+    println!("Write-token audit log:");
+    for e in entries {
+        println!("  #{} {} {} -> {} ({}){}{}", e.id, legion_core::time::format_utc(e.granted_at), e.operation, e.target, e.reason,
+            if e.used { ", used" } else { ", unused" },
+            if e.revoked_at.is_some() { ", revoked" } else { "" });
+    }
+}
+
+/// Prints the outcome of a write (as JSON with `--json`) and exits with the
+/// error's exit code on failure, or 10 if the write could not be read back.
+/// With `audit`, the write-token audit log is printed too (JSON: an `audit` array).
+fn report_write(what: &str, value: &str, result: Result<WriteOutcome, WriteError>, json_mode: bool, audit: bool) {
+    let audit_json = |json: &mut serde_json::Value| {
+        if let (true, Some(json)) = (audit, json.as_object_mut()) {
+            json.insert("audit".to_string(), serde_json::to_value(legion_core::safety::guards::audit_log()).unwrap_or_default());
+        }
+    };
+    match result {
+        Ok(outcome) => {
+            if json_mode {
+                let mut json = serde_json::json!({ "operation": what, "value": value });
+                if let (Some(json), serde_json::Value::Object(status)) = (json.as_object_mut(), serde_json::to_value(&outcome).unwrap_or_default()) {
+                    json.extend(status);
+                }
+                audit_json(&mut json);
+                println!("{}", json);
+            } else {
+                match &outcome {
+                    WriteOutcome::Verified => println!("Verified: {} set to {} (read back).", what, value),
+                    WriteOutcome::Unverified(e) => println!("Unverified: {} written as {}, but it could not be read back [{}]: {}", what, value, e.code(), e),
+                }
+                if audit {
+                    print_audit(&legion_core::safety::guards::audit_log());
+                }
+            }
+            info!("{} update: {}", what, outcome);
+            if outcome.exit_code() != 0 {
+                std::process::exit(outcome.exit_code());
+            }
+        }
+        Err(e) => {
+            error!("Operation failed: {}", e);
+            if json_mode {
+                let mut json = serde_json::json!({
+                    "operation": what,
+                    "value": value,
+                    "status": e.status(),
+                    "errorCode": e.code(),
+                    "detail": e.to_string(),
+                });
+                audit_json(&mut json);
+                println!("{}", json);
+            } else {
+                eprintln!("Error: Failed to set {} [{}]: {}", what, e.code(), e);
+                if audit {
+                    print_audit(&legion_core::safety::guards::audit_log());
+                }
+            }
+            std::process::exit(e.exit_code());
+        }
+    }
+}
+
+/// One line of the status listing; failed reads show their error code instead of a value.
+fn print_read<T>(label: &str, result: &HardwareReadResult<T>, show: impl FnOnce(&T) -> String) {
+    let label = format!("{}:", label);
+    match result {
+        HardwareReadResult::Success(value) => println!("{:<19}{}", label, show(value)),
+        HardwareReadResult::Failure(e) => println!("{:<19}Unknown [{}]", label, e.code()),
+    }
+}
+
+fn on_off(enabled: &bool) -> String {
+    if *enabled { "ON" } else { "OFF" }.to_string()
+}
+
+/// One journal entry: the intent, then how it ended (or that it never did).
+fn print_journal_entry(entry: &JournalEntry) {
+    let intent = &entry.intent;
+    let ended = match (&entry.result, &entry.reconciled) {
+        (Some(result), _) => match &result.error_code {
+            Some(code) => format!("{} [{}]", result.status, code),
+            None => result.status.clone(),
+        },
+        (None, Some(reconciled)) => format!("INTERRUPTED (found {}, value then: {})", reconciled.at_utc, reconciled.current),
+        (None, None) => "INTERRUPTED".to_string(),
+    };
+    println!("{} {} -> {} ({}, pid {}, {}); before: {}; {}", intent.at_utc, intent.operation, intent.target,
+        intent.reason, intent.pid, intent.backend, intent.before.as_deref().unwrap_or("unknown"), ended);
+}
+
+fn print_capabilities(report: &CapabilityReport) {
+    for e in &report.observations {
+        println!("{:<21}{:<12} {}{}", format!("{}:", e.capability), e.status.to_string(), e.evidence_code,
+            e.detail.as_ref().map(|d| format!(" - {}", d)).unwrap_or_default());
+    }
+}
+
 fn main() {
     env_logger::init();
     
@@ -44,6 +260,62 @@ fn main() {
         return;
     }
     
+    // Backend selection must happen before any hardware access
+    if let Err(e) = legion_core::backend::init(args.contains(&"--simulate".to_string())) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+
+    // Report writes an earlier run left unfinished; they are never replayed
+    let journal = journal::init(legion_core::backend::active());
+    undo::init(legion_core::backend::active());
+    if let Some(journal) = journal {
+        for entry in journal.reconcile(legion_core::backend::active(), journal::process_is_running) {
+            let intent = &entry.intent;
+            eprintln!("WARNING: An earlier write did not finish: {} -> {} ({}, pid {}, {}). Before: {}; now: {}. It was not repeated.",
+                intent.operation, intent.target, intent.reason, intent.pid, intent.at_utc,
+                intent.before.as_deref().unwrap_or("unknown"), entry.reconciled.as_ref().map_or("unknown", |r| r.current.as_str()));
+        }
+    }
+
+    // A subcommand only as the first argument, never a flag value that happens to be "journal"
+    if args.get(1).is_some_and(|a| a == "journal") {
+        let Some(journal) = journal else {
+            eprintln!("Error: No journal location (set {} or HOME/APPDATA).", journal::JOURNAL_ENV);
+            std::process::exit(1);
+        };
+        match args[2..].iter().map(String::as_str).find(|a| !a.starts_with('-')) {
+            Some("clear") => match journal.clear() {
+                Ok(()) => println!("Cleared {}.", journal.path().display()),
+                Err(e) => {
+                    eprintln!("Error: Could not clear {}: {}", journal.path().display(), e);
+                    std::process::exit(1);
+                }
+            },
+            None | Some("list") => match journal.entries() {
+                Ok(entries) if args.contains(&"--json".to_string()) => {
+                    println!("{}", serde_json::to_string_pretty(&entries).unwrap_or_default());
+                }
+                Ok(entries) => {
+                    println!("Write journal: {}", journal.path().display());
+                    if entries.is_empty() {
+                        println!("No entries.");
+                    }
+                    entries.iter().for_each(print_journal_entry);
+                }
+                Err(e) => {
+                    eprintln!("Error: Could not read {}: {}", journal.path().display(), e);
+                    std::process::exit(1);
+                }
+            },
+            Some(other) => {
+                eprintln!("Error: Unknown journal command '{}'. Use 'list' or 'clear'.", other);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     // GUI mode check (early exit)
     if args.contains(&"--gui".to_string()) {
         if let Err(e) = gui::run_gui() {
@@ -54,14 +326,70 @@ fn main() {
     }
     
     // CLI mode
+    if args.contains(&"--sensors".to_string()) {
+        let temps = match legion_core::hw::thermal::get_temperatures() {
+            HardwareReadResult::Success(temps) => temps,
+            HardwareReadResult::Failure(e) => {
+                eprintln!("Error: Failed to read temperature sensors [{}]: {}", e.code(), e);
+                std::process::exit(e.status().exit_code());
+            }
+        };
+        if temps.is_empty() {
+            println!("No temperature sensors found.");
+        }
+        for t in temps {
+            println!("{:<12} {:>6.1}°C  {} / {}", t.kind.to_string(), t.celsius, t.source, t.label);
+        }
+        return;
+    }
+
     let dry_run = args.contains(&"--dry-run".to_string());
     let json_mode = args.len() > 1 && args.contains(&"--json".to_string());
+    let audit = args.contains(&"--audit".to_string());
+
+    if args.contains(&"--capabilities".to_string()) {
+        match legion_core::device::detect::detect_device() {
+            Ok(device) => {
+                let report = legion_core::capability::probe::probe(&device);
+                if json_mode {
+                    println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+                } else {
+                    print_capabilities(&report);
+                }
+            }
+            Err(e) => {
+                if json_mode {
+                    println!("{}", serde_json::to_string_pretty(&e).unwrap_or_default());
+                } else {
+                    eprintln!("Error: Device detection failed [{}]: {}", e.code(), e);
+                }
+                std::process::exit(e.status().exit_code());
+            }
+        }
+        return;
+    }
     
+    let mut set_charge_mode_arg: Option<ChargeMode> = None;
     let mut set_conservation_mode_arg: Option<bool> = None;
     let mut set_rapid_charge_arg: Option<bool> = None;
     let mut set_profile_arg: Option<String> = None;
+    let mut set_toggle_args: Vec<(Toggle, bool)> = Vec::new();
+    // Applied over the current lighting state once the backend is chosen
+    let mut lighting_edits: Vec<(&str, &str)> = Vec::new();
 
     for i in 0..args.len() {
+        if args[i] == "--charge-mode" && i + 1 < args.len() {
+            set_charge_mode_arg = match args[i+1].to_lowercase().as_str() {
+                "normal" => Some(ChargeMode::Normal),
+                "conservation" => Some(ChargeMode::Conservation),
+                "rapid" => Some(ChargeMode::Rapid),
+                _ => {
+                    eprintln!("Invalid value for --charge-mode. Use 'normal', 'conservation' or 'rapid'.");
+                    std::process::exit(1);
+                }
+            };
+        }
+
         if args[i] == "--set-conservation-mode" && i + 1 < args.len() {
             let val = args[i+1].to_lowercase();
             if val == "on" || val == "enable" || val == "true" {
@@ -89,6 +417,30 @@ fn main() {
         if args[i] == "--set-profile" && i + 1 < args.len() {
             set_profile_arg = Some(args[i+1].to_lowercase());
         }
+
+        let lighting_flag = ["--effect", "--zone", "--speed", "--brightness", "--direction"].contains(&args[i].as_str());
+        if lighting_flag && i + 1 < args.len() {
+            let val = args[i+1].as_str();
+            if edit_lighting(&mut KeyboardLighting::default(), &args[i], val).is_none() {
+                eprintln!("Invalid value '{}' for {}. See --help.", val, args[i]);
+                std::process::exit(1);
+            }
+            lighting_edits.push((&args[i], val));
+        }
+
+        for toggle in Toggle::ALL {
+            if args[i] == toggle.cli_flag() && i + 1 < args.len() {
+                let val = args[i+1].to_lowercase();
+                if val == "on" || val == "enable" || val == "true" {
+                    set_toggle_args.push((toggle, true));
+                } else if val == "off" || val == "disable" || val == "false" {
+                    set_toggle_args.push((toggle, false));
+                } else {
+                    eprintln!("Invalid value for {}. Use 'on' or 'off'.", toggle.cli_flag());
+                    std::process::exit(1);
+                }
+            }
+        }
     }
 
     if !json_mode {
@@ -98,81 +450,99 @@ fn main() {
         }
     }
 
+    // Undo the last change (history persists across runs)
+    if args.contains(&"--undo".to_string()) {
+        let Some(history) = undo::init(legion_core::backend::active()) else {
+            eprintln!("Error: No undo history location (set {} or HOME/APPDATA).", undo::UNDO_ENV);
+            std::process::exit(1);
+        };
+        if dry_run {
+            println!("--- Dry Run Mode ---");
+            match history.last() {
+                Some(entry) => println!("Action: Undo {} (changed {}), restoring {}", entry.change, entry.at_utc, entry.change.restored_value()),
+                None => println!("Nothing to undo."),
+            }
+            println!("History: {}", history.path().display());
+            return;
+        }
+        match history.undo_last(legion_core::backend::active(), "CLI --undo") {
+            Some((entry, result)) => {
+                if let Err(WriteError::Conflict(_)) = &result {
+                    eprintln!("Note: {} changed since {}; that change was dropped from the undo history.", entry.change.operation(), entry.at_utc);
+                }
+                report_write(entry.change.operation(), &entry.change.restored_value(), result, json_mode, audit);
+            }
+            None => println!("Nothing to undo."),
+        }
+        return;
+    }
+
     // Handle Write Operations (if requested and we are running)
+    if let Some(target_mode) = set_charge_mode_arg {
+        info!("Command: Set Charge Mode to {:?}", target_mode);
+        let capability = match target_mode {
+            ChargeMode::Normal => None,
+            ChargeMode::Conservation => Some(Capability::BatteryConservationMode),
+            ChargeMode::Rapid => Some(Capability::BatteryRapidCharge),
+        };
+        require_offered(&format!("{} charge mode", target_mode), capability, |caps| caps.offers_charge_mode(target_mode), dry_run);
+
+        if dry_run {
+            println!("--- Dry Run Mode ---");
+            println!("Action: Set Charge Mode to {}", target_mode);
+            let current = legion_core::hw::battery::get_charge_mode();
+            if let HardwareReadResult::Success(current) = &current {
+                let steps: Vec<String> = current.steps_to(target_mode).iter().map(|s| format!("{:?}", s)).collect();
+                println!("Steps: {}", if steps.is_empty() { "none".to_string() } else { steps.join(" -> ") });
+            }
+            print_dry_run("Charge Mode", current, &target_mode, |m| m.to_string());
+            return;
+        }
+
+        let value = format!("{:?}", target_mode);
+        report_write("Charge Mode", &value,
+            with_token(legion_core::hw::battery::CHARGE_MODE_OPERATION, &value, "--charge-mode",
+                |token| legion_core::hw::battery::set_charge_mode(target_mode, None, token)), json_mode, audit);
+        return;
+    }
+
     if let Some(target_state) = set_conservation_mode_arg {
         info!("Command: Set Conservation Mode to {}", if target_state { "ON" } else { "OFF" });
+        require_offered("Conservation Mode", Some(Capability::BatteryConservationMode), |_| true, dry_run);
         
         if dry_run {
             println!("--- Dry Run Mode ---");
-            println!("Action: Set Conservation Mode to {}", if target_state { "ON" } else { "OFF" });
-            
-            match legion_core::hw::battery::get_conservation_mode() {
-                Some(current) => {
-                    println!("Current State: {}", if current { "ON" } else { "OFF" });
-                    
-                    if current == target_state {
-                        println!("Result: No change needed (values match).");
-                    } else {
-                        println!("Result: State would change.");
-                    }
-                },
-                None => {
-                    println!("Current State: Unknown (Read failed)");
-                    println!("WARNING: Unable to verify current state. Write might be unsafe.");
-                },
-            }
+            println!("Action: Set Conservation Mode to {}", on_off(&target_state));
+            print_dry_run("State", legion_core::hw::battery::get_conservation_mode(), &target_state, on_off);
+            // The two modes are exclusive, so show which one is active now.
+            print_read("Charge Mode", &legion_core::hw::battery::get_charge_mode(), |m| m.to_string());
             return;
         }
         
-        // Real Write
-        // Standard check: verify support again just in case (though detect_device usually gates this)
-        // For CLI simplicity in `main`, we'll trust the explicit flag if they forced it, 
-        // but it's good practice to ensure we aren't running on a toaster.
-        // (We rely on WMI failing if checking failed).
+        // Real Write: `require_offered` has checked the capability probe; the charge-mode
+        // state machine reads the current mode first and refuses an unreadable or illegal one.
 
-        legion_core::safety::guards::GlobalWriteLock::request_write_access();
-        
-        match legion_core::hw::battery::set_conservation_mode(target_state) {
-            Ok(_) => {
-                println!("Success: Conservation Mode set to {}.", if target_state { "ON" } else { "OFF" });
-                info!("Conservation Mode update successful.");
-            },
-            Err(e) => {
-                error!("Operation failed: {}", e);
-                eprintln!("Error: Failed to set Conservation Mode.");
-                eprintln!("Details: {}", e);
-                eprintln!("No changes were applied.");
-                std::process::exit(1);
-            }
-        }
+        report_write("Conservation Mode", &on_off(&target_state),
+            with_token(legion_core::hw::battery::CHARGE_MODE_OPERATION, &format!("Conservation {}", on_off(&target_state)), "--set-conservation-mode",
+                |token| legion_core::hw::battery::set_conservation_mode(target_state, None, token)), json_mode, audit);
         return;
     }
 
     // Handle Rapid Charge
     if let Some(target_state) = set_rapid_charge_arg {
         info!("Command: Set Rapid Charge to {}", if target_state { "ON" } else { "OFF" });
+        require_offered("Rapid Charge", Some(Capability::BatteryRapidCharge), |_| true, dry_run);
         if dry_run {
             println!("--- Dry Run Mode ---");
-            println!("Action: Set Rapid Charge to {}", if target_state { "ON" } else { "OFF" });
-            match legion_core::hw::battery::get_rapid_charge() {
-                Some(current) => {
-                    println!("Current State: {}", if current { "ON" } else { "OFF" });
-                    if current == target_state { println!("Result: No change needed."); } 
-                    else { println!("Result: State would change."); }
-                },
-                None => println!("WARNING: Unable to read current state. Write might be unsafe."),
-            }
+            println!("Action: Set Rapid Charge to {}", on_off(&target_state));
+            print_dry_run("State", legion_core::hw::battery::get_rapid_charge(), &target_state, on_off);
+            // The two modes are exclusive, so show which one is active now.
+            print_read("Charge Mode", &legion_core::hw::battery::get_charge_mode(), |m| m.to_string());
             return;
         }
-        legion_core::safety::guards::GlobalWriteLock::request_write_access();
-        match legion_core::hw::battery::set_rapid_charge(target_state) {
-            Ok(_) => println!("Success: Rapid Charge set to {}.", if target_state { "ON" } else { "OFF" }),
-            Err(e) => {
-                error!("Operation failed: {}", e);
-                eprintln!("Error: Failed to set Rapid Charge: {}", e);
-                std::process::exit(1);
-            }
-        }
+        report_write("Rapid Charge", &on_off(&target_state),
+            with_token(legion_core::hw::battery::CHARGE_MODE_OPERATION, &format!("Rapid Charge {}", on_off(&target_state)), "--rapid-charge",
+                |token| legion_core::hw::battery::set_rapid_charge(target_state, None, token)), json_mode, audit);
         return;
     }
 
@@ -181,107 +551,177 @@ fn main() {
         let target_profile = match profile_str.as_str() {
             "quiet" | "blue" => legion_core::hw::power::PowerProfile::Quiet,
             "balanced" | "white" | "auto" => legion_core::hw::power::PowerProfile::Balanced,
+            "balanced-performance" | "balanced-perf" => legion_core::hw::power::PowerProfile::BalancedPerformance,
             "perf" | "performance" | "red" => legion_core::hw::power::PowerProfile::Performance,
+            "custom" | "purple" => legion_core::hw::power::PowerProfile::Custom,
             _ => {
-                eprintln!("Error: Invalid profile '{}'. Use 'quiet', 'balanced', or 'perf'.", profile_str);
+                eprintln!("Error: Invalid profile '{}'. Use 'quiet', 'balanced', 'balanced-performance', 'perf' or 'custom'.", profile_str);
                 std::process::exit(1);
             }
         };
 
         info!("Command: Set Power Profile to {:?}", target_profile);
+        require_offered(&format!("{:?} thermal mode", target_profile), Some(Capability::ThermalMode), |caps| caps.offers_thermal_mode(target_profile), dry_run);
         if dry_run {
             println!("--- Dry Run Mode ---");
             println!("Action: Set Power Profile to {:?}", target_profile);
-            match legion_core::hw::power::get_power_profile() {
-                Some(current) => {
-                    println!("Current Profile: {:?}", current);
-                    if current == target_profile { println!("Result: No change needed."); }
-                    else { println!("Result: Profile would change."); }
-                },
-                None => println!("WARNING: Unable to read current profile."),
+            print_dry_run("Profile", legion_core::hw::power::get_power_profile(), &target_profile, |p| format!("{:?}", p));
+            let offered = legion_core::hw::power::get_available_profiles().ok().unwrap_or_default();
+            if !offered.contains(&target_profile) {
+                println!("WARNING: This machine does not offer {:?}. The write would be refused.", target_profile);
             }
             return;
         }
 
-        legion_core::safety::guards::GlobalWriteLock::request_write_access();
-        match legion_core::hw::power::set_power_profile(target_profile) {
-            Ok(_) => println!("Success: Power Profile set to {:?}.", target_profile),
-            Err(e) => {
-                error!("Operation failed: {}", e);
-                eprintln!("Error: Failed to set Power Profile: {}", e);
-                std::process::exit(1);
+        let value = format!("{:?}", target_profile);
+        report_write("Power Profile", &value,
+            with_token(legion_core::hw::power::POWER_PROFILE_OPERATION, &value, "--set-profile",
+                |token| legion_core::hw::power::set_power_profile(target_profile, None, token)), json_mode, audit);
+        return;
+    }
+
+    // Handle Keyboard Lighting (one report with every field)
+    if !lighting_edits.is_empty() {
+        require_offered("4-zone keyboard lighting", Some(Capability::FourZoneRgbKeyboard),
+            |caps| caps.keyboard_backlight == KeyboardBacklight::FourZoneRgb, dry_run);
+        // Edit the state the controller reports; the write then expects it unchanged
+        let current = legion_core::hw::lighting::LightingController::new().read_state();
+        let expected = current.value().copied();
+        if let HardwareReadResult::Failure(e) = &current {
+            eprintln!("Note: current lighting could not be read ({}); unset fields use the default.", e.code());
+        }
+        let mut lighting = expected.unwrap_or_default();
+        for (flag, val) in &lighting_edits {
+            edit_lighting(&mut lighting, flag, val);
+        }
+        info!("Command: Set Keyboard Lighting to {}", lighting);
+        if dry_run {
+            println!("--- Dry Run Mode ---");
+            println!("Action: Set Keyboard Lighting to {}", lighting);
+            match lighting.to_report() {
+                Ok(report) => {
+                    println!("Report: {}", report.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" "));
+                    // Compare as the controller would read the report back
+                    if let Ok(target) = KeyboardLighting::from_report(&report) {
+                        print_dry_run("Lighting", current, &target, |l| l.to_string());
+                    }
+                }
+                Err(e) => println!("WARNING: {}. The write would be refused.", e),
             }
+            return;
         }
+        let value = lighting.to_string();
+        report_write(legion_core::hw::lighting::LIGHTING_OPERATION, &value,
+            with_token(legion_core::hw::lighting::LIGHTING_OPERATION, &value, "--effect/--zone",
+                |token| legion_core::hw::lighting::LightingController::new().apply(&lighting, expected.as_ref(), token)), json_mode, audit);
         return;
     }
 
-    match legion_core::device::detect::detect_device() {
-        Ok(device) => {
-            if json_mode {
-                let json = serde_json::to_string_pretty(&device).unwrap_or_default();
-                println!("{}", json);
-                return;
+    // Handle Fn Lock / USB Charging / Camera Power
+    if !set_toggle_args.is_empty() {
+        for (toggle, target_state) in set_toggle_args {
+            info!("Command: Set {} to {}", toggle, if target_state { "ON" } else { "OFF" });
+            // Toggles are not described per model; any listed machine may use them.
+            require_offered(&toggle.to_string(), None, |_| true, dry_run);
+            if dry_run {
+                println!("--- Dry Run Mode ---");
+                println!("Action: Set {} to {}", toggle, on_off(&target_state));
+                print_dry_run("State", legion_core::hw::toggles::get_toggle(toggle), &target_state, on_off);
+                continue;
             }
+            report_write(toggle.label(), &on_off(&target_state),
+                with_token(toggle.label(), &on_off(&target_state), toggle.cli_flag(),
+                    |token| legion_core::hw::toggles::set_toggle(toggle, target_state, None, token)), json_mode, audit);
+        }
+        return;
+    }
+
+    let snapshot = legion_core::snapshot::HardwareStateSnapshot::capture();
+    if json_mode {
+        println!("{}", serde_json::to_string_pretty(&snapshot).unwrap_or_default());
+        if let HardwareReadResult::Failure(e) = &snapshot.device {
+            std::process::exit(e.status().exit_code());
+        }
+        return;
+    }
 
+    match &snapshot.device {
+        HardwareReadResult::Success(device) => {
             println!("Legion + LOQ Control (v0.2.0)");
             println!("-----------------------------");
             
-            if !device.supported {
+            if !device.is_supported() {
                 println!("Status: Unsupported Device (Read-Only)");
-                println!("Reason: Model '{}' not recognized as Legion or LOQ.", device.model_name);
-                if device.manufacturer.to_uppercase().contains("LENOVO") {
-                     println!("Note: Detected Lenovo device, but series '{:?}' is not in the allowlist.", device.series);
+                println!("Reason: {} [{}] with BIOS {} is not in the model database (revision {}).",
+                    device.marketing_model, device.machine_type, device.bios_version, legion_core::device::database::active().revision);
+                if device.series.is_legion() || device.series == legion_core::device::models::Series::LOQ {
+                    if let Some(path) = legion_core::device::database::override_path() {
+                        println!("Note: Detected a {:?} machine. Describe it in {} to enable controls.", device.series, path.display());
+                    }
                 }
                 println!("Hardware control features are disabled for safety.");
             } else {
                 println!("Status: Supported");
-                println!("Device: {} ({:?})", device.model_name, device.series);
-                println!("BIOS:   {}", device.bios_version);
-                
-                // Hardware Monitoring
-                println!("\n--- Hardware Status ---");
-                
-                match legion_core::hw::battery::get_battery_status() {
-                    Some(bat) => println!("Battery:           {}% (Charging: {})", bat.charge_percent, bat.is_charging),
-                    None => println!("Battery:           Not detected"),
+                println!("Device: {} [{}] ({:?})", device.marketing_model, device.machine_type, device.series);
+                if let (Some(size), Some(platform), Some(generation)) = (device.screen_size, &device.platform_code, device.generation) {
+                    println!("Model:  {}\" {} Gen {}", size, platform, generation);
                 }
-                
-                
-                match legion_core::hw::battery::get_conservation_mode() {
-                    Some(enabled) => println!("Conservation Mode: {}", if enabled { "ON" } else { "OFF" }),
-                    None => println!("Conservation Mode: Unknown (WMI unavailable)"),
+                match &device.bios {
+                    Some(bios) => println!("BIOS:   {} (family {}, build {})", device.bios_version, bios.family, bios.build),
+                    None => println!("BIOS:   {} (unrecognised format)", device.bios_version),
                 }
-
-                match legion_core::hw::battery::get_rapid_charge() {
-                    Some(enabled) => println!("Rapid Charge:      {}", if enabled { "ON" } else { "OFF" }),
-                    None => println!("Rapid Charge:      Unknown"),
+                if let Some(caps) = &device.capabilities {
+                    let modes: Vec<String> = caps.thermal_modes.iter().map(|p| format!("{:?}", p)).collect();
+                    println!("Thermal Modes: {}", if modes.is_empty() { "none".to_string() } else { modes.join(", ") });
                 }
 
-                match legion_core::hw::power::get_power_profile() {
-                    Some(p) => println!("Power Profile:     {}", p),
-                    None => println!("Power Profile:     Unknown"),
-                }
+                println!("\n--- Capabilities ---");
+                print_capabilities(&legion_core::capability::probe::probe(device));
                 
-                match legion_core::hw::thermal::get_cpu_temp() {
-                    Some(t) => println!("CPU Temp:          {:.1}°C", t),
-                    None => println!("CPU Temp:          N/A (Stubbed)"),
+                // Hardware Monitoring
+                println!("\n--- Hardware Status ({}) ---", legion_core::time::format_utc(snapshot.captured_at));
+                
+                match &snapshot.battery {
+                    HardwareReadResult::Success(bat) => {
+                        let na = || "N/A".to_string();
+                        println!("Battery:           {}% ({})", bat.charge_percent, bat.state);
+                        println!("AC Adapter:        {}", bat.ac_online.map(|on| if on { "Online" } else { "Offline" }.to_string()).unwrap_or_else(na));
+                        println!("Energy:            {} / {} Wh",
+                            bat.energy_now_wh.map(|e| format!("{:.1}", e)).unwrap_or_else(na),
+                            bat.energy_full_wh.map(|e| format!("{:.1}", e)).unwrap_or_else(na));
+                        println!("Design Capacity:   {}{}",
+                            bat.energy_design_wh.map(|e| format!("{:.1} Wh", e)).unwrap_or_else(na),
+                            bat.health_percent().map(|h| format!(" (health {:.0}%)", h)).unwrap_or_default());
+                        println!("Power Draw:        {}", bat.power_w.map(|p| format!("{:.1} W", p)).unwrap_or_else(na));
+                        println!("Voltage:           {}", bat.voltage_v.map(|v| format!("{:.2} V", v)).unwrap_or_else(na));
+                        println!("Cycle Count:       {}", bat.cycle_count.map(|c| c.to_string()).unwrap_or_else(na));
+                    },
+                    HardwareReadResult::Failure(e) => println!("Battery:           Not detected [{}]", e.code()),
                 }
+                
+                print_read("Charge Mode", &snapshot.charge_mode, |m| format!("{:?}", m));
+                print_read("Conservation Mode", &snapshot.conservation_mode, on_off);
+                print_read("Rapid Charge", &snapshot.rapid_charge, on_off);
+                print_read("Power Profile", &snapshot.thermal_mode, |p| p.to_string());
+                print_read("Overdrive", &snapshot.overdrive, on_off);
+                print_read("GPU Mode", &snapshot.igpu_mode, |m| m.to_string());
+                print_read("Keyboard Lighting", &snapshot.lighting, |l| l.to_string());
 
-                 match legion_core::hw::thermal::get_gpu_temp() {
-                    Some(t) => println!("GPU Temp:          {:.1}°C", t),
-                    None => println!("GPU Temp:          N/A (Stubbed)"),
+                for (toggle, result) in &snapshot.toggles {
+                    print_read(toggle.label(), result, on_off);
                 }
+                
+                let celsius = |t: Option<f32>| t.map(|t| format!("{:.1}°C", t)).unwrap_or_else(|| "N/A".to_string());
+                print_read("CPU Temp", &snapshot.temperatures, |t| celsius(legion_core::hw::thermal::cpu_temp(t)));
+                print_read("GPU Temp", &snapshot.temperatures, |t| celsius(legion_core::hw::thermal::gpu_temp(t)));
             }
         },
-        Err(e) => {
-            if json_mode {
-                println!("{{ \"error\": \"{}\" }}", e);
-            } else {
-                error!("Device detection failed: {}", e);
-                eprintln!("Error: Critical failure during device detection.");
-                eprintln!("Details: {}", e);
-                eprintln!("Ensure you are running as Administrator and WMI is accessible.");
-            }
+        HardwareReadResult::Failure(e) => {
+            error!("Device detection failed: {}", e);
+            eprintln!("Error: Critical failure during device detection [{}].", e.code());
+            eprintln!("Details: {}", e);
+            eprintln!("Ensure you are running as Administrator and WMI is accessible.");
+            std::process::exit(e.status().exit_code());
         }
     }
 }
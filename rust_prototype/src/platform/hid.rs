@@ -0,0 +1,160 @@
+use crate::legion_core::hw::hid_transport::{HidHandle, HidTransport};
+use crate::legion_core::hw::ite_device::{self, HidCollection};
+use crate::legion_core::result::{HardwareError, HardwareResult};
+use hidapi::{DeviceInfo, HidApi, HidDevice, HidError, MAX_REPORT_DESCRIPTOR_SIZE};
+use std::collections::HashMap;
+use std::ffi::CString;
+
+/// `HidTransport` over hidapi (hidraw on Linux, the HID class driver on Windows).
+pub struct HidApiTransport;
+
+impl HidTransport for HidApiTransport {
+    /// Only collections that pass `ite_device::check_enumerated` are opened, once each,
+    /// to read their report descriptor; the rest are listed without being opened.
+    fn enumerate(&self, vendor_id: u16) -> HardwareResult<Vec<HidCollection>> {
+        let api = HidApi::new().map_err(|e| hid_error("hidapi init", e))?;
+        Ok(api.device_list()
+            .filter(|device| device.vendor_id() == vendor_id)
+            .map(|device| collection(&api, device))
+            .collect())
+    }
+
+    fn open(&self, path: &str) -> HardwareResult<Box<dyn HidHandle + '_>> {
+        let api = HidApi::new().map_err(|e| hid_error("hidapi init", e))?;
+        let path = CString::new(path).map_err(|e| HardwareError::InvalidData(format!("HID path {}: {}", path, e)))?;
+        let device = api.open_path(&path).map_err(|e| hid_error("open lighting device", e))?;
+        Ok(Box::new(device))
+    }
+}
+
+impl HidHandle for HidDevice {
+    fn send_feature_report(&self, report: &[u8]) -> HardwareResult<()> {
+        HidDevice::send_feature_report(self, report).map_err(|e| hid_error("send feature report", e))
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> HardwareResult<usize> {
+        HidDevice::get_feature_report(self, buf).map_err(|e| hid_error("get feature report", e))
+    }
+}
+
+fn collection(api: &HidApi, device: &DeviceInfo) -> HidCollection {
+    let feature_report_len = match ite_device::check_enumerated(device.product_id()) {
+        // Only collections of allowlisted controllers are ever opened
+        Err(rejection) => Err(HardwareError::Unsupported(format!("Report descriptor not read: {}", rejection))),
+        Ok(()) => descriptor_report_len(api, device),
+    };
+    HidCollection {
+        path: device.path().to_string_lossy().into_owned(),
+        vendor_id: device.vendor_id(),
+        product_id: device.product_id(),
+        usage_page: device.usage_page(),
+        usage: device.usage(),
+        feature_report_len,
+    }
+}
+
+/// Opens `device` once to read the length of its lighting feature report from the report descriptor.
+fn descriptor_report_len(api: &HidApi, device: &DeviceInfo) -> HardwareResult<usize> {
+    let report_id = ite_device::report_id(device.product_id())?;
+    let descriptor = api.open_path(device.path())
+        .and_then(|handle| {
+            let mut descriptor = vec![0u8; MAX_REPORT_DESCRIPTOR_SIZE];
+            let len = handle.get_report_descriptor(&mut descriptor)?;
+            descriptor.truncate(len);
+            Ok(descriptor)
+        })
+        .map_err(|e| hid_error(&format!("report descriptor of {}", device.path().to_string_lossy()), e))?;
+    feature_report_len(&descriptor, report_id).ok_or_else(|| HardwareError::InvalidData(
+        format!("No feature report {:02x} in the report descriptor", report_id)))
+}
+
+/// Bytes in feature report `report_id` (including the ID byte) as declared by a HID
+/// report descriptor; `None` if the descriptor declares no such feature report.
+fn feature_report_len(descriptor: &[u8], report_id: u8) -> Option<usize> {
+    #[derive(Clone, Copy, Default)]
+    struct Globals {
+        report_id: u8,
+        report_size: u32,
+        report_count: u32,
+    }
+
+    let mut globals = Globals::default();
+    let mut stack = Vec::new();
+    let mut feature_bits: HashMap<u8, u32> = HashMap::new();
+    let mut numbered = false;
+    let mut i = 0;
+    while i < descriptor.len() {
+        let prefix = descriptor[i];
+        // Long item: 0xFE, data size, tag, data
+        if prefix == 0xFE {
+            i += 3 + *descriptor.get(i + 1)? as usize;
+            continue;
+        }
+        let size = [0, 1, 2, 4][(prefix & 0x03) as usize];
+        let data = descriptor.get(i + 1..i + 1 + size)?;
+        let value = data.iter().rev().fold(0u32, |acc, b| (acc << 8) | *b as u32);
+        match prefix & 0xFC {
+            0x74 => globals.report_size = value,
+            0x84 => {
+                globals.report_id = value as u8;
+                numbered = true;
+            }
+            0x94 => globals.report_count = value,
+            0xA4 => stack.push(globals),
+            0xB4 => globals = stack.pop().unwrap_or_default(),
+            0xB0 => *feature_bits.entry(globals.report_id).or_default() += globals.report_size * globals.report_count,
+            _ => {}
+        }
+        i += 1 + size;
+    }
+
+    let bits = *feature_bits.get(&if numbered { report_id } else { 0 })?;
+    Some(bits.div_ceil(8) as usize + numbered as usize)
+}
+
+/// Keeps the I/O error kind (EACCES on /dev/hidraw* → AccessDenied) where hidapi exposes it.
+fn hid_error(context: &str, e: HidError) -> HardwareError {
+    match e {
+        HidError::IoError { error } => HardwareError::from_io(context, error),
+        other => HardwareError::Failed(format!("{}: {}", context, other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Vendor collection with a 32-byte feature report 0xCC and a 16-byte one 0x5A.
+    const ITE_DESCRIPTOR: &[u8] = &[
+        0x06, 0x89, 0xFF, // Usage Page (0xFF89)
+        0x09, 0xCC,       // Usage (0xCC)
+        0xA1, 0x01,       // Collection (Application)
+        0x85, 0xCC,       //   Report ID (0xCC)
+        0x15, 0x00,       //   Logical Minimum (0)
+        0x26, 0xFF, 0x00, //   Logical Maximum (255)
+        0x75, 0x08,       //   Report Size (8)
+        0x95, 0x20,       //   Report Count (32)
+        0x09, 0x01,       //   Usage (1)
+        0xB1, 0x02,       //   Feature (Data, Var, Abs)
+        0x85, 0x5A,       //   Report ID (0x5A)
+        0x95, 0x10,       //   Report Count (16)
+        0x09, 0x02,       //   Usage (2)
+        0xB1, 0x02,       //   Feature (Data, Var, Abs)
+        0xC0,             // End Collection
+    ];
+
+    #[test]
+    fn feature_report_length_includes_the_report_id() {
+        assert_eq!(feature_report_len(ITE_DESCRIPTOR, 0xCC), Some(33));
+        assert_eq!(feature_report_len(ITE_DESCRIPTOR, 0x5A), Some(17));
+        assert_eq!(feature_report_len(ITE_DESCRIPTOR, 0x01), None);
+    }
+
+    #[test]
+    fn unnumbered_and_truncated_descriptors() {
+        // Without a Report ID item the only feature report has no ID byte
+        let unnumbered = [0x75, 0x08, 0x95, 0x40, 0xB1, 0x02];
+        assert_eq!(feature_report_len(&unnumbered, 0xCC), Some(64));
+        assert_eq!(feature_report_len(&ITE_DESCRIPTOR[..ITE_DESCRIPTOR.len() - 12], 0xCC), None);
+    }
+}
@@ -0,0 +1,97 @@
+use super::read_attr;
+use crate::legion_core::device::models::DeviceIdentity;
+use crate::legion_core::result::HardwareResult;
+use std::path::Path;
+
+const DMI_DIR: &str = "class/dmi/id";
+
+/// The only DMI attributes this module can open. Serial numbers and UUIDs
+/// (`product_serial`, `board_serial`, `chassis_serial`, `product_uuid`) have no variant,
+/// so reading one does not compile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DmiField {
+    SysVendor,
+    ProductName,
+    ProductVersion,
+    ProductFamily,
+    BiosVersion,
+}
+
+impl DmiField {
+    fn attribute(self) -> &'static str {
+        match self {
+            DmiField::SysVendor => "sys_vendor",
+            DmiField::ProductName => "product_name",
+            DmiField::ProductVersion => "product_version",
+            DmiField::ProductFamily => "product_family",
+            DmiField::BiosVersion => "bios_version",
+        }
+    }
+}
+
+fn field(sysfs_root: &Path, field: DmiField) -> HardwareResult<String> {
+    read_attr(&sysfs_root.join(DMI_DIR).join(field.attribute()))
+}
+
+/// Reads vendor, product and BIOS strings from `/sys/class/dmi/id` (world-readable, no root needed).
+pub fn read_identity(sysfs_root: &Path) -> HardwareResult<DeviceIdentity> {
+    Ok(DeviceIdentity {
+        manufacturer: field(sysfs_root, DmiField::SysVendor)?,
+        product_name: field(sysfs_root, DmiField::ProductName)?,
+        product_version: field(sysfs_root, DmiField::ProductVersion).unwrap_or_default(),
+        product_family: field(sysfs_root, DmiField::ProductFamily).ok(),
+        bios_version: field(sysfs_root, DmiField::BiosVersion)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::linux::fixture::FakeSysfs;
+
+    #[test]
+    fn reads_loq_identity() {
+        let sysfs = FakeSysfs::new();
+        sysfs.write("class/dmi/id/sys_vendor", "LENOVO\n");
+        sysfs.write("class/dmi/id/product_name", "83DV\n");
+        sysfs.write("class/dmi/id/product_version", "LOQ 15IRX9\n");
+        sysfs.write("class/dmi/id/product_family", "LOQ 15IRX9\n");
+        sysfs.write("class/dmi/id/bios_version", "NECN50WW\n");
+        sysfs.write("class/dmi/id/product_serial", "PF0XXXXX\n");
+
+        let identity = read_identity(sysfs.root()).unwrap();
+        assert_eq!(identity, DeviceIdentity {
+            manufacturer: "LENOVO".to_string(),
+            product_name: "83DV".to_string(),
+            product_version: "LOQ 15IRX9".to_string(),
+            product_family: Some("LOQ 15IRX9".to_string()),
+            bios_version: "NECN50WW".to_string(),
+        });
+    }
+
+    #[test]
+    fn never_touches_serial_fields() {
+        let sysfs = FakeSysfs::new();
+        sysfs.write("class/dmi/id/sys_vendor", "LENOVO\n");
+        sysfs.write("class/dmi/id/product_name", "83DV\n");
+        sysfs.write("class/dmi/id/bios_version", "NECN50WW\n");
+        // Directories in place of the serial attributes: any attempt to read one fails
+        for name in ["product_serial", "board_serial", "chassis_serial", "product_uuid"] {
+            std::fs::create_dir_all(sysfs.root().join(DMI_DIR).join(name)).unwrap();
+        }
+
+        assert_eq!(read_identity(sysfs.root()).unwrap().product_name, "83DV");
+    }
+
+    #[test]
+    fn optional_fields_may_be_missing() {
+        let sysfs = FakeSysfs::new();
+        sysfs.write("class/dmi/id/sys_vendor", "LENOVO\n");
+        sysfs.write("class/dmi/id/product_name", "82JW\n");
+        sysfs.write("class/dmi/id/bios_version", "GKCN58WW\n");
+
+        let identity = read_identity(sysfs.root()).unwrap();
+        assert_eq!(identity.product_version, "");
+        assert_eq!(identity.product_family, None);
+    }
+}
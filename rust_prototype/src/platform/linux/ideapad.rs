@@ -0,0 +1,94 @@
+use super::{read_attr, write_attr};
+use crate::legion_core::result::{HardwareError, HardwareResult};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONSERVATION_MODE: &str = "conservation_mode";
+
+const DRIVER_DIR: &str = "bus/platform/drivers/ideapad_acpi";
+const DEVICE_PREFIX: &str = "VPC2004:";
+
+/// The `ideapad_acpi` platform device (`VPC2004:*`) and its boolean attributes.
+pub struct IdeapadAcpi {
+    device_dir: PathBuf,
+}
+
+impl IdeapadAcpi {
+    /// Finds the first `VPC2004:*` device bound to `ideapad_acpi` under `sysfs_root`.
+    pub fn locate(sysfs_root: &Path) -> HardwareResult<Self> {
+        let driver_dir = sysfs_root.join(DRIVER_DIR);
+        let entries = fs::read_dir(&driver_dir)
+            .map_err(|e| HardwareError::Unavailable(format!("ideapad_acpi driver not loaded ({}: {})", driver_dir.display(), e)))?;
+
+        let mut devices: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(DEVICE_PREFIX))
+            .map(|entry| entry.path())
+            .collect();
+        devices.sort();
+
+        devices.into_iter().next()
+            .map(|device_dir| Self { device_dir })
+            .ok_or_else(|| HardwareError::Unavailable(format!("No {}* device under {}", DEVICE_PREFIX, driver_dir.display())))
+    }
+
+    pub fn read(&self, attribute: &str) -> HardwareResult<bool> {
+        let raw = read_attr(&self.device_dir.join(attribute))?;
+        match raw.as_str() {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            other => Err(HardwareError::InvalidData(format!("Unexpected value '{}' in ideapad_acpi/{}", other, attribute))),
+        }
+    }
+
+    pub fn write(&self, attribute: &str, enable: bool) -> HardwareResult<()> {
+        let path = self.device_dir.join(attribute);
+        // The driver only creates attributes the firmware supports; never create one.
+        if !path.exists() {
+            return Err(HardwareError::Unsupported(format!("ideapad_acpi/{} is not exposed on this machine", attribute)));
+        }
+        write_attr(&path, if enable { "1" } else { "0" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::linux::fixture::FakeSysfs;
+
+    const DEVICE: &str = "bus/platform/drivers/ideapad_acpi/VPC2004:00";
+
+    #[test]
+    fn reads_and_writes_attributes() {
+        let sysfs = FakeSysfs::new();
+        sysfs.write(&format!("{}/conservation_mode", DEVICE), "0\n");
+        sysfs.write(&format!("{}/fn_lock", DEVICE), "1\n");
+
+        let ideapad = IdeapadAcpi::locate(sysfs.root()).unwrap();
+        assert!(!ideapad.read(CONSERVATION_MODE).unwrap());
+        assert!(ideapad.read("fn_lock").unwrap());
+
+        ideapad.write(CONSERVATION_MODE, true).unwrap();
+        assert_eq!(sysfs.read(&format!("{}/conservation_mode", DEVICE)), "1");
+    }
+
+    #[test]
+    fn missing_driver_or_attribute_is_an_error() {
+        let sysfs = FakeSysfs::new();
+        assert!(matches!(IdeapadAcpi::locate(sysfs.root()), Err(HardwareError::Unavailable(_))));
+
+        sysfs.write(&format!("{}/conservation_mode", DEVICE), "0\n");
+        let ideapad = IdeapadAcpi::locate(sysfs.root()).unwrap();
+        assert!(matches!(ideapad.read("camera_power"), Err(HardwareError::Unavailable(_))));
+        assert!(matches!(ideapad.write("camera_power", true), Err(HardwareError::Unsupported(_))));
+        assert!(!sysfs.root().join(DEVICE).join("camera_power").exists());
+    }
+
+    #[test]
+    fn rejects_unexpected_values() {
+        let sysfs = FakeSysfs::new();
+        sysfs.write(&format!("{}/usb_charging", DEVICE), "2\n");
+        let ideapad = IdeapadAcpi::locate(sysfs.root()).unwrap();
+        assert!(matches!(ideapad.read("usb_charging"), Err(HardwareError::InvalidData(_))));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::legion_core::backend::{self, HardwareBackend};
+use crate::legion_core::device::models::DeviceIdentity;
+use crate::legion_core::hw::battery::{BatteryStatus, ChargeMode};
+use crate::legion_core::hw::hid_transport::HidTransport;
+use crate::legion_core::hw::ite_device::{self, HidCollection};
+use crate::legion_core::hw::power::PowerProfile;
+use crate::legion_core::hw::thermal::TemperatureReading;
+use crate::legion_core::hw::toggles::Toggle;
+use crate::legion_core::result::{HardwareError, HardwareResult};
+use super::hid::HidApiTransport;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub mod dmi;
+pub mod ideapad;
+pub mod platform_profile;
+pub mod power_supply;
+pub mod sensors;
+
+#[cfg(test)]
+pub(crate) mod fixture;
+
+/// Overrides the sysfs mount point (default `/sys`), e.g. to point at a captured tree.
+pub const SYSFS_ROOT_ENV: &str = "LEGION_SYSFS_ROOT";
+
+/// sysfs implementation of `HardwareBackend` (DMI, ideapad_acpi, platform_profile, power_supply, hwmon, hidraw).
+pub struct LinuxBackend {
+    sysfs_root: PathBuf,
+}
+
+impl LinuxBackend {
+    pub fn new(sysfs_root: impl Into<PathBuf>) -> Self {
+        Self { sysfs_root: sysfs_root.into() }
+    }
+
+    /// Uses `LEGION_SYSFS_ROOT` if set, otherwise `/sys`.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var_os(SYSFS_ROOT_ENV).unwrap_or_else(|| "/sys".into()))
+    }
+
+    fn ideapad(&self) -> HardwareResult<ideapad::IdeapadAcpi> {
+        ideapad::IdeapadAcpi::locate(&self.sysfs_root)
+    }
+
+    fn platform_profile(&self) -> platform_profile::PlatformProfile {
+        platform_profile::PlatformProfile::new(&self.sysfs_root)
+    }
+}
+
+impl HardwareBackend for LinuxBackend {
+    fn name(&self) -> &'static str {
+        "linux"
+    }
+
+    fn identity(&self) -> HardwareResult<DeviceIdentity> {
+        dmi::read_identity(&self.sysfs_root)
+    }
+
+    fn battery_status(&self) -> HardwareResult<BatteryStatus> {
+        power_supply::read_battery(&self.sysfs_root)
+    }
+
+    fn conservation_mode(&self) -> HardwareResult<bool> {
+        self.ideapad()?.read(ideapad::CONSERVATION_MODE)
+    }
+
+    fn set_conservation_mode(&self, enable: bool) -> HardwareResult<()> {
+        self.ideapad()?.write(ideapad::CONSERVATION_MODE, enable)
+    }
+
+    fn rapid_charge(&self) -> HardwareResult<bool> {
+        // ideapad_acpi does not expose rapid charge; it needs the out-of-tree legion-laptop module.
+        backend::unsupported(self.name(), "Rapid charge")
+    }
+
+    fn set_rapid_charge(&self, _enable: bool) -> HardwareResult<()> {
+        backend::unsupported(self.name(), "Rapid charge")
+    }
+
+    fn charge_mode(&self) -> HardwareResult<ChargeMode> {
+        // Without rapid charge the mode is conservation or normal.
+        Ok(if self.conservation_mode()? { ChargeMode::Conservation } else { ChargeMode::Normal })
+    }
+
+    fn toggle(&self, toggle: Toggle) -> HardwareResult<bool> {
+        self.ideapad()?.read(toggle.key())
+    }
+
+    fn set_toggle(&self, toggle: Toggle, enable: bool) -> HardwareResult<()> {
+        self.ideapad()?.write(toggle.key(), enable)
+    }
+
+    fn power_profile(&self) -> HardwareResult<PowerProfile> {
+        self.platform_profile().read()
+    }
+
+    fn set_power_profile(&self, profile: PowerProfile) -> HardwareResult<()> {
+        self.platform_profile().write(profile)
+    }
+
+    fn power_profile_choices(&self) -> HardwareResult<Vec<PowerProfile>> {
+        self.platform_profile().choices()
+    }
+
+    fn temperatures(&self) -> HardwareResult<Vec<TemperatureReading>> {
+        Ok(sensors::discover(&self.sysfs_root))
+    }
+
+    fn set_light_control_owner(&self, _app_control: bool) -> HardwareResult<()> {
+        // Ownership is a LENOVO_GAMEZONE_DATA WMI method with no sysfs equivalent.
+        backend::unsupported(self.name(), "Lighting ownership")
+    }
+
+    fn send_lighting_report(&self, report: &[u8]) -> HardwareResult<()> {
+        ite_device::send_report(&HidApiTransport, report)
+    }
+
+    fn lighting_report(&self) -> HardwareResult<Vec<u8>> {
+        ite_device::read_report(&HidApiTransport)
+    }
+
+    fn lighting_collections(&self) -> HardwareResult<Vec<HidCollection>> {
+        HidApiTransport.enumerate(ite_device::VENDOR_ID)
+    }
+}
+
+/// Reads a sysfs attribute, trimming the trailing newline. I/O errors keep their kind
+/// (EACCES → `AccessDenied`, ENOENT → `Unavailable`).
+pub(crate) fn read_attr(path: &Path) -> HardwareResult<String> {
+    fs::read_to_string(path)
+        .map(|s| s.trim().to_string())
+        .map_err(|e| HardwareError::from_io(&path.display().to_string(), e))
+}
+
+pub(crate) fn write_attr(path: &Path, value: &str) -> HardwareResult<()> {
+    fs::write(path, value).map_err(|e| HardwareError::from_io(&path.display().to_string(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::legion_core::hw::battery::{self, CHARGE_MODE_OPERATION};
+    use crate::legion_core::result::WriteOutcome;
+    use crate::legion_core::safety::guards::WriteToken;
+    use fixture::FakeSysfs;
+
+    const CONSERVATION: &str = "bus/platform/drivers/ideapad_acpi/VPC2004:00/conservation_mode";
+
+    #[test]
+    fn conservation_toggles_through_the_charge_mode_state_machine() {
+        let sysfs = FakeSysfs::new();
+        sysfs.write(CONSERVATION, "0\n");
+        let linux = LinuxBackend::new(sysfs.root());
+        assert_eq!(linux.charge_mode().unwrap(), ChargeMode::Normal);
+
+        let token = WriteToken::grant(CHARGE_MODE_OPERATION, "test", "test");
+        crate::legion_core::safety::lock::tests::use_test_lock_file();
+        let on = battery::change_charge_mode(&linux, None, &token, Some(ChargeMode::Normal), |_| ChargeMode::Conservation);
+        assert_eq!(on, Ok(WriteOutcome::Verified));
+        assert_eq!(sysfs.read(CONSERVATION), "1");
+
+        let off = battery::change_charge_mode(&linux, None, &token, Some(ChargeMode::Conservation), |_| ChargeMode::Normal);
+        assert_eq!(off, Ok(WriteOutcome::Verified));
+        assert_eq!(sysfs.read(CONSERVATION), "0");
+    }
+}
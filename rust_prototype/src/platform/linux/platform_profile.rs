@@ -0,0 +1,135 @@
+use super::{read_attr, write_attr};
+use crate::legion_core::hw::power::PowerProfile;
+use crate::legion_core::result::{HardwareError, HardwareResult};
+use std::path::{Path, PathBuf};
+
+const PROFILE: &str = "firmware/acpi/platform_profile";
+const CHOICES: &str = "firmware/acpi/platform_profile_choices";
+
+/// Kernel names accepted for each profile, most specific first.
+fn kernel_names(profile: PowerProfile) -> &'static [&'static str] {
+    match profile {
+        PowerProfile::Quiet => &["low-power", "quiet"],
+        PowerProfile::Balanced => &["balanced"],
+        PowerProfile::BalancedPerformance => &["balanced-performance"],
+        PowerProfile::Performance => &["performance"],
+        PowerProfile::Custom => &["custom"],
+        PowerProfile::Unknown => &[],
+    }
+}
+
+fn from_kernel_name(name: &str) -> PowerProfile {
+    match name {
+        "low-power" | "quiet" => PowerProfile::Quiet,
+        "balanced" => PowerProfile::Balanced,
+        "balanced-performance" => PowerProfile::BalancedPerformance,
+        "performance" => PowerProfile::Performance,
+        "custom" => PowerProfile::Custom,
+        // "cool" and vendor additions have no Legion equivalent.
+        _ => PowerProfile::Unknown,
+    }
+}
+
+/// ACPI `platform_profile` (`/sys/firmware/acpi/platform_profile{,_choices}`).
+pub struct PlatformProfile {
+    root: PathBuf,
+}
+
+impl PlatformProfile {
+    pub fn new(sysfs_root: &Path) -> Self {
+        Self { root: sysfs_root.to_path_buf() }
+    }
+
+    pub fn read(&self) -> HardwareResult<PowerProfile> {
+        Ok(from_kernel_name(&read_attr(&self.root.join(PROFILE))?))
+    }
+
+    /// Profiles listed in `platform_profile_choices`, in display order.
+    pub fn choices(&self) -> HardwareResult<Vec<PowerProfile>> {
+        let raw = self.raw_choices()?;
+        let order = [
+            PowerProfile::Quiet,
+            PowerProfile::Balanced,
+            PowerProfile::BalancedPerformance,
+            PowerProfile::Performance,
+            PowerProfile::Custom,
+        ];
+        Ok(order.into_iter()
+            .filter(|p| kernel_names(*p).iter().any(|name| raw.iter().any(|c| c == name)))
+            .collect())
+    }
+
+    pub fn write(&self, profile: PowerProfile) -> HardwareResult<()> {
+        let raw = self.raw_choices()?;
+        let name = kernel_names(profile).iter()
+            .find(|name| raw.iter().any(|c| c == *name))
+            .ok_or_else(|| HardwareError::Unsupported(format!("platform_profile does not offer {}", profile)))?;
+        write_attr(&self.root.join(PROFILE), name)
+    }
+
+    fn raw_choices(&self) -> HardwareResult<Vec<String>> {
+        Ok(read_attr(&self.root.join(CHOICES))?
+            .split_whitespace()
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::linux::fixture::FakeSysfs;
+
+    fn fixture(current: &str, choices: &str) -> FakeSysfs {
+        let sysfs = FakeSysfs::new();
+        sysfs.write(PROFILE, &format!("{}\n", current));
+        sysfs.write(CHOICES, &format!("{}\n", choices));
+        sysfs
+    }
+
+    #[test]
+    fn maps_ideapad_choices() {
+        let sysfs = fixture("balanced", "low-power balanced performance");
+        let pp = PlatformProfile::new(sysfs.root());
+        assert_eq!(pp.read().unwrap(), PowerProfile::Balanced);
+        assert_eq!(pp.choices().unwrap(), vec![PowerProfile::Quiet, PowerProfile::Balanced, PowerProfile::Performance]);
+    }
+
+    #[test]
+    fn offers_extended_legion_choices_in_order() {
+        let sysfs = fixture("custom", "custom performance balanced-performance balanced low-power");
+        let pp = PlatformProfile::new(sysfs.root());
+        assert_eq!(pp.read().unwrap(), PowerProfile::Custom);
+        assert_eq!(pp.choices().unwrap(), vec![
+            PowerProfile::Quiet,
+            PowerProfile::Balanced,
+            PowerProfile::BalancedPerformance,
+            PowerProfile::Performance,
+            PowerProfile::Custom,
+        ]);
+    }
+
+    #[test]
+    fn writes_kernel_name_from_choices() {
+        let sysfs = fixture("balanced", "quiet balanced performance");
+        let pp = PlatformProfile::new(sysfs.root());
+        pp.write(PowerProfile::Quiet).unwrap();
+        assert_eq!(sysfs.read(PROFILE), "quiet");
+    }
+
+    #[test]
+    fn refuses_profile_not_in_choices() {
+        let sysfs = fixture("balanced", "low-power balanced performance");
+        let pp = PlatformProfile::new(sysfs.root());
+        assert!(matches!(pp.write(PowerProfile::Custom), Err(HardwareError::Unsupported(_))));
+        assert_eq!(sysfs.read(PROFILE), "balanced\n");
+    }
+
+    #[test]
+    fn unknown_kernel_name_reads_as_unknown() {
+        let sysfs = fixture("cool", "cool balanced");
+        let pp = PlatformProfile::new(sysfs.root());
+        assert_eq!(pp.read().unwrap(), PowerProfile::Unknown);
+        assert_eq!(pp.choices().unwrap(), vec![PowerProfile::Balanced]);
+    }
+}
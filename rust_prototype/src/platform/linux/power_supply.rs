@@ -0,0 +1,145 @@
+use super::read_attr;
+use crate::legion_core::hw::battery::{BatteryStatus, ChargingState};
+use crate::legion_core::result::{HardwareError, HardwareResult};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const POWER_SUPPLY_DIR: &str = "class/power_supply";
+
+/// Reads the first `BAT*` supply and any `AC*`/`ADP*` adapter under `class/power_supply`.
+pub fn read_battery(sysfs_root: &Path) -> HardwareResult<BatteryStatus> {
+    let dir = sysfs_root.join(POWER_SUPPLY_DIR);
+    let supplies = supplies(&dir);
+
+    let battery = supplies.iter()
+        .find(|(name, _)| name.starts_with("BAT"))
+        .map(|(_, path)| path)
+        .ok_or_else(|| HardwareError::Unavailable(format!("No BAT* power supply under {}", dir.display())))?;
+
+    let ac_online = supplies.iter()
+        .filter(|(name, _)| name.starts_with("AC") || name.starts_with("ADP"))
+        .filter_map(|(_, path)| read_attr(&path.join("online")).ok())
+        .map(|online| online == "1")
+        .reduce(|a, b| a || b);
+
+    let state = match read_attr(&battery.join("status")).as_deref() {
+        Ok("Charging") => ChargingState::Charging,
+        Ok("Discharging") => ChargingState::Discharging,
+        Ok("Full") => ChargingState::Full,
+        Ok("Not charging") => ChargingState::NotCharging,
+        _ => ChargingState::Unknown,
+    };
+
+    // Voltages, energies and powers are reported in micro-units.
+    let micro = |attr: &str| read_number(&battery.join(attr)).map(|v| v / 1_000_000.0);
+    let voltage_v = micro("voltage_now");
+
+    // Some packs report charge (µAh) + current (µA) instead of energy (µWh) + power (µW).
+    let design_voltage_v = micro("voltage_min_design").or(voltage_v);
+    let energy = |energy_attr: &str, charge_attr: &str| {
+        micro(energy_attr).or_else(|| Some(micro(charge_attr)? * design_voltage_v?))
+    };
+    let energy_now_wh = energy("energy_now", "charge_now");
+    let energy_full_wh = energy("energy_full", "charge_full");
+    let energy_design_wh = energy("energy_full_design", "charge_full_design");
+    let power_w = micro("power_now")
+        .or_else(|| Some(micro("current_now")? * voltage_v?))
+        .map(f32::abs);
+
+    let charge_percent = match read_number(&battery.join("capacity")) {
+        Some(capacity) => capacity.clamp(0.0, 100.0) as u16,
+        None => match (energy_now_wh, energy_full_wh) {
+            (Some(now), Some(full)) if full > 0.0 => (now / full * 100.0).round().clamp(0.0, 100.0) as u16,
+            _ => return Err(HardwareError::InvalidData(format!("{} reports neither capacity nor energy", battery.display()))),
+        },
+    };
+
+    Ok(BatteryStatus {
+        charge_percent,
+        state,
+        ac_online,
+        energy_now_wh,
+        energy_full_wh,
+        energy_design_wh,
+        voltage_v,
+        power_w,
+        cycle_count: read_number(&battery.join("cycle_count")).map(|c| c as u32),
+    })
+}
+
+fn read_number(path: &Path) -> Option<f32> {
+    read_attr(path).ok()?.parse().ok()
+}
+
+/// `(name, path)` of every supply, sorted by name so BAT0 wins over BAT1.
+fn supplies(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut supplies: Vec<(String, PathBuf)> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| (entry.file_name().to_string_lossy().to_string(), entry.path()))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    supplies.sort();
+    supplies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::linux::fixture::FakeSysfs;
+
+    #[test]
+    fn reads_energy_based_battery() {
+        let sysfs = FakeSysfs::new();
+        sysfs.write("class/power_supply/ADP0/online", "1\n");
+        sysfs.write("class/power_supply/BAT0/status", "Not charging\n");
+        sysfs.write("class/power_supply/BAT0/capacity", "60\n");
+        sysfs.write("class/power_supply/BAT0/energy_now", "34200000\n");
+        sysfs.write("class/power_supply/BAT0/energy_full", "57000000\n");
+        sysfs.write("class/power_supply/BAT0/energy_full_design", "60000000\n");
+        sysfs.write("class/power_supply/BAT0/voltage_now", "16100000\n");
+        sysfs.write("class/power_supply/BAT0/power_now", "0\n");
+        sysfs.write("class/power_supply/BAT0/cycle_count", "42\n");
+
+        let bat = read_battery(sysfs.root()).unwrap();
+        assert_eq!(bat.charge_percent, 60);
+        assert_eq!(bat.state, ChargingState::NotCharging);
+        assert_eq!(bat.ac_online, Some(true));
+        assert_eq!(bat.energy_now_wh, Some(34.2));
+        assert_eq!(bat.energy_full_wh, Some(57.0));
+        assert_eq!(bat.health_percent(), Some(95.0));
+        assert_eq!(bat.voltage_v, Some(16.1));
+        assert_eq!(bat.power_w, Some(0.0));
+        assert_eq!(bat.cycle_count, Some(42));
+    }
+
+    #[test]
+    fn derives_energy_and_power_from_charge_and_current() {
+        let sysfs = FakeSysfs::new();
+        sysfs.write("class/power_supply/AC/online", "0\n");
+        sysfs.write("class/power_supply/BAT1/status", "Discharging\n");
+        sysfs.write("class/power_supply/BAT1/charge_now", "2000000\n");
+        sysfs.write("class/power_supply/BAT1/charge_full", "4000000\n");
+        sysfs.write("class/power_supply/BAT1/voltage_min_design", "15000000\n");
+        sysfs.write("class/power_supply/BAT1/voltage_now", "15000000\n");
+        sysfs.write("class/power_supply/BAT1/current_now", "1000000\n");
+
+        let bat = read_battery(sysfs.root()).unwrap();
+        assert_eq!(bat.state, ChargingState::Discharging);
+        assert_eq!(bat.ac_online, Some(false));
+        assert_eq!(bat.energy_now_wh, Some(30.0));
+        assert_eq!(bat.energy_full_wh, Some(60.0));
+        assert_eq!(bat.charge_percent, 50);
+        assert_eq!(bat.power_w, Some(15.0));
+        assert_eq!(bat.energy_design_wh, None);
+        assert_eq!(bat.cycle_count, None);
+    }
+
+    #[test]
+    fn missing_battery_is_an_error() {
+        let sysfs = FakeSysfs::new();
+        sysfs.write("class/power_supply/AC/online", "1\n");
+        assert!(matches!(read_battery(sysfs.root()), Err(HardwareError::Unavailable(_))));
+    }
+}
@@ -0,0 +1,197 @@
+use super::read_attr;
+use crate::legion_core::hw::thermal::{SensorKind, TemperatureReading};
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const HWMON_DIR: &str = "class/hwmon";
+const THERMAL_DIR: &str = "class/thermal";
+
+/// Discovers every readable temperature under `class/hwmon` and `class/thermal`.
+///
+/// hwmon chips come first so callers that take the first CPU/GPU match get the
+/// driver-specific sensor rather than a generic ACPI zone.
+pub fn discover(sysfs_root: &Path) -> Vec<TemperatureReading> {
+    let mut readings = Vec::new();
+
+    for chip_dir in sorted_entries(&sysfs_root.join(HWMON_DIR), "hwmon") {
+        let chip = match read_attr(&chip_dir.join("name")) {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        for input in sorted_temp_inputs(&chip_dir) {
+            let index = input.trim_start_matches("temp").trim_end_matches("_input").to_string();
+            let label = read_attr(&chip_dir.join(format!("temp{}_label", index)))
+                .unwrap_or_else(|_| format!("temp{}", index));
+            if let Some(celsius) = read_millidegrees(&chip_dir.join(&input)) {
+                readings.push(TemperatureReading {
+                    kind: classify_hwmon(&chip, &label),
+                    source: chip.clone(),
+                    label,
+                    celsius,
+                });
+            }
+        }
+    }
+
+    for zone_dir in sorted_entries(&sysfs_root.join(THERMAL_DIR), "thermal_zone") {
+        let zone_type = match read_attr(&zone_dir.join("type")) {
+            Ok(t) => t,
+            Err(_) => continue,
+        };
+        if let Some(celsius) = read_millidegrees(&zone_dir.join("temp")) {
+            readings.push(TemperatureReading {
+                kind: classify_thermal_zone(&zone_type),
+                source: zone_type.clone(),
+                label: zone_type,
+                celsius,
+            });
+        }
+    }
+
+    readings
+}
+
+/// Maps a hwmon chip name + sensor label to what it measures.
+fn classify_hwmon(chip: &str, label: &str) -> SensorKind {
+    let label_lower = label.to_lowercase();
+    match chip {
+        "coretemp" if label_lower.starts_with("package") => SensorKind::CpuPackage,
+        "coretemp" => SensorKind::CpuCore,
+        // Tctl/Tdie are package-level; TccdN are per-CCD.
+        "k10temp" | "zenpower" if label_lower.starts_with("tccd") => SensorKind::CpuCore,
+        "k10temp" | "zenpower" => SensorKind::CpuPackage,
+        "amdgpu" | "nouveau" | "radeon" => SensorKind::Gpu,
+        "nvme" | "drivetemp" => SensorKind::Ssd,
+        "legion_hwmon" if label_lower.contains("cpu") => SensorKind::CpuPackage,
+        "legion_hwmon" if label_lower.contains("gpu") => SensorKind::Gpu,
+        "legion_hwmon" => SensorKind::Ambient,
+        "acpitz" => SensorKind::Ambient,
+        _ => SensorKind::Other,
+    }
+}
+
+fn classify_thermal_zone(zone_type: &str) -> SensorKind {
+    match zone_type {
+        "x86_pkg_temp" | "TCPU" => SensorKind::CpuPackage,
+        "acpitz" => SensorKind::Ambient,
+        t if t.starts_with("SEN") => SensorKind::Ambient,
+        _ => SensorKind::Other,
+    }
+}
+
+/// Reads a millidegree attribute; unreadable or absurd values are skipped.
+fn read_millidegrees(path: &Path) -> Option<f32> {
+    let raw = read_attr(path).ok()?;
+    match raw.parse::<i64>() {
+        // Disconnected sensors report 0 or negative values; >150°C is never real.
+        Ok(m) if m > 0 && m <= 150_000 => Some(m as f32 / 1000.0),
+        _ => {
+            debug!("Skipping {} (value '{}')", path.display(), raw);
+            None
+        }
+    }
+}
+
+/// Child directories of `dir` whose name starts with `prefix`, in numeric order.
+fn sorted_entries(dir: &Path, prefix: &str) -> Vec<PathBuf> {
+    let mut entries: Vec<(u32, PathBuf)> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let index = name.strip_prefix(prefix)?.parse().ok()?;
+                Some((index, entry.path()))
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    entries.sort();
+    entries.into_iter().map(|(_, path)| path).collect()
+}
+
+/// `tempN_input` file names in a hwmon chip directory, in numeric order.
+fn sorted_temp_inputs(chip_dir: &Path) -> Vec<String> {
+    let mut inputs: Vec<(u32, String)> = match fs::read_dir(chip_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let index = name.strip_prefix("temp")?.strip_suffix("_input")?.parse().ok()?;
+                Some((index, name))
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    inputs.sort();
+    inputs.into_iter().map(|(_, name)| name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::linux::fixture::FakeSysfs;
+
+    /// A LOQ 15IRX9 (Intel + NVIDIA via nouveau) with an NVMe drive and legion-laptop loaded.
+    fn loq_fixture() -> FakeSysfs {
+        let sysfs = FakeSysfs::new();
+        sysfs.write("class/hwmon/hwmon0/name", "acpitz\n");
+        sysfs.write("class/hwmon/hwmon0/temp1_input", "40000\n");
+        sysfs.write("class/hwmon/hwmon2/name", "coretemp\n");
+        sysfs.write("class/hwmon/hwmon2/temp1_label", "Package id 0\n");
+        sysfs.write("class/hwmon/hwmon2/temp1_input", "61000\n");
+        sysfs.write("class/hwmon/hwmon2/temp2_label", "Core 0\n");
+        sysfs.write("class/hwmon/hwmon2/temp2_input", "58000\n");
+        sysfs.write("class/hwmon/hwmon10/name", "nouveau\n");
+        sysfs.write("class/hwmon/hwmon10/temp1_input", "47500\n");
+        sysfs.write("class/hwmon/hwmon3/name", "nvme\n");
+        sysfs.write("class/hwmon/hwmon3/temp1_label", "Composite\n");
+        sysfs.write("class/hwmon/hwmon3/temp1_input", "38850\n");
+        sysfs.write("class/hwmon/hwmon4/name", "legion_hwmon\n");
+        sysfs.write("class/hwmon/hwmon4/temp1_label", "CPU Temperature\n");
+        sysfs.write("class/hwmon/hwmon4/temp1_input", "62000\n");
+        sysfs.write("class/hwmon/hwmon4/temp3_label", "IC Temperature\n");
+        sysfs.write("class/hwmon/hwmon4/temp3_input", "44000\n");
+        sysfs.write("class/thermal/thermal_zone0/type", "x86_pkg_temp\n");
+        sysfs.write("class/thermal/thermal_zone0/temp", "61000\n");
+        sysfs.write("class/thermal/thermal_zone1/type", "iwlwifi_1\n");
+        sysfs.write("class/thermal/thermal_zone1/temp", "-274000\n");
+        sysfs
+    }
+
+    #[test]
+    fn discovers_and_classifies_loq_sensors() {
+        let sysfs = loq_fixture();
+        let readings = discover(sysfs.root());
+        let summary: Vec<(SensorKind, &str, &str, f32)> = readings.iter()
+            .map(|r| (r.kind, r.source.as_str(), r.label.as_str(), r.celsius))
+            .collect();
+
+        assert_eq!(summary, vec![
+            (SensorKind::Ambient, "acpitz", "temp1", 40.0),
+            (SensorKind::CpuPackage, "coretemp", "Package id 0", 61.0),
+            (SensorKind::CpuCore, "coretemp", "Core 0", 58.0),
+            (SensorKind::Ssd, "nvme", "Composite", 38.85),
+            (SensorKind::CpuPackage, "legion_hwmon", "CPU Temperature", 62.0),
+            (SensorKind::Ambient, "legion_hwmon", "IC Temperature", 44.0),
+            (SensorKind::Gpu, "nouveau", "temp1", 47.5),
+            (SensorKind::CpuPackage, "x86_pkg_temp", "x86_pkg_temp", 61.0),
+        ]);
+    }
+
+    #[test]
+    fn classifies_amd_sensors() {
+        assert_eq!(classify_hwmon("k10temp", "Tctl"), SensorKind::CpuPackage);
+        assert_eq!(classify_hwmon("k10temp", "Tccd1"), SensorKind::CpuCore);
+        assert_eq!(classify_hwmon("amdgpu", "edge"), SensorKind::Gpu);
+        assert_eq!(classify_hwmon("amdgpu", "junction"), SensorKind::Gpu);
+        assert_eq!(classify_hwmon("legion_hwmon", "GPU Temperature"), SensorKind::Gpu);
+        assert_eq!(classify_hwmon("iwlwifi_1", "temp1"), SensorKind::Other);
+    }
+
+    #[test]
+    fn missing_sysfs_yields_no_readings() {
+        let sysfs = FakeSysfs::new();
+        assert!(discover(sysfs.root()).is_empty());
+    }
+}
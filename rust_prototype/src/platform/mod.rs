@@ -1 +1,6 @@
+#[cfg(any(windows, target_os = "linux"))]
+pub mod hid;
+#[cfg(target_os = "linux")]
+pub mod linux;
+#[cfg(windows)]
 pub mod windows;
@@ -1,6 +1,18 @@
 use wmi::{COMLibrary, WMIConnection, Variant};
 use serde::Deserialize;
 use std::error::Error;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+use crate::legion_core::backend::HardwareBackend;
+use crate::legion_core::device::models::DeviceIdentity;
+use crate::legion_core::hw::battery::{BatteryStatus, ChargeMode, ChargingState};
+use crate::legion_core::hw::gpu::IgpuMode;
+use crate::legion_core::hw::hid_transport::HidTransport;
+use crate::legion_core::hw::ite_device::{self, HidCollection};
+use crate::legion_core::hw::power::PowerProfile;
+use crate::legion_core::result::{HardwareError, HardwareResult};
+use super::hid::HidApiTransport;
 
 pub struct WmiQueryHandler {
     con: WMIConnection,
@@ -112,17 +124,31 @@ impl WmiQueryHandler {
     }
 
     pub fn get_thermal_mode(&self) -> Result<u32, Box<dyn Error>> {
+        self.get_gamezone_value("GetSmartFanMode")
+    }
+
+    pub fn get_od_status(&self) -> Result<u32, Box<dyn Error>> {
+        self.get_gamezone_value("GetODStatus")
+    }
+
+    pub fn get_igpu_mode_status(&self) -> Result<u32, Box<dyn Error>> {
+        self.get_gamezone_value("GetIGPUModeStatus")
+    }
+
+    /// Calls a parameterless LENOVO_GAMEZONE_DATA getter and returns its `Data` value.
+    fn get_gamezone_value(&self, method: &str) -> Result<u32, Box<dyn Error>> {
         use std::process::Command;
         
         // (Get-WmiObject -Namespace root/WMI -Class LENOVO_GAMEZONE_DATA).GetSmartFanMode().Data
-        let ps_script = "(Get-WmiObject -Namespace root\\WMI -Class LENOVO_GAMEZONE_DATA).GetSmartFanMode().Data";
+        let ps_script = format!("(Get-WmiObject -Namespace root\\WMI -Class LENOVO_GAMEZONE_DATA).{}().Data", method);
         
         let output = Command::new("powershell")
-            .args(&["-NoProfile", "-Command", ps_script])
+            .args(&["-NoProfile", "-Command", &ps_script])
             .output()?;
             
         if !output.status.success() {
-            return Err("Failed to read thermal mode".into());
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("{} failed: {}", method, stderr.trim()).into());
         }
         
         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -131,7 +157,7 @@ impl WmiQueryHandler {
         if let Ok(val) = trimmed.parse::<u32>() {
             Ok(val)
         } else {
-            Err(format!("Invalid integer from WMI: {}", trimmed).into())
+            Err(Box::new(HardwareError::InvalidData(format!("Invalid integer from {}: {}", method, trimmed))))
         }
     }
 
@@ -158,22 +184,15 @@ impl WmiQueryHandler {
         Ok(())
     }
     
-    pub fn get_manufacturer(&self) -> Result<String, Box<dyn Error>> {
+    /// Vendor, Name (machine type) and Version (marketing name) from Win32_ComputerSystemProduct.
+    /// IdentifyingNumber (the serial) is deliberately never queried.
+    pub fn get_system_product(&self) -> Result<(String, String, String), Box<dyn Error>> {
         // Use Win32_ComputerSystemProduct (matching LenovoLegionToolkit)
         let results: Vec<Win32ComputerSystemProduct> = self.con.raw_query("SELECT Vendor, Name, Version FROM Win32_ComputerSystemProduct")?;
         if let Some(sys) = results.first() {
-            Ok(sys.vendor.clone())
+            Ok((sys.vendor.clone(), sys.name.clone(), sys.version.clone()))
         } else {
-            Err("Could not retrieve Vendor".into())
-        }
-    }
-
-    pub fn get_model(&self) -> Result<String, Box<dyn Error>> {
-        let results: Vec<Win32ComputerSystemProduct> = self.con.raw_query("SELECT Vendor, Name, Version FROM Win32_ComputerSystemProduct")?;
-        if let Some(sys) = results.first() {
-            Ok(sys.name.clone())
-        } else {
-            Err("Could not retrieve Name".into())
+            Err("Could not retrieve Win32_ComputerSystemProduct".into())
         }
     }
 
@@ -197,21 +216,37 @@ impl WmiQueryHandler {
 }
 
 
+/// IOCTL_ENERGY_BATTERY_CHARGE_MODE; the input selector picks the operation.
+const CHARGE_MODE_IOCTL: u32 = 0x831020F8;
+/// Selector that turns `CHARGE_MODE_IOCTL` into a read of the `chargeModeBits`.
+const CHARGE_MODE_READ_SELECTOR: u32 = 0xFF;
+
 pub struct EnergyDriver {
     handle: windows::Win32::Foundation::HANDLE,
 }
 
 impl EnergyDriver {
     pub fn new() -> Result<Self, Box<dyn Error>> {
+        use windows::Win32::Foundation::{GENERIC_READ, GENERIC_WRITE};
+        Self::open(GENERIC_READ.0 | GENERIC_WRITE.0)
+    }
+
+    /// Opens with zero requested access: enough for the charge-mode read, and
+    /// SECURITY.md limits that read to such a handle.
+    pub fn query() -> Result<Self, Box<dyn Error>> {
+        Self::open(0)
+    }
+
+    fn open(desired_access: u32) -> Result<Self, Box<dyn Error>> {
         use windows::Win32::Storage::FileSystem::{CreateFileA, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING, FILE_ATTRIBUTE_NORMAL};
-        use windows::Win32::Foundation::{GENERIC_READ, GENERIC_WRITE, INVALID_HANDLE_VALUE};
+        use windows::Win32::Foundation::INVALID_HANDLE_VALUE;
         
         let path = std::ffi::CString::new(r"\\.\EnergyDrv")?;
         
         let handle = unsafe {
             CreateFileA(
                 windows::core::PCSTR(path.as_ptr() as *const _),
-                GENERIC_READ.0 | GENERIC_WRITE.0,
+                desired_access,
                 FILE_SHARE_READ | FILE_SHARE_WRITE,
                 None,
                 OPEN_EXISTING,
@@ -227,27 +262,52 @@ impl EnergyDriver {
         Ok(Self { handle })
     }
 
+    /// One `ChargeModeStep`: selector 0x3 (on) / 0x5 (off). Sequencing between
+    /// conservation and rapid charge is done by `battery::change_charge_mode`.
     pub fn set_conservation_mode(&self, enable: bool) -> Result<(), Box<dyn Error>> {
-        // IOCTL_ENERGY_BATTERY_CHARGE_MODE = 0x831020F8
-        // 3 = Conservation, 5 = Disable Conservation (Normal?), 8 = Normal?
-        // Logic from LLT: 
-        // To Conservation: [0x3]
-        // To Normal (from Conservation): [0x5]
-        // We will assume [0x3] enables conservation, [0x5] disables it (returns to Normal/Rapid).
-        
         let code: u32 = if enable { 0x3 } else { 0x5 };
-        self.send_command(0x831020F8, code)
+        self.send_command(CHARGE_MODE_IOCTL, code)
     }
 
+    /// One `ChargeModeStep`: selector 0x7 (on) / 0x8 (off).
     pub fn set_rapid_charge(&self, enable: bool) -> Result<(), Box<dyn Error>> {
-        // IOCTL_ENERGY_BATTERY_CHARGE_MODE = 0x831020F8
-        // 7 = Rapid, 8 = Disable Rapid (Normal?)
-        // Logic from LLT:
-        // To Rapid: [0x7]
-        // To Normal (from Rapid): [0x8]
-        
         let code: u32 = if enable { 0x7 } else { 0x8 };
-        self.send_command(0x831020F8, code)
+        self.send_command(CHARGE_MODE_IOCTL, code)
+    }
+
+    /// Reads the raw `chargeModeBits`; decode with `ChargeMode::from_energy_bits`.
+    pub fn read_charge_mode_bits(&self) -> HardwareResult<u32> {
+        use windows::Win32::System::IO::DeviceIoControl;
+        use windows::Win32::Foundation::{ERROR_INVALID_FUNCTION, ERROR_NOT_SUPPORTED};
+        use std::ffi::c_void;
+
+        let mut in_buffer = CHARGE_MODE_READ_SELECTOR;
+        let mut out_buffer: u32 = 0;
+        let mut bytes_returned: u32 = 0;
+
+        unsafe {
+            DeviceIoControl(
+                self.handle,
+                CHARGE_MODE_IOCTL,
+                Some(&mut in_buffer as *mut _ as *mut c_void),
+                std::mem::size_of::<u32>() as u32,
+                Some(&mut out_buffer as *mut _ as *mut c_void),
+                std::mem::size_of::<u32>() as u32,
+                Some(&mut bytes_returned),
+                None
+            )
+        }.map_err(|e| {
+            if e.code() == ERROR_INVALID_FUNCTION.to_hresult() || e.code() == ERROR_NOT_SUPPORTED.to_hresult() {
+                HardwareError::Unsupported(format!("EnergyDrv charge-mode read: {} (energy_battery_getter_not_supported)", e))
+            } else {
+                HardwareError::from(Box::new(e) as Box<dyn Error>)
+            }
+        })?;
+
+        if bytes_returned != std::mem::size_of::<u32>() as u32 {
+            return Err(HardwareError::InvalidData(format!("EnergyDrv returned {} bytes for the charge-mode read, expected 4", bytes_returned)));
+        }
+        Ok(out_buffer)
     }
     
     // Helper for DeviceIoControl
@@ -282,3 +342,149 @@ impl Drop for EnergyDriver {
         unsafe { let _ = CloseHandle(self.handle); };
     }
 }
+
+/// WMI + EnergyDrv + ITE HID implementation of `HardwareBackend`.
+///
+/// Stateless: COM must be initialized on the calling thread, so a fresh
+/// `WmiQueryHandler` / `EnergyDriver` is opened for every call.
+pub struct WindowsBackend;
+
+impl HardwareBackend for WindowsBackend {
+    fn name(&self) -> &'static str {
+        "windows"
+    }
+
+    fn identity(&self) -> HardwareResult<DeviceIdentity> {
+        let wmi = WmiQueryHandler::new()?;
+        let (manufacturer, product_name, product_version) = wmi.get_system_product()?;
+        Ok(DeviceIdentity {
+            manufacturer,
+            product_name,
+            product_version,
+            product_family: None,
+            bios_version: wmi.get_bios_version()?,
+        })
+    }
+
+    fn battery_status(&self) -> HardwareResult<BatteryStatus> {
+        let (charge, status) = WmiQueryHandler::new()?.get_battery_info()?;
+        // Win32_Battery only reports percent and BatteryStatus; energy, voltage,
+        // power and cycle count stay unavailable.
+        let (state, ac_online) = match status {
+            1 | 4 | 5 => (ChargingState::Discharging, Some(false)),
+            2 => (ChargingState::Unknown, Some(true)), // AC Power (Charging or Charged)
+            3 => (ChargingState::Full, Some(true)),
+            6..=9 => (ChargingState::Charging, Some(true)),
+            _ => (ChargingState::Unknown, None),
+        };
+        Ok(BatteryStatus {
+            charge_percent: charge,
+            state,
+            ac_online,
+            ..Default::default()
+        })
+    }
+
+    fn conservation_mode(&self) -> HardwareResult<bool> {
+        Ok(self.charge_mode()? == ChargeMode::Conservation)
+    }
+
+    fn set_conservation_mode(&self, enable: bool) -> HardwareResult<()> {
+        Ok(energy_driver()?.set_conservation_mode(enable)?)
+    }
+
+    fn rapid_charge(&self) -> HardwareResult<bool> {
+        Ok(self.charge_mode()? == ChargeMode::Rapid)
+    }
+
+    fn set_rapid_charge(&self, enable: bool) -> HardwareResult<()> {
+        Ok(energy_driver()?.set_rapid_charge(enable)?)
+    }
+
+    fn charge_mode(&self) -> HardwareResult<ChargeMode> {
+        ChargeMode::from_energy_bits(energy_charge_mode_bits()?)
+    }
+
+    fn power_profile(&self) -> HardwareResult<PowerProfile> {
+        Ok(PowerProfile::from_smart_fan_mode(WmiQueryHandler::new()?.get_thermal_mode()?))
+    }
+
+    fn set_power_profile(&self, profile: PowerProfile) -> HardwareResult<()> {
+        let mode = profile.to_smart_fan_mode()
+            .ok_or_else(|| HardwareError::Unsupported(format!("{} has no SmartFanMode value", profile)))?;
+        Ok(WmiQueryHandler::new()?.set_thermal_mode(mode)?)
+    }
+
+    fn power_profile_choices(&self) -> HardwareResult<Vec<PowerProfile>> {
+        // SmartFanMode has no capability query; these three exist on every Legion/LOQ.
+        Ok(vec![PowerProfile::Quiet, PowerProfile::Balanced, PowerProfile::Performance])
+    }
+
+    fn overdrive(&self) -> HardwareResult<bool> {
+        match WmiQueryHandler::new()?.get_od_status()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            raw => Err(HardwareError::InvalidData(format!("Unknown GetODStatus value {}", raw))),
+        }
+    }
+
+    fn igpu_mode(&self) -> HardwareResult<IgpuMode> {
+        let raw = WmiQueryHandler::new()?.get_igpu_mode_status()?;
+        IgpuMode::from_raw(raw).ok_or_else(|| HardwareError::InvalidData(format!("Unknown GetIGPUModeStatus value {}", raw)))
+    }
+
+    fn set_light_control_owner(&self, app_control: bool) -> HardwareResult<()> {
+        Ok(WmiQueryHandler::new()?.set_light_control_owner(app_control)?)
+    }
+
+    fn send_lighting_report(&self, report: &[u8]) -> HardwareResult<()> {
+        ite_device::send_report(&HidApiTransport, report)
+    }
+
+    fn lighting_report(&self) -> HardwareResult<Vec<u8>> {
+        ite_device::read_report(&HidApiTransport)
+    }
+
+    fn lighting_collections(&self) -> HardwareResult<Vec<HidCollection>> {
+        HidApiTransport.enumerate(ite_device::VENDOR_ID)
+    }
+}
+
+/// Opens EnergyDrv for writing; a missing driver is `Unavailable` rather than a generic failure.
+fn energy_driver() -> HardwareResult<EnergyDriver> {
+    EnergyDriver::new().map_err(energy_open_error)
+}
+
+/// Opens EnergyDrv with zero access for the charge-mode read.
+fn energy_query() -> HardwareResult<EnergyDriver> {
+    EnergyDriver::query().map_err(energy_open_error)
+}
+
+/// How long the EnergyDrv charge-mode read may block, as in the C# reader.
+const CHARGE_MODE_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs the blocking charge-mode IOCTL on a worker and waits at most
+/// `CHARGE_MODE_READ_TIMEOUT`. A read that never returns leaves only its worker blocked.
+fn energy_charge_mode_bits() -> HardwareResult<u32> {
+    let (tx, rx) = mpsc::channel();
+    thread::Builder::new()
+        .name("energydrv-read".to_string())
+        .spawn(move || {
+            let _ = tx.send(energy_query().and_then(|driver| driver.read_charge_mode_bits()));
+        })
+        .map_err(|e| HardwareError::Failed(format!("EnergyDrv read worker: {}", e)))?;
+    match rx.recv_timeout(CHARGE_MODE_READ_TIMEOUT) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => Err(HardwareError::TimedOut(format!(
+            "EnergyDrv charge-mode read did not return within {:?} (energy_driver_read_timed_out)", CHARGE_MODE_READ_TIMEOUT))),
+        Err(RecvTimeoutError::Disconnected) => Err(HardwareError::Failed(
+            "EnergyDrv read worker stopped without a result (energy_driver_read_failed)".to_string())),
+    }
+}
+
+fn energy_open_error(e: Box<dyn Error>) -> HardwareError {
+    match HardwareError::from(e) {
+        HardwareError::Failed(detail) => HardwareError::Unavailable(format!("EnergyDrv: {}", detail)),
+        other => other,
+    }
+}